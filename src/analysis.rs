@@ -0,0 +1,998 @@
+use crate::{
+    board::{
+        self,
+        geometry::{BOARD_WIDTH, NUM_SQUARES},
+        position::{Offset, Position},
+        rank, Board, MoveKind, MoveRequest,
+    },
+    game::Game,
+    piece::{Piece, PieceType, PromotionType, Side},
+};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Motif {
+    // Moving `piece` to `destination` attacks every square in `targets` at once.
+    Fork {
+        piece: Position,
+        destination: Position,
+        targets: Vec<Position>,
+    },
+    // `pinned` cannot leave the line between `pinner` and its king.
+    Pin {
+        pinner: Position,
+        pinned: Position,
+        king: Position,
+    },
+    // Moving `piece` to `destination` attacks `front`, exposing `behind` once it moves away.
+    Skewer {
+        piece: Position,
+        destination: Position,
+        front: Position,
+        behind: Position,
+    },
+    // Moving `blocker` off the line opens an attack from `slider` onto `target`.
+    DiscoveredAttack {
+        blocker: Position,
+        slider: Position,
+        target: Position,
+    },
+    // The king is walled in by its own pieces and a rook or queen can reach its back rank.
+    BackRankWeakness {
+        king: Position,
+    },
+}
+
+// Finds simple tactical patterns available to `side` without searching.
+pub fn motifs(board: &Board, side: &Side) -> Vec<Motif> {
+    let mut motifs = Vec::new();
+
+    let legal_moves = sorted_legal_moves(board, side);
+    for (start, end) in &legal_moves {
+        let mut new_board = board.clone_for_simulation();
+        if board::move_piece(&mut new_board, move_request(board, start, end)).is_err() {
+            continue;
+        }
+
+        if let Some(fork) = find_fork(&new_board, side, start, end) {
+            motifs.push(fork);
+        }
+    }
+
+    motifs.extend(find_pins(board, side));
+
+    for (start, end) in &legal_moves {
+        let mut new_board = board.clone_for_simulation();
+        if board::move_piece(&mut new_board, move_request(board, start, end)).is_err() {
+            continue;
+        }
+
+        motifs.extend(find_skewers(&new_board, side, start, end));
+    }
+
+    motifs.extend(find_discovered_attacks(board, side, &legal_moves));
+
+    if let Some(back_rank) = find_back_rank_weakness(board, side, &legal_moves) {
+        motifs.push(back_rank);
+    }
+
+    motifs
+}
+
+// The squares `side`'s king could legally move to right now, sorted from a1 to h8.
+pub fn king_escape_squares(board: &Board, side: &Side) -> Vec<Position> {
+    let Some(king) = board.king_position(side) else {
+        return Vec::new();
+    };
+
+    sorted(
+        board::get_all_legal_moves(board, side)
+            .remove(&king)
+            .map(|moves| moves.into_keys().collect())
+            .unwrap_or_default(),
+    )
+}
+
+// The fraction of the squares around the defending king that `attacker_side` controls, from 0.0
+// for a king with room to breathe to 1.0 for one that is completely boxed in. Squares the
+// defender's own pieces stand on are left out, since they say nothing about the attack.
+pub fn mating_net_progress(board: &Board, attacker_side: &Side) -> f32 {
+    let defender = attacker_side.opponent();
+    let Some(king) = board.king_position(&defender) else {
+        return 0.0;
+    };
+
+    // The king can't shelter behind itself, so sliders see through its square.
+    let mut without_king = board.clone_for_simulation();
+    without_king.take_piece(&king);
+    let attacked = board::get_all_target_positions(&without_king, attacker_side);
+
+    let neighbourhood: Vec<Position> = [Offset::ORTHOGONAL, Offset::DIAGONAL]
+        .concat()
+        .into_iter()
+        .filter_map(|offset| king + offset)
+        .filter(|square| !without_king.is_occupied_by(square, &defender))
+        .collect();
+    // A king smothered by its own pieces is only in a net if it is also in check.
+    if neighbourhood.is_empty() {
+        return if board::is_in_check(board, &defender) {
+            1.0
+        } else {
+            0.0
+        };
+    }
+
+    let controlled = neighbourhood
+        .iter()
+        .filter(|square| {
+            // An attacker's own piece next to the king is controlled if something defends it.
+            if without_king.is_occupied_by(square, attacker_side) {
+                is_defended(&without_king, square)
+            } else {
+                attacked.contains(square)
+            }
+        })
+        .count();
+
+    controlled as f32 / neighbourhood.len() as f32
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HeatmapKind {
+    Occupancy,    // squares holding one of the side's pieces, counted once per position
+    Destinations, // squares the side moved a piece to
+    Captures,     // squares the side captured on
+}
+
+// Per square counts, indexed by Position::value().
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Heatmap([u32; NUM_SQUARES]);
+
+impl Heatmap {
+    pub fn get(&self, position: &Position) -> u32 {
+        self.0[position.value()]
+    }
+
+    pub fn counts(&self) -> &[u32; NUM_SQUARES] {
+        &self.0
+    }
+}
+
+// The counts laid out like the board, with the eighth rank on top.
+impl std::fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.0.iter().max().unwrap_or(&0).to_string().len();
+
+        for rank in (rank::ONE..=rank::EIGHT).rev() {
+            let row: Vec<String> = self.0[rank * BOARD_WIDTH..(rank + 1) * BOARD_WIDTH]
+                .iter()
+                .map(|count| format!("{count:>width$}"))
+                .collect();
+            write!(f, "{}", row.join(" "))?;
+            if rank != rank::ONE {
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Counts over every position and move of the game's main line, regardless of the ply being viewed.
+pub fn square_heatmap(game: &Game, kind: HeatmapKind, side: &Side) -> Heatmap {
+    let mut counts = [0; NUM_SQUARES];
+
+    let mut ply = 0;
+    while let Some(board) = game.board_at(ply) {
+        match kind {
+            HeatmapKind::Occupancy => {
+                for position in side_positions(&board, side) {
+                    counts[position.value()] += 1;
+                }
+            }
+            HeatmapKind::Destinations | HeatmapKind::Captures => {
+                let played = game
+                    .move_at(ply + 1)
+                    .filter(|_| board.side_to_move() == *side);
+                if let Some(request) = played {
+                    let mut after = board.clone_for_simulation();
+                    let is_capture = board::move_piece(&mut after, request.clone())
+                        .is_ok_and(|move_info| move_info.is_capture);
+
+                    if kind == HeatmapKind::Destinations || is_capture {
+                        counts[request.end.value()] += 1;
+                    }
+                }
+            }
+        }
+
+        ply += 1;
+    }
+
+    Heatmap(counts)
+}
+
+// White attackers minus black attackers for every square, indexed by Position::value().
+// Positive squares are controlled by white, negative ones by black.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ControlMap([i8; NUM_SQUARES]);
+
+impl ControlMap {
+    pub fn get(&self, position: &Position) -> i8 {
+        self.0[position.value()]
+    }
+
+    pub fn values(&self) -> &[i8; NUM_SQUARES] {
+        &self.0
+    }
+}
+
+// The signed values laid out like the board, with the eighth rank on top.
+impl std::fmt::Display for ControlMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for rank in (rank::ONE..=rank::EIGHT).rev() {
+            let row: Vec<String> = self.0[rank * BOARD_WIDTH..(rank + 1) * BOARD_WIDTH]
+                .iter()
+                .map(|value| format!("{value:>2}"))
+                .collect();
+            write!(f, "{}", row.join(" "))?;
+            if rank != rank::ONE {
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Counts every attack on a square whatever stands on it, so defending a piece counts as
+// controlling its square.
+pub fn control_map(board: &Board) -> ControlMap {
+    let mut values = [0; NUM_SQUARES];
+    for position in (0..NUM_SQUARES).filter_map(Position::from_index) {
+        let white = board::attackers_of(board, &position, &Side::White, None).len();
+        let black = board::attackers_of(board, &position, &Side::Black, None).len();
+        values[position.value()] = white as i8 - black as i8;
+    }
+
+    ControlMap(values)
+}
+
+// The number of squares in the opponent's half that white and black each control.
+pub fn space_advantage(board: &Board) -> (u32, u32) {
+    let control = control_map(board);
+
+    let mut white = 0;
+    let mut black = 0;
+    for position in (0..NUM_SQUARES).filter_map(Position::from_index) {
+        let value = control.get(&position);
+        if value > 0 && position.rank() >= rank::FIVE {
+            white += 1;
+        } else if value < 0 && position.rank() <= rank::FOUR {
+            black += 1;
+        }
+    }
+
+    (white, black)
+}
+
+// The material difference between the sides, with white ahead when counts are positive.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct MaterialImbalance {
+    pub exchanges: i32,             // rooks held against minor pieces
+    pub minor_for_pawns: i32,       // minor pieces held against pawns, once exchanges are counted
+    pub raw: Vec<(PieceType, i32)>, // white count minus black count, for each piece type that differs
+}
+
+const IMBALANCE_PIECE_TYPES: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+impl MaterialImbalance {
+    fn difference(&self, piece_types: &[PieceType]) -> i32 {
+        self.raw
+            .iter()
+            .filter(|(piece_type, _)| piece_types.contains(piece_type))
+            .map(|(_, difference)| difference)
+            .sum()
+    }
+
+    // The difference in piece values, positive when white has more.
+    pub fn value(&self) -> i32 {
+        self.raw
+            .iter()
+            .map(|(piece_type, difference)| piece_type.value() * difference)
+            .sum()
+    }
+
+    // Describes the imbalance the way a commentator would, such as "White is up the exchange for a
+    // pawn" or "Black has two minor pieces for a rook".
+    pub fn describe(&self) -> String {
+        if self.raw.is_empty() {
+            return String::from("Material is equal.");
+        }
+
+        let queens = self.difference(&[PieceType::Queen]);
+        let rooks = self.difference(&[PieceType::Rook]);
+        let minors = self.difference(&[PieceType::Bishop, PieceType::Knight]);
+        let pawns = self.difference(&[PieceType::Pawn]);
+
+        for (side, sign) in [("White", 1), ("Black", -1)] {
+            let pawn_suffix = match pawns * sign {
+                0 => String::new(),
+                count if count > 0 => format!(" and {}", count_of("pawn", count)),
+                count => format!(" for {}", count_of("pawn", -count)),
+            };
+
+            let trade = match (queens * sign, rooks * sign, minors * sign) {
+                (1, -1, -1) => Some(format!("{side} has a queen for a rook and a minor piece")),
+                (1, -2, 0) => Some(format!("{side} has a queen for two rooks")),
+                (0, -1, 2) => Some(format!("{side} has two minor pieces for a rook")),
+                (0, 1, -1) => Some(format!("{side} is up the exchange")),
+                (0, 0, 1) if pawns * sign < 0 => {
+                    let pawns = count_of("pawn", -pawns * sign);
+                    return format!("{side} has a piece for {pawns}.");
+                }
+                _ => None,
+            };
+            if let Some(trade) = trade {
+                return format!("{trade}{pawn_suffix}.");
+            }
+        }
+
+        // A single kind of piece more on one side.
+        if let [(piece_type, difference)] = self.raw.as_slice() {
+            let side = if *difference > 0 { "White" } else { "Black" };
+            let pieces = count_of(piece_type.name(), difference.abs());
+            return format!("{side} is up {pieces}.");
+        }
+
+        match self.value() {
+            0 => String::from("Material is equal in value, with different pieces."),
+            value => {
+                let side = if value > 0 { "White" } else { "Black" };
+                let points = value.abs();
+                let plural = if points == 1 { "" } else { "s" };
+                format!("{side} is up {points} point{plural} of material.")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for MaterialImbalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe())
+    }
+}
+
+pub fn imbalance(board: &Board) -> MaterialImbalance {
+    let count = |piece_type: PieceType, side: Side| {
+        side_positions(board, &side)
+            .iter()
+            .filter(|position| board.piece_at(position).unwrap().piece_type == piece_type)
+            .count() as i32
+    };
+
+    let raw: Vec<(PieceType, i32)> = IMBALANCE_PIECE_TYPES
+        .into_iter()
+        .map(|piece_type| {
+            let difference = count(piece_type, Side::White) - count(piece_type, Side::Black);
+            (piece_type, difference)
+        })
+        .filter(|(_, difference)| *difference != 0)
+        .collect();
+
+    let mut imbalance = MaterialImbalance {
+        exchanges: 0,
+        minor_for_pawns: 0,
+        raw,
+    };
+
+    let rooks = imbalance.difference(&[PieceType::Rook]);
+    let minors = imbalance.difference(&[PieceType::Bishop, PieceType::Knight]);
+    let pawns = imbalance.difference(&[PieceType::Pawn]);
+    if rooks.signum() * minors.signum() < 0 {
+        imbalance.exchanges = rooks.signum() * rooks.abs().min(minors.abs());
+    }
+    let remaining_minors = minors + imbalance.exchanges;
+    if remaining_minors.signum() * pawns.signum() < 0 {
+        imbalance.minor_for_pawns = remaining_minors;
+    }
+
+    imbalance
+}
+
+// "a knight", "two pawns" and so on.
+fn count_of(name: &str, count: i32) -> String {
+    const NUMBERS: [&str; 9] = [
+        "no", "a", "two", "three", "four", "five", "six", "seven", "eight",
+    ];
+
+    match NUMBERS.get(count as usize) {
+        Some(&"a") => format!("a {name}"),
+        Some(number) => format!("{number} {name}s"),
+        None => format!("{count} {name}s"),
+    }
+}
+
+fn find_fork(board: &Board, side: &Side, start: &Position, end: &Position) -> Option<Motif> {
+    let piece_type = &board.piece_at(end)?.piece_type;
+
+    let targets: Vec<Position> = attacked_enemy_pieces(board, side, piece_type, end)
+        .into_iter()
+        .filter(|target| {
+            let target_type = &board.piece_at(target).unwrap().piece_type;
+            *target_type == PieceType::King
+                || target_type.value() > piece_type.value()
+                || !is_defended(board, target)
+        })
+        .collect();
+
+    if targets.len() < 2 {
+        return None;
+    }
+
+    Some(Motif::Fork {
+        piece: *start,
+        destination: *end,
+        targets,
+    })
+}
+
+fn find_pins(board: &Board, side: &Side) -> Vec<Motif> {
+    let mut pins = Vec::new();
+
+    for pinner in sorted(side_positions(board, side)) {
+        for offset in slider_offsets(&board.piece_at(&pinner).unwrap().piece_type) {
+            let Some((pinned, behind)) = first_two_pieces_along(board, &pinner, &offset) else {
+                continue;
+            };
+
+            let pinned_piece = board.piece_at(&pinned).unwrap();
+            let behind_piece = board.piece_at(&behind).unwrap();
+            if pinned_piece.side != *side
+                && pinned_piece.piece_type != PieceType::King
+                && *behind_piece == Piece::new(PieceType::King, side.opponent())
+            {
+                pins.push(Motif::Pin {
+                    pinner,
+                    pinned,
+                    king: behind,
+                });
+            }
+        }
+    }
+
+    pins
+}
+
+fn find_skewers(board: &Board, side: &Side, start: &Position, end: &Position) -> Vec<Motif> {
+    let mut skewers = Vec::new();
+
+    let piece_type = &board.piece_at(end).unwrap().piece_type;
+    for offset in slider_offsets(piece_type) {
+        let Some((front, behind)) = first_two_pieces_along(board, end, &offset) else {
+            continue;
+        };
+
+        let front_piece = board.piece_at(&front).unwrap();
+        let behind_piece = board.piece_at(&behind).unwrap();
+        if front_piece.side == *side || behind_piece.side == *side {
+            continue;
+        }
+
+        // The piece in front has to be worth more, otherwise it's just a pin or an attack.
+        let more_valuable_front = front_piece.piece_type == PieceType::King
+            || front_piece.piece_type.value() > behind_piece.piece_type.value();
+        let behind_is_loose =
+            behind_piece.piece_type.value() > piece_type.value() || !is_defended(board, &behind);
+
+        if more_valuable_front && behind_is_loose {
+            skewers.push(Motif::Skewer {
+                piece: *start,
+                destination: *end,
+                front,
+                behind,
+            });
+        }
+    }
+
+    skewers
+}
+
+fn find_discovered_attacks(
+    board: &Board,
+    side: &Side,
+    legal_moves: &[(Position, Position)],
+) -> Vec<Motif> {
+    let mut discovered_attacks = Vec::new();
+
+    for slider in sorted(side_positions(board, side)) {
+        let slider_type = &board.piece_at(&slider).unwrap().piece_type;
+        for offset in slider_offsets(slider_type) {
+            let Some((blocker, target)) = first_two_pieces_along(board, &slider, &offset) else {
+                continue;
+            };
+
+            let target_piece = board.piece_at(&target).unwrap();
+            if board.piece_at(&blocker).unwrap().side != *side || target_piece.side == *side {
+                continue;
+            }
+
+            let worth_attacking = target_piece.piece_type == PieceType::King
+                || target_piece.piece_type.value() > slider_type.value()
+                || !is_defended(board, &target);
+
+            // The blocker has to be able to step off the line for anything to be discovered.
+            let line = squares_between(&slider, &target, &offset);
+            let can_leave_line = legal_moves
+                .iter()
+                .any(|(start, end)| *start == blocker && !line.contains(end));
+
+            if worth_attacking && can_leave_line {
+                discovered_attacks.push(Motif::DiscoveredAttack {
+                    blocker,
+                    slider,
+                    target,
+                });
+            }
+        }
+    }
+
+    discovered_attacks
+}
+
+fn find_back_rank_weakness(
+    board: &Board,
+    side: &Side,
+    legal_moves: &[(Position, Position)],
+) -> Option<Motif> {
+    let opponent = side.opponent();
+    let king = board.king_position(&opponent)?;
+
+    let back_rank = match opponent {
+        Side::White => rank::ONE,
+        Side::Black => rank::EIGHT,
+    };
+    if king.rank() != back_rank {
+        return None;
+    }
+
+    let walled_in = [Offset::NORTH_WEST, Offset::NORTH, Offset::NORTH_EAST]
+        .into_iter()
+        .filter_map(|offset| king + offset.for_side(&opponent))
+        .all(|escape| {
+            board
+                .piece_at(&escape)
+                .is_some_and(|piece| piece.side == opponent)
+        });
+
+    let heavy_piece_reaches_back_rank = legal_moves.iter().any(|(start, end)| {
+        let piece_type = &board.piece_at(start).unwrap().piece_type;
+        matches!(piece_type, PieceType::Rook | PieceType::Queen) && end.rank() == back_rank
+    });
+
+    if walled_in && heavy_piece_reaches_back_rank {
+        Some(Motif::BackRankWeakness { king })
+    } else {
+        None
+    }
+}
+
+// Enemy pieces the given piece would attack from `from`, sorted from a1 to h8.
+fn attacked_enemy_pieces(
+    board: &Board,
+    side: &Side,
+    piece_type: &PieceType,
+    from: &Position,
+) -> Vec<Position> {
+    let attacked: Vec<Position> = match piece_type {
+        PieceType::Pawn => [Offset::NORTH_WEST, Offset::NORTH_EAST]
+            .into_iter()
+            .filter_map(|offset| from + offset.for_side(side))
+            .collect(),
+        _ => board::moves_for_piece_type(board, piece_type, side, from)
+            .into_iter()
+            .filter(|(_, move_kind)| *move_kind == MoveKind::Capture)
+            .map(|(position, _)| position)
+            .collect(),
+    };
+
+    sorted(
+        attacked
+            .into_iter()
+            .filter(|position| {
+                board
+                    .piece_at(position)
+                    .is_some_and(|piece| piece.side != *side)
+            })
+            .collect(),
+    )
+}
+
+// Whether the piece on `position` is protected by another piece of its own side.
+fn is_defended(board: &Board, position: &Position) -> bool {
+    let Some(piece) = board.piece_at(position) else {
+        return false;
+    };
+    let defender = piece.side;
+
+    // Move generation never targets friendly pieces, so swap in an enemy to see who covers it.
+    let mut board = board.clone();
+    board.set_position(
+        position,
+        Some(Piece::new(PieceType::Pawn, defender.opponent())),
+    );
+    board::get_all_target_positions(&board, &defender).contains(position)
+}
+
+fn first_two_pieces_along(
+    board: &Board,
+    from: &Position,
+    offset: &Offset,
+) -> Option<(Position, Position)> {
+    let first = first_piece_along(board, from, offset)?;
+    let second = first_piece_along(board, &first, offset)?;
+    Some((first, second))
+}
+
+fn first_piece_along(board: &Board, from: &Position, offset: &Offset) -> Option<Position> {
+    let mut current = from + *offset;
+    while let Some(position) = current {
+        if board.piece_at(&position).is_some() {
+            return Some(position);
+        }
+        current = position + *offset;
+    }
+
+    None
+}
+
+fn squares_between(from: &Position, to: &Position, offset: &Offset) -> Vec<Position> {
+    let mut squares = Vec::new();
+    let mut current = from + *offset;
+    while let Some(position) = current {
+        if position == *to {
+            break;
+        }
+        current = position + *offset;
+        squares.push(position);
+    }
+
+    squares
+}
+
+fn slider_offsets(piece_type: &PieceType) -> Vec<Offset> {
+    match piece_type {
+        PieceType::Rook => Offset::ORTHOGONAL.to_vec(),
+        PieceType::Bishop => Offset::DIAGONAL.to_vec(),
+        PieceType::Queen => [Offset::ORTHOGONAL, Offset::DIAGONAL].concat(),
+        _ => Vec::new(),
+    }
+}
+
+fn side_positions(board: &Board, side: &Side) -> Vec<Position> {
+    match side {
+        Side::White => board.get_white_positions().iter().collect(),
+        Side::Black => board.get_black_positions().iter().collect(),
+    }
+}
+
+fn sorted_legal_moves(board: &Board, side: &Side) -> Vec<(Position, Position)> {
+    let mut legal_moves: Vec<(Position, Position)> = board::get_all_legal_moves(board, side)
+        .into_iter()
+        .flat_map(|(start, moves)| moves.into_keys().map(move |end| (start, end)))
+        .collect();
+    legal_moves.sort_by_key(|(start, end)| (start.value(), end.value()));
+    legal_moves
+}
+
+fn move_request(board: &Board, start: &Position, end: &Position) -> MoveRequest {
+    let is_promotion = board
+        .piece_at(start)
+        .is_some_and(|piece| piece.piece_type == PieceType::Pawn)
+        && (end.rank() == rank::ONE || end.rank() == rank::EIGHT);
+
+    if is_promotion {
+        MoveRequest::promotion(*start, *end, PromotionType::Queen)
+    } else {
+        MoveRequest::new(*start, *end)
+    }
+}
+
+fn sorted(mut positions: Vec<Position>) -> Vec<Position> {
+    positions.sort_by_key(Position::value);
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen, ParseError};
+
+    #[test]
+    fn knight_fork_test() -> Result<(), ParseError> {
+        let board = fen::parse("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1")?;
+
+        assert_eq!(
+            motifs(&board, &Side::White),
+            vec![Motif::Fork {
+                piece: Position::b5(),
+                destination: Position::c7(),
+                targets: vec![Position::a8(), Position::e8()],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn skewer_test() -> Result<(), ParseError> {
+        let board = fen::parse("r7/8/8/3k4/8/8/8/4K3 w - - 0 1")?;
+        assert!(motifs(&board, &Side::White).is_empty());
+
+        let board = fen::parse("r7/8/8/3k4/8/7B/8/4K3 w - - 0 1")?;
+        assert!(motifs(&board, &Side::White).contains(&Motif::Skewer {
+            piece: Position::h3(),
+            destination: Position::g2(),
+            front: Position::d5(),
+            behind: Position::a8(),
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pin_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/4r3/8/8/8/8/8/4R1K1 w - - 0 1")?;
+
+        assert!(motifs(&board, &Side::White).contains(&Motif::Pin {
+            pinner: Position::e1(),
+            pinned: Position::e7(),
+            king: Position::e8(),
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn discovered_attack_test() -> Result<(), ParseError> {
+        let board = fen::parse("3qk3/8/8/8/8/8/3N4/3RK3 w - - 0 1")?;
+
+        assert!(
+            motifs(&board, &Side::White).contains(&Motif::DiscoveredAttack {
+                blocker: Position::d2(),
+                slider: Position::d1(),
+                target: Position::d8(),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn back_rank_test() -> Result<(), ParseError> {
+        let board = fen::parse("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1")?;
+
+        assert_eq!(
+            motifs(&board, &Side::White),
+            vec![Motif::BackRankWeakness {
+                king: Position::g8()
+            }]
+        );
+
+        // An escape square means the king isn't trapped.
+        let board = fen::parse("6k1/5pp1/7p/8/8/8/5PPP/R5K1 w - - 0 1")?;
+        assert!(motifs(&board, &Side::White).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn quiet_position_test() {
+        let board = Board::default();
+
+        assert!(motifs(&board, &Side::White).is_empty());
+        assert!(motifs(&board, &Side::Black).is_empty());
+    }
+
+    #[test]
+    fn king_escape_squares_test() -> Result<(), ParseError> {
+        // Stalemated in the corner.
+        let board = fen::parse("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")?;
+        assert!(king_escape_squares(&board, &Side::Black).is_empty());
+
+        let board = fen::parse("8/8/8/4k3/8/8/8/K7 b - - 0 1")?;
+        assert_eq!(king_escape_squares(&board, &Side::Black).len(), 8);
+
+        let board = fen::parse("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1")?;
+        assert_eq!(
+            king_escape_squares(&board, &Side::Black),
+            vec![Position::f8(), Position::h8()]
+        );
+
+        assert!(king_escape_squares(&Board::empty(), &Side::White).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mating_net_progress_test() -> Result<(), ParseError> {
+        let board = fen::parse("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")?;
+        assert_eq!(mating_net_progress(&board, &Side::White), 1.0);
+
+        // The queen next to the king is defended, so the king can't take it.
+        let board = fen::parse("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1")?;
+        assert_eq!(mating_net_progress(&board, &Side::White), 1.0);
+
+        let board = fen::parse("8/8/8/4k3/8/8/8/K7 b - - 0 1")?;
+        assert_eq!(mating_net_progress(&board, &Side::White), 0.0);
+
+        // The rook covers both back rank squares, including h8 behind the king. The king's own
+        // pawns aren't part of the net, so the back rank mate is as complete as the queen's.
+        let board = fen::parse("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1")?;
+        assert!(king_escape_squares(&board, &Side::Black).is_empty());
+        assert_eq!(mating_net_progress(&board, &Side::White), 1.0);
+
+        // Without the rook nothing of White's reaches the king.
+        let board = fen::parse("6k1/5ppp/8/8/8/8/8/6K1 b - - 0 1")?;
+        assert_eq!(mating_net_progress(&board, &Side::White), 0.0);
+
+        // An uncastled king surrounded by its own pieces isn't in a mating net.
+        assert_eq!(mating_net_progress(&Board::default(), &Side::White), 0.0);
+
+        // Smothered mate.
+        let board = fen::parse("6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1")?;
+        assert_eq!(mating_net_progress(&board, &Side::White), 1.0);
+
+        assert_eq!(mating_net_progress(&Board::empty(), &Side::White), 0.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn square_heatmap_test() {
+        let game = crate::fixtures::play("scholars-mate");
+
+        let destinations = square_heatmap(&game, HeatmapKind::Destinations, &Side::White);
+        for square in [
+            Position::e4(),
+            Position::h5(),
+            Position::c4(),
+            Position::f7(),
+        ] {
+            assert_eq!(destinations.get(&square), 1);
+        }
+        assert_eq!(destinations.counts().iter().sum::<u32>(), 4);
+
+        let destinations = square_heatmap(&game, HeatmapKind::Destinations, &Side::Black);
+        for square in [Position::e5(), Position::c6(), Position::f6()] {
+            assert_eq!(destinations.get(&square), 1);
+        }
+        assert_eq!(destinations.counts().iter().sum::<u32>(), 3);
+
+        let captures = square_heatmap(&game, HeatmapKind::Captures, &Side::White);
+        assert_eq!(captures.get(&Position::f7()), 1);
+        assert_eq!(captures.counts().iter().sum::<u32>(), 1);
+        assert_eq!(
+            square_heatmap(&game, HeatmapKind::Captures, &Side::Black),
+            Heatmap([0; NUM_SQUARES])
+        );
+
+        // Eight positions, with the queen on h5 for four of them and on f7 for the last.
+        let occupancy = square_heatmap(&game, HeatmapKind::Occupancy, &Side::White);
+        assert_eq!(occupancy.get(&Position::e1()), 8);
+        assert_eq!(occupancy.get(&Position::h5()), 4);
+        assert_eq!(occupancy.get(&Position::f7()), 1);
+        assert_eq!(occupancy.get(&Position::e2()), 1);
+        assert_eq!(occupancy.get(&Position::e4()), 7);
+
+        assert_eq!(
+            captures.to_string(),
+            concat!(
+                "0 0 0 0 0 0 0 0\n",
+                "0 0 0 0 0 1 0 0\n",
+                "0 0 0 0 0 0 0 0\n",
+                "0 0 0 0 0 0 0 0\n",
+                "0 0 0 0 0 0 0 0\n",
+                "0 0 0 0 0 0 0 0\n",
+                "0 0 0 0 0 0 0 0\n",
+                "0 0 0 0 0 0 0 0"
+            )
+        );
+    }
+
+    #[test]
+    fn control_map_test() -> Result<(), ParseError> {
+        let control = control_map(&Board::startpos());
+        assert_eq!(
+            control.values().iter().map(|&v| i32::from(v)).sum::<i32>(),
+            0
+        );
+        for position in (0..NUM_SQUARES).filter_map(Position::from_index) {
+            let mirrored = Position::new_unchecked(position.file(), rank::EIGHT - position.rank());
+            assert_eq!(control.get(&position), -control.get(&mirrored));
+        }
+        assert_eq!(
+            control.to_string().lines().nth(5),
+            Some(" 2  2  3  2  2  3  2  2")
+        );
+        assert_eq!(space_advantage(&Board::startpos()), (0, 0));
+
+        // The pawn takes d5 and f5, and the bishop and queen reach into black's half.
+        let control = control_map(&Board::from_moves(&["e4"])?);
+        assert_eq!(control.get(&Position::d5()), 1);
+        assert_eq!(control.get(&Position::f5()), 1);
+        assert_eq!(control.get(&Position::e4()), 0);
+        assert_eq!(control.get(&Position::a6()), -1);
+        assert_eq!(space_advantage(&Board::from_moves(&["e4"])?), (4, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn imbalance_test() -> Result<(), ParseError> {
+        let describe = |fen: &str| imbalance(&fen::parse(fen).unwrap()).describe();
+
+        assert_eq!(
+            describe("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            "Material is equal."
+        );
+        assert_eq!(
+            imbalance(&Board::startpos()),
+            MaterialImbalance {
+                exchanges: 0,
+                minor_for_pawns: 0,
+                raw: vec![]
+            }
+        );
+
+        // A rook against a bishop, with and without a pawn to compensate.
+        let exchange_up = fen::parse("4k3/3bpp2/8/8/8/8/4PP2/4K2R w - - 0 1")?;
+        assert_eq!(
+            imbalance(&exchange_up),
+            MaterialImbalance {
+                exchanges: 1,
+                minor_for_pawns: 0,
+                raw: vec![(PieceType::Rook, 1), (PieceType::Bishop, -1)]
+            }
+        );
+        assert_eq!(
+            imbalance(&exchange_up).to_string(),
+            "White is up the exchange."
+        );
+        assert_eq!(
+            describe("4k3/3bppp1/8/8/8/8/4PP2/4K2R w - - 0 1"),
+            "White is up the exchange for a pawn."
+        );
+
+        let piece_for_pawns = fen::parse("4k3/ppp5/8/8/8/8/8/4KN2 w - - 0 1")?;
+        assert_eq!(imbalance(&piece_for_pawns).minor_for_pawns, 1);
+        assert_eq!(
+            imbalance(&piece_for_pawns).describe(),
+            "White has a piece for three pawns."
+        );
+
+        assert_eq!(
+            describe("3qk3/8/8/8/8/8/8/2BRK3 w - - 0 1"),
+            "Black has a queen for a rook and a minor piece."
+        );
+        assert_eq!(
+            describe("r3k3/8/8/8/8/8/8/2BNK3 w - - 0 1"),
+            "White has two minor pieces for a rook."
+        );
+        assert_eq!(
+            describe("4k3/pp6/8/8/8/8/8/4K3 w - - 0 1"),
+            "Black is up two pawns."
+        );
+        assert_eq!(
+            describe("4k1n1/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+            "Material is equal in value, with different pieces."
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{board::position::Position, ParseError};
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Color {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl Color {
+    pub fn from(notation: char) -> Option<Color> {
+        match notation {
+            'G' => Some(Color::Green),
+            'R' => Some(Color::Red),
+            'Y' => Some(Color::Yellow),
+            'B' => Some(Color::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Color::Green => 'G',
+            Color::Red => 'R',
+            Color::Yellow => 'Y',
+            Color::Blue => 'B',
+        }
+    }
+}
+
+// Circled squares and arrows drawn on a position, independent of how they end up rendered.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Annotations {
+    pub circles: Vec<(Position, Color)>,
+    pub arrows: Vec<(Position, Position, Color)>,
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.circles.is_empty() && self.arrows.is_empty()
+    }
+
+    pub fn circle(&mut self, position: Position, color: Color) {
+        self.circles.push((position, color));
+    }
+
+    pub fn arrow(&mut self, from: Position, to: Position, color: Color) {
+        self.arrows.push((from, to, color));
+    }
+
+    // The squares to highlight, with arrows marking both of their ends.
+    pub fn highlighted_squares(&self) -> Vec<(Position, Color)> {
+        let mut squares = self.circles.clone();
+        for (from, to, color) in &self.arrows {
+            squares.push((*from, *color));
+            squares.push((*to, *color));
+        }
+
+        squares
+    }
+
+    // The PGN comment commands used by lichess, e.g. "[%csl Ge4] [%cal Rd1h5]".
+    pub fn to_comment(&self) -> String {
+        let mut commands = Vec::new();
+
+        if !self.circles.is_empty() {
+            let circles: Vec<String> = self
+                .circles
+                .iter()
+                .map(|(position, color)| format!("{}{position}", color.to_char()))
+                .collect();
+            commands.push(format!("[%csl {}]", circles.join(",")));
+        }
+
+        if !self.arrows.is_empty() {
+            let arrows: Vec<String> = self
+                .arrows
+                .iter()
+                .map(|(from, to, color)| format!("{}{from}{to}", color.to_char()))
+                .collect();
+            commands.push(format!("[%cal {}]", arrows.join(",")));
+        }
+
+        commands.join(" ")
+    }
+
+    // Reads the circle and arrow commands out of a PGN comment, ignoring any other text.
+    pub fn from_comment(comment: &str) -> Result<Annotations, ParseError> {
+        let mut annotations = Annotations::new();
+
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%") {
+            let command_end = rest[start..]
+                .find(']')
+                .ok_or(ParseError::new("Unterminated comment command."))?;
+            let command = &rest[start + 2..start + command_end];
+            rest = &rest[start + command_end + 1..];
+
+            let (name, values) = command.split_once(' ').unwrap_or((command, ""));
+            let values = values.split(',').map(str::trim).filter(|v| !v.is_empty());
+            match name {
+                "csl" => {
+                    for value in values {
+                        let (color, position) = parse_color(value)?;
+                        let position = Position::from_notation(position)
+                            .ok_or(ParseError::new("Invalid circle square."))?;
+                        annotations.circle(position, color);
+                    }
+                }
+                "cal" => {
+                    for value in values {
+                        let (color, squares) = parse_color(value)?;
+                        let (from, to) = match (squares.get(0..2), squares.get(2..)) {
+                            (Some(from), Some(to)) => {
+                                (Position::from_notation(from), Position::from_notation(to))
+                            }
+                            _ => (None, None),
+                        };
+                        match (from, to) {
+                            (Some(from), Some(to)) => annotations.arrow(from, to, color),
+                            _ => return Err(ParseError::new("Invalid arrow squares.")),
+                        }
+                    }
+                }
+                // Other commands such as clock times aren't annotations.
+                _ => (),
+            }
+        }
+
+        Ok(annotations)
+    }
+}
+
+fn parse_color(value: &str) -> Result<(Color, &str), ParseError> {
+    let mut chars = value.chars();
+    let color = chars
+        .next()
+        .and_then(Color::from)
+        .ok_or(ParseError::new("Invalid annotation color."))?;
+
+    Ok((color, chars.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_round_trip_test() -> Result<(), ParseError> {
+        let mut annotations = Annotations::new();
+        assert_eq!(annotations.to_comment(), "");
+
+        annotations.circle(Position::e4(), Color::Green);
+        annotations.circle(Position::d5(), Color::Red);
+        annotations.arrow(Position::d1(), Position::h5(), Color::Yellow);
+
+        let comment = annotations.to_comment();
+        assert_eq!(comment, "[%csl Ge4,Rd5] [%cal Yd1h5]");
+        assert_eq!(Annotations::from_comment(&comment)?, annotations);
+
+        let parsed =
+            Annotations::from_comment("Strong move! [%clk 0:05:00] [%cal Bg1f3,Ge2e4] [%csl]")?;
+        assert!(parsed.circles.is_empty());
+        assert_eq!(
+            parsed.arrows,
+            vec![
+                (Position::g1(), Position::f3(), Color::Blue),
+                (Position::e2(), Position::e4(), Color::Green)
+            ]
+        );
+
+        assert!(Annotations::from_comment("[%csl Xe4]").is_err());
+        assert!(Annotations::from_comment("[%cal Ge2]").is_err());
+        assert!(Annotations::from_comment("[%csl Ge4").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn highlighted_squares_test() {
+        let mut annotations = Annotations::new();
+        annotations.circle(Position::c3(), Color::Blue);
+        annotations.arrow(Position::a1(), Position::a8(), Color::Red);
+
+        assert_eq!(
+            annotations.highlighted_squares(),
+            vec![
+                (Position::c3(), Color::Blue),
+                (Position::a1(), Color::Red),
+                (Position::a8(), Color::Red)
+            ]
+        );
+    }
+}
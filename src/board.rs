@@ -1,30 +1,145 @@
+mod diff;
 pub mod file;
+pub mod geometry;
 pub mod position;
 pub mod rank;
+mod rays;
+pub mod square_set;
 mod utils;
 
+pub use diff::diff_display;
+pub(crate) use utils::attackers_of;
+#[allow(deprecated)]
+pub use utils::{are_positions_empty, contains_enemy_piece, contains_piece};
 pub use utils::{
-    get_all_legal_moves, get_move_state, is_in_check, move_piece, MoveError, MoveInfo, MoveKind,
-    MoveRequest, MoveState,
+    can_claim_fifty_move_rule, checkmate_details, filter_legal, find_divergence,
+    get_all_legal_moves, get_all_target_positions, get_disambiguation, get_move_state, is_in_check,
+    is_promotion_move, is_zugzwang_risky, legal_moves_if_to_move, make_move, move_piece,
+    moves_for_piece_type, only_move, perft, perft_divide, perft_stats, preview_move,
+    pseudo_moves_ignoring_turn, CoordinateOptions, EscapeBlocker, MateDetails, MoveError, MoveInfo,
+    MoveKind, MoveRequest, MoveState, MoveUndo, PerftStats,
 };
 
-use std::collections::HashSet;
-
 use crate::{
-    piece::{Piece, PieceType, Side},
-    piece_position,
+    piece::{Piece, PieceType, PromotionType, Side},
+    stats, ParseError,
 };
+use geometry::NUM_SQUARES;
 use position::Position;
+use square_set::SquareSet;
 
-const BOARD_SIZE: usize = 64;
 const EMPTY: Option<Piece> = None;
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+const STARTING_POSITIONS: [Option<Piece>; NUM_SQUARES] = starting_positions();
+const STARTING_WHITE_POSITIONS: u64 = 0x0000_0000_0000_FFFF;
+const STARTING_BLACK_POSITIONS: u64 = 0xFFFF_0000_0000_0000;
+
+const fn starting_positions() -> [Option<Piece>; NUM_SQUARES] {
+    let mut positions = [EMPTY; NUM_SQUARES];
+
+    let mut file = 0;
+    while file < file::LENGTH {
+        positions[file] = Some(Piece {
+            piece_type: back_rank_piece_type(file),
+            side: Side::White,
+        });
+        positions[geometry::square_index(file, rank::TWO)] = Some(Piece {
+            piece_type: PieceType::Pawn,
+            side: Side::White,
+        });
+        positions[geometry::square_index(file, rank::SEVEN)] = Some(Piece {
+            piece_type: PieceType::Pawn,
+            side: Side::Black,
+        });
+        positions[geometry::square_index(file, rank::EIGHT)] = Some(Piece {
+            piece_type: back_rank_piece_type(file),
+            side: Side::Black,
+        });
+
+        file += 1;
+    }
+
+    positions
+}
+
+// The squares that must be empty for each castle, and the squares the king crosses or lands on.
+static WHITE_SHORT_CASTLE_SQUARES: [Position; 2] = [Position::F1, Position::G1];
+static WHITE_LONG_CASTLE_SQUARES: [Position; 3] = [Position::D1, Position::C1, Position::B1];
+static WHITE_LONG_CASTLE_KING_PATH: [Position; 2] = [Position::D1, Position::C1];
+static BLACK_SHORT_CASTLE_SQUARES: [Position; 2] = [Position::F8, Position::G8];
+static BLACK_LONG_CASTLE_SQUARES: [Position; 3] = [Position::D8, Position::C8, Position::B8];
+static BLACK_LONG_CASTLE_KING_PATH: [Position; 2] = [Position::D8, Position::C8];
+
+fn castle_squares(
+    side: &Side,
+    castle_side: CastleSide,
+) -> (&'static [Position], &'static [Position]) {
+    match (side, castle_side) {
+        (Side::White, CastleSide::Short) => {
+            (&WHITE_SHORT_CASTLE_SQUARES, &WHITE_SHORT_CASTLE_SQUARES)
+        }
+        (Side::White, CastleSide::Long) => {
+            (&WHITE_LONG_CASTLE_SQUARES, &WHITE_LONG_CASTLE_KING_PATH)
+        }
+        (Side::Black, CastleSide::Short) => {
+            (&BLACK_SHORT_CASTLE_SQUARES, &BLACK_SHORT_CASTLE_SQUARES)
+        }
+        (Side::Black, CastleSide::Long) => {
+            (&BLACK_LONG_CASTLE_SQUARES, &BLACK_LONG_CASTLE_KING_PATH)
+        }
+    }
+}
+
+const fn back_rank_piece_type(file: usize) -> PieceType {
+    match file {
+        0 | 7 => PieceType::Rook,
+        1 | 6 => PieceType::Knight,
+        2 | 5 => PieceType::Bishop,
+        3 => PieceType::Queen,
+        _ => PieceType::King,
+    }
+}
+
+#[derive(Debug)]
+pub struct BoardError(String);
+
+impl BoardError {
+    pub fn new(error: &str) -> BoardError {
+        BoardError(String::from(error))
+    }
+}
+
+impl std::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum CastleSide {
+    Short,
+    Long,
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Default)]
+pub struct SideCastleRights {
+    pub short: bool,
+    pub long: bool,
+}
+
+impl SideCastleRights {
+    pub fn get(&self, castle_side: CastleSide) -> bool {
+        match castle_side {
+            CastleSide::Short => self.short,
+            CastleSide::Long => self.long,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub struct CastleRights {
-    pub white_short_castle_rights: bool,
-    pub white_long_castle_rights: bool,
-    pub black_short_castle_rights: bool,
-    pub black_long_castle_rights: bool,
+    pub white: SideCastleRights,
+    pub black: SideCastleRights,
 }
 
 impl CastleRights {
@@ -35,51 +150,163 @@ impl CastleRights {
         black_long_castle_rights: bool,
     ) -> CastleRights {
         CastleRights {
-            white_short_castle_rights,
-            white_long_castle_rights,
-            black_short_castle_rights,
-            black_long_castle_rights,
+            white: SideCastleRights {
+                short: white_short_castle_rights,
+                long: white_long_castle_rights,
+            },
+            black: SideCastleRights {
+                short: black_short_castle_rights,
+                long: black_long_castle_rights,
+            },
+        }
+    }
+
+    pub fn rights_for(&self, side: &Side) -> &SideCastleRights {
+        match side {
+            Side::White => &self.white,
+            Side::Black => &self.black,
+        }
+    }
+
+    fn rights_for_mut(&mut self, side: &Side) -> &mut SideCastleRights {
+        match side {
+            Side::White => &mut self.white,
+            Side::Black => &mut self.black,
+        }
+    }
+
+    pub fn revoke_all(&mut self, side: &Side) {
+        *self.rights_for_mut(side) = SideCastleRights::default();
+    }
+
+    pub fn revoke(&mut self, side: &Side, castle_side: CastleSide) {
+        let rights = self.rights_for_mut(side);
+        match castle_side {
+            CastleSide::Short => rights.short = false,
+            CastleSide::Long => rights.long = false,
         }
     }
+
+    pub fn any(&self) -> bool {
+        self.white.short || self.white.long || self.black.short || self.black.long
+    }
+}
+
+// The castling field of a FEN, e.g. "KQkq", "Kq" or "-".
+impl std::fmt::Display for CastleRights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.any() {
+            return write!(f, "-");
+        }
+
+        let rights = [
+            (self.white.short, 'K'),
+            (self.white.long, 'Q'),
+            (self.black.short, 'k'),
+            (self.black.long, 'q'),
+        ];
+        for (has_right, notation) in rights {
+            if has_right {
+                write!(f, "{notation}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum CastleAvailability {
+    Available,
+    BlockedBy(Position),
+    ThroughCheck(Position),
+    InCheck,
+    NoRights,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FileState {
+    Open,
+    HalfOpenFor(Side), // only the other side has pawns on the file
+    Closed,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct RepetitionState {
-    positions: [Option<Piece>; BOARD_SIZE],
+    positions: [Option<Piece>; NUM_SQUARES],
     current_turn: Side,
     castle_rights: CastleRights,
     en_passant_capture: Option<Position>,
 }
 
-#[derive(Clone, Debug)]
+impl RepetitionState {
+    pub fn get_castle_rights(&self) -> &CastleRights {
+        &self.castle_rights
+    }
+}
+
+#[derive(Eq, Debug)]
 pub struct Board {
-    positions: [Option<Piece>; BOARD_SIZE],
-    white_positions: HashSet<Position>,
-    black_positions: HashSet<Position>,
+    positions: [Option<Piece>; NUM_SQUARES],
+    white_positions: SquareSet,
+    black_positions: SquareSet,
     current_turn: Side,
     castle_rights: CastleRights,
     en_passant_target: Option<Position>,
     half_moves: u32,
     full_moves: u32,
+    last_move: Option<(Position, Position)>, // start and end of the move that led here
+}
+
+// The last move is only for display, two boards reached by different moves are still the same.
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.positions == other.positions
+            && self.white_positions == other.white_positions
+            && self.black_positions == other.black_positions
+            && self.current_turn == other.current_turn
+            && self.castle_rights == other.castle_rights
+            && self.en_passant_target == other.en_passant_target
+            && self.half_moves == other.half_moves
+            && self.full_moves == other.full_moves
+    }
+}
+
+// Every copy goes through clone_for_simulation so the stats counters see it.
+impl Clone for Board {
+    fn clone(&self) -> Board {
+        self.clone_for_simulation()
+    }
 }
 
 impl Board {
     pub fn empty() -> Board {
-        let positions: [Option<Piece>; BOARD_SIZE] = [EMPTY; BOARD_SIZE];
+        let positions: [Option<Piece>; NUM_SQUARES] = [EMPTY; NUM_SQUARES];
         Board {
             positions,
-            white_positions: HashSet::new(),
-            black_positions: HashSet::new(),
+            white_positions: SquareSet::new(),
+            black_positions: SquareSet::new(),
             current_turn: Side::White,
-            castle_rights: CastleRights {
-                white_short_castle_rights: true,
-                white_long_castle_rights: true,
-                black_short_castle_rights: true,
-                black_long_castle_rights: true,
-            },
+            castle_rights: CastleRights::new(true, true, true, true),
             en_passant_target: None,
             half_moves: 0,
             full_moves: 1,
+            last_move: None,
+        }
+    }
+
+    // The standard starting position, built from constants without placing pieces one by one.
+    pub fn startpos() -> Board {
+        Board {
+            positions: STARTING_POSITIONS,
+            white_positions: SquareSet::from_bits(STARTING_WHITE_POSITIONS),
+            black_positions: SquareSet::from_bits(STARTING_BLACK_POSITIONS),
+            current_turn: Side::White,
+            castle_rights: CastleRights::new(true, true, true, true),
+            en_passant_target: None,
+            half_moves: 0,
+            full_moves: 1,
+            last_move: None,
         }
     }
 
@@ -91,17 +318,54 @@ impl Board {
         half_moves: u32,
         full_moves: u32,
     ) -> Board {
-        let positions: [Option<Piece>; BOARD_SIZE] = [EMPTY; BOARD_SIZE];
+        let mut board =
+            Board::with_pieces(pieces, current_turn, castle_rights, half_moves, full_moves);
+
+        // Silently drop a target that could not have come from a double move.
+        if let Some(target) = en_passant_target {
+            if board.validate_en_passant_target(&target).is_ok() {
+                board.en_passant_target = Some(target);
+            }
+        }
+
+        board
+    }
+
+    pub fn try_new(
+        pieces: Vec<(Position, Piece)>,
+        current_turn: Side,
+        castle_rights: CastleRights,
+        en_passant_target: Option<Position>,
+        half_moves: u32,
+        full_moves: u32,
+    ) -> Result<Board, BoardError> {
+        let mut board =
+            Board::with_pieces(pieces, current_turn, castle_rights, half_moves, full_moves);
+
+        board.set_en_passant_target(en_passant_target)?;
+
+        Ok(board)
+    }
+
+    fn with_pieces(
+        pieces: Vec<(Position, Piece)>,
+        current_turn: Side,
+        castle_rights: CastleRights,
+        half_moves: u32,
+        full_moves: u32,
+    ) -> Board {
+        let positions: [Option<Piece>; NUM_SQUARES] = [EMPTY; NUM_SQUARES];
 
         let mut board = Board {
             positions,
-            white_positions: HashSet::new(),
-            black_positions: HashSet::new(),
+            white_positions: SquareSet::new(),
+            black_positions: SquareSet::new(),
             current_turn,
             castle_rights,
-            en_passant_target,
+            en_passant_target: None,
             half_moves,
             full_moves,
+            last_move: None,
         };
 
         board.add_pieces(pieces);
@@ -109,6 +373,40 @@ impl Board {
         board
     }
 
+    // A copy of the board for trying out a move. Every field is Copy, so this is a flat copy of
+    // the squares and bitboards.
+    pub fn clone_for_simulation(&self) -> Board {
+        stats::count_board_clone();
+
+        Board {
+            positions: self.positions,
+            white_positions: self.white_positions,
+            black_positions: self.black_positions,
+            current_turn: self.current_turn,
+            castle_rights: self.castle_rights,
+            en_passant_target: self.en_passant_target,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            last_move: self.last_move,
+        }
+    }
+
+    // The start and end of the move that led to this position, if it was played on this board.
+    // Castling reports the king's move.
+    pub fn last_move(&self) -> Option<(Position, Position)> {
+        self.last_move
+    }
+
+    pub fn set_last_move(&mut self, last_move: Option<(Position, Position)>) {
+        self.last_move = last_move;
+    }
+
+    pub fn side_to_move(&self) -> Side {
+        self.current_turn
+    }
+
+    // The old accessors below stay for existing callers, new code uses the un-prefixed names.
+    #[deprecated(note = "use Board::side_to_move")]
     pub fn get_current_turn(&self) -> &Side {
         &self.current_turn
     }
@@ -123,14 +421,125 @@ impl Board {
         };
     }
 
+    // Passes the turn without moving, as in null-move pruning. The en passant target only
+    // applies to the very next move, so it goes, and the move counters advance as for a
+    // quiet move.
+    pub fn make_null_move(&mut self) {
+        self.en_passant_target = None;
+        self.half_moves += 1;
+        self.change_turn();
+        self.last_move = None;
+    }
+
+    // The en passant target belongs to the side that moves next, so it's dropped if it no
+    // longer fits.
+    pub fn set_turn(&mut self, side: Side) {
+        self.current_turn = side;
+        self.clear_stale_en_passant_target();
+    }
+
     pub fn get_castle_rights(&self) -> &CastleRights {
         &self.castle_rights
     }
 
+    pub fn set_castle_rights(&mut self, castle_rights: CastleRights) -> Result<(), BoardError> {
+        self.validate_castle_rights(&castle_rights)?;
+        self.castle_rights = castle_rights;
+
+        Ok(())
+    }
+
+    fn validate_castle_rights(&self, castle_rights: &CastleRights) -> Result<(), BoardError> {
+        if castle_rights.white.short && !self.has_castling_pieces(Side::White, CastleSide::Short) {
+            return Err(BoardError::new(
+                "White short castle rights require a king on e1 and a rook on h1.",
+            ));
+        }
+
+        if castle_rights.white.long && !self.has_castling_pieces(Side::White, CastleSide::Long) {
+            return Err(BoardError::new(
+                "White long castle rights require a king on e1 and a rook on a1.",
+            ));
+        }
+
+        if castle_rights.black.short && !self.has_castling_pieces(Side::Black, CastleSide::Short) {
+            return Err(BoardError::new(
+                "Black short castle rights require a king on e8 and a rook on h8.",
+            ));
+        }
+
+        if castle_rights.black.long && !self.has_castling_pieces(Side::Black, CastleSide::Long) {
+            return Err(BoardError::new(
+                "Black long castle rights require a king on e8 and a rook on a8.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Whether the king and the rook for a castle are still on their starting squares.
+    fn has_castling_pieces(&self, side: Side, castle_side: CastleSide) -> bool {
+        let (king, rook) = match (side, castle_side) {
+            (Side::White, CastleSide::Short) => (Position::e1(), Position::h1()),
+            (Side::White, CastleSide::Long) => (Position::e1(), Position::a1()),
+            (Side::Black, CastleSide::Short) => (Position::e8(), Position::h8()),
+            (Side::Black, CastleSide::Long) => (Position::e8(), Position::a8()),
+        };
+
+        self.piece_at(&king) == Some(&Piece::new(PieceType::King, side))
+            && self.piece_at(&rook) == Some(&Piece::new(PieceType::Rook, side))
+    }
+
+    pub fn en_passant_target(&self) -> Option<Position> {
+        self.en_passant_target
+    }
+
+    #[deprecated(note = "use Board::en_passant_target")]
     pub fn get_en_passant_target(&self) -> &Option<Position> {
         &self.en_passant_target
     }
 
+    pub fn set_en_passant_target(
+        &mut self,
+        en_passant_target: Option<Position>,
+    ) -> Result<(), BoardError> {
+        if let Some(target) = &en_passant_target {
+            self.validate_en_passant_target(target)?;
+        }
+
+        self.en_passant_target = en_passant_target;
+
+        Ok(())
+    }
+
+    fn validate_en_passant_target(&self, target: &Position) -> Result<(), BoardError> {
+        // The target sits behind a pawn that just double moved, so it depends on who moves next.
+        let opponent = self.current_turn.opponent();
+        let start_position = Position::new_unchecked(target.file(), opponent.pawn_start_rank());
+        let target_position = (start_position + opponent.pawn_direction()).unwrap();
+        let pawn_position = (target_position + opponent.pawn_direction()).unwrap();
+
+        if target.rank() != target_position.rank() {
+            let error = format!(
+                "En passant target {target} is not on rank {} for the side to move.",
+                rank::to_char(target_position.rank())
+            );
+            return Err(BoardError::new(error.as_str()));
+        }
+
+        let pushed_pawn = Piece::new(PieceType::Pawn, self.current_turn.opponent());
+
+        if self.piece_at(&pawn_position) != Some(&pushed_pawn)
+            || self.piece_at(target).is_some()
+            || self.piece_at(&start_position).is_some()
+        {
+            let error = format!("En passant target {target} does not follow a pawn double move.");
+            return Err(BoardError::new(error.as_str()));
+        }
+
+        Ok(())
+    }
+
     pub fn get_half_moves(&self) -> u32 {
         self.half_moves
     }
@@ -139,33 +548,117 @@ impl Board {
         self.full_moves
     }
 
+    // The number of plies played before this position, counting from white's first move, so the
+    // standard starting position is ply 0. Saturates for move numbers no real game reaches.
+    pub fn ply(&self) -> u32 {
+        let black_to_move = u32::from(self.current_turn == Side::Black);
+        self.full_moves
+            .saturating_sub(1)
+            .saturating_mul(2)
+            .saturating_add(black_to_move)
+    }
+
+    // Piece occupancy from the side to move's point of view, for tooling such as neural networks.
+    // planes[0] holds the side to move's pieces and planes[1] the opponent's, each indexed in
+    // PieceType order: pawn, knight, bishop, rook, queen, king. A piece sets the bit numbered by
+    // its square after Position::relative_to, so the side to move always starts from ranks one and
+    // two and its pawns move towards bit 63.
+    pub fn to_relative_planes(&self) -> [[u64; 6]; 2] {
+        let side = self.side_to_move();
+
+        let mut planes = [[0; 6]; 2];
+        for position in self
+            .white_positions
+            .iter()
+            .chain(self.black_positions.iter())
+        {
+            if let Some(piece) = self.piece_at(&position) {
+                let owner = usize::from(piece.side != side);
+                planes[owner][piece.piece_type as usize] |=
+                    1 << position.relative_to(&side).value();
+            }
+        }
+
+        planes
+    }
+
     pub fn get_repetition_state(&self) -> RepetitionState {
         let en_passant_capture = if utils::possible_en_passant_capture(self) {
-            self.en_passant_target.clone()
+            self.en_passant_target
         } else {
             None
         };
 
         RepetitionState {
-            positions: self.positions.clone(),
-            current_turn: self.current_turn.clone(),
-            castle_rights: self.castle_rights.clone(),
+            positions: self.positions,
+            current_turn: self.current_turn,
+            castle_rights: self.castle_rights,
             en_passant_capture,
         }
     }
 
-    pub fn get_white_positions(&self) -> &HashSet<Position> {
+    pub fn get_white_positions(&self) -> &SquareSet {
         &self.white_positions
     }
 
-    pub fn get_black_positions(&self) -> &HashSet<Position> {
+    pub fn get_black_positions(&self) -> &SquareSet {
         &self.black_positions
     }
 
-    pub fn get_piece(&self, position: &Position) -> Option<&Piece> {
+    pub fn piece_at(&self, position: &Position) -> Option<&Piece> {
         self.positions[position.value()].as_ref()
     }
 
+    #[deprecated(note = "use Board::piece_at")]
+    pub fn get_piece(&self, position: &Position) -> Option<&Piece> {
+        self.piece_at(position)
+    }
+
+    pub fn is_occupied(&self, position: &Position) -> bool {
+        self.piece_at(position).is_some()
+    }
+
+    pub fn is_occupied_by(&self, position: &Position, side: &Side) -> bool {
+        self.piece_at(position)
+            .is_some_and(|piece| piece.side == *side)
+    }
+
+    // Whether every square strictly between two squares on a shared rank, file or diagonal is
+    // empty. Squares that don't share a line have nothing between them to check, so they never
+    // count as clear.
+    pub fn is_empty_between(&self, from: &Position, to: &Position) -> bool {
+        let aligned = from.file() == to.file()
+            || from.rank() == to.rank()
+            || from.file().abs_diff(to.file()) == from.rank().abs_diff(to.rank());
+        if !aligned || from == to {
+            return false;
+        }
+
+        utils::squares_between(from, to)
+            .iter()
+            .all(|square| !self.is_occupied(square))
+    }
+
+    pub fn has_king(&self, side: &Side) -> bool {
+        self.king_position(side).is_some()
+    }
+
+    pub fn king_position(&self, side: &Side) -> Option<Position> {
+        let positions = match side {
+            Side::White => &self.white_positions,
+            Side::Black => &self.black_positions,
+        };
+        let king = Piece::new(PieceType::King, *side);
+
+        positions
+            .iter()
+            .find(|position| self.piece_at(position) == Some(&king))
+    }
+
+    pub fn occupied_count(&self) -> usize {
+        self.white_positions.len() + self.black_positions.len()
+    }
+
     pub fn take_piece(&mut self, position: &Position) -> Option<Piece> {
         let opt_piece = self.positions[position.value()].take();
 
@@ -190,10 +683,10 @@ impl Board {
         if let Some(piece) = &opt_piece {
             match piece.side {
                 Side::White => {
-                    self.white_positions.insert(position.clone());
+                    self.white_positions.insert(position);
                 }
                 Side::Black => {
-                    self.black_positions.insert(position.clone());
+                    self.black_positions.insert(position);
                 }
             }
         }
@@ -201,6 +694,120 @@ impl Board {
         self.positions[position.value()] = opt_piece;
     }
 
+    pub fn clear_square(&mut self, position: &Position) {
+        self.set_position(position, None);
+    }
+
+    // Leaves the turn and move counters alone, only dropping castle rights and an en passant
+    // target the pieces no longer support.
+    pub fn move_piece_raw(&mut self, from: &Position, to: &Position) {
+        if let Some(piece) = self.take_piece(from) {
+            self.set_position(to, Some(piece));
+            self.revoke_stale_castle_rights();
+            self.clear_stale_en_passant_target();
+        }
+    }
+
+    fn revoke_stale_castle_rights(&mut self) {
+        for side in [Side::White, Side::Black] {
+            for castle_side in [CastleSide::Short, CastleSide::Long] {
+                if !self.has_castling_pieces(side, castle_side) {
+                    self.castle_rights.revoke(&side, castle_side);
+                }
+            }
+        }
+    }
+
+    fn clear_stale_en_passant_target(&mut self) {
+        if let Some(target) = self.en_passant_target {
+            if self.validate_en_passant_target(&target).is_err() {
+                self.en_passant_target = None;
+            }
+        }
+    }
+
+    pub fn undo(&mut self, undo: MoveUndo) {
+        // Clear the destination first, a normal capture is restored onto the same position.
+        self.set_position(&undo.end, None);
+
+        if let Some((rook_start, rook_end)) = &undo.rook_move {
+            let rook = self.take_piece(rook_end);
+            self.set_position(rook_start, rook);
+        }
+
+        if let Some((captured_position, captured_piece)) = undo.captured {
+            self.set_position(&captured_position, Some(captured_piece));
+        }
+
+        self.current_turn = undo.moved_piece.side;
+        self.set_position(&undo.start, Some(undo.moved_piece));
+
+        self.castle_rights = undo.castle_rights;
+        self.en_passant_target = undo.en_passant_target;
+        self.half_moves = undo.half_moves;
+        self.full_moves = undo.full_moves;
+        self.last_move = undo.last_move;
+    }
+
+    // Moves whatever is on `from` to `to` without checking that the move is legal, for setting up
+    // positions and correcting mistakes. Castling and en passant are not recognised, the en
+    // passant target is cleared and any castle rights whose king or rook left its square are
+    // revoked. Returns the captured piece. Nothing happens if `from` is empty.
+    pub fn force_move(
+        &mut self,
+        from: &Position,
+        to: &Position,
+        promotion: Option<PromotionType>,
+    ) -> Option<Piece> {
+        let mut piece = self.take_piece(from)?;
+        let is_pawn_move = piece.piece_type == PieceType::Pawn;
+        if let Some(promotion) = promotion {
+            piece.piece_type = promotion.to_piece_type();
+        }
+
+        let captured = self.take_piece(to);
+        self.set_position(to, Some(piece));
+
+        if is_pawn_move || captured.is_some() {
+            self.half_moves = 0;
+        } else {
+            self.half_moves += 1;
+        }
+        self.change_turn();
+
+        self.en_passant_target = None;
+        self.revoke_stale_castle_rights();
+        self.last_move = Some((*from, *to));
+
+        captured
+    }
+
+    // Checks the invariants that the editing methods can't break but a bug in move generation
+    // could: the squares agree with the side bitboards, and the castle rights and en passant
+    // target still fit the pieces.
+    pub fn check_consistency(&self) -> Result<(), BoardError> {
+        for position in (0..NUM_SQUARES).filter_map(Position::from_index) {
+            let (in_white, in_black) = match self.piece_at(&position) {
+                Some(piece) => (piece.side == Side::White, piece.side == Side::Black),
+                None => (false, false),
+            };
+
+            if self.white_positions.contains(&position) != in_white
+                || self.black_positions.contains(&position) != in_black
+            {
+                let error = format!("The bitboards disagree with the piece on {position}.");
+                return Err(BoardError::new(error.as_str()));
+            }
+        }
+
+        self.validate_castle_rights(&self.castle_rights)?;
+        if let Some(target) = &self.en_passant_target {
+            self.validate_en_passant_target(target)?;
+        }
+
+        Ok(())
+    }
+
     pub fn add_piece(&mut self, position: &Position, piece: Piece) {
         self.set_position(position, Some(piece));
     }
@@ -210,50 +817,134 @@ impl Board {
             self.add_piece(&position, piece);
         }
     }
-}
 
-impl Default for Board {
-    fn default() -> Self {
-        let pieces = vec![
-            piece_position!(a2, Pawn, White),
-            piece_position!(b2, Pawn, White),
-            piece_position!(c2, Pawn, White),
-            piece_position!(d2, Pawn, White),
-            piece_position!(e2, Pawn, White),
-            piece_position!(f2, Pawn, White),
-            piece_position!(g2, Pawn, White),
-            piece_position!(h2, Pawn, White),
-            piece_position!(a1, Rook, White),
-            piece_position!(b1, Knight, White),
-            piece_position!(c1, Bishop, White),
-            piece_position!(d1, Queen, White),
-            piece_position!(e1, King, White),
-            piece_position!(f1, Bishop, White),
-            piece_position!(g1, Knight, White),
-            piece_position!(h1, Rook, White),
-            piece_position!(a7, Pawn, Black),
-            piece_position!(b7, Pawn, Black),
-            piece_position!(c7, Pawn, Black),
-            piece_position!(d7, Pawn, Black),
-            piece_position!(e7, Pawn, Black),
-            piece_position!(f7, Pawn, Black),
-            piece_position!(g7, Pawn, Black),
-            piece_position!(h7, Pawn, Black),
-            piece_position!(a8, Rook, Black),
-            piece_position!(b8, Knight, Black),
-            piece_position!(c8, Bishop, Black),
-            piece_position!(d8, Queen, Black),
-            piece_position!(e8, King, Black),
-            piece_position!(f8, Bishop, Black),
-            piece_position!(g8, Knight, Black),
-            piece_position!(h8, Rook, Black),
-        ];
+    // Checked in the order a player would notice: rights, check, pieces in the way, then
+    // attacked squares the king would cross or land on.
+    pub fn can_castle(&self, side: &Side, long: bool) -> CastleAvailability {
+        let castle_side = if long {
+            CastleSide::Long
+        } else {
+            CastleSide::Short
+        };
+        if !self.castle_rights.rights_for(side).get(castle_side) {
+            return CastleAvailability::NoRights;
+        }
 
-        let mut board = Board::empty();
+        if utils::is_in_check(self, side) {
+            return CastleAvailability::InCheck;
+        }
 
-        board.add_pieces(pieces);
+        let (between, king_path) = castle_squares(side, castle_side);
 
-        board
+        if let Some(blocker) = between
+            .iter()
+            .copied()
+            .find(|position| self.piece_at(position).is_some())
+        {
+            return CastleAvailability::BlockedBy(blocker);
+        }
+
+        let attacked = get_all_target_positions(self, &side.opponent());
+        if let Some(attacked_square) = king_path
+            .iter()
+            .copied()
+            .find(|position| attacked.contains(position))
+        {
+            return CastleAvailability::ThroughCheck(attacked_square);
+        }
+
+        CastleAvailability::Available
+    }
+
+    // These only look for a single legal move, so they cost a check test plus one pass over the
+    // pseudo-legal moves at worst, rather than building the full list of legal moves.
+    pub fn has_legal_move(&self) -> bool {
+        utils::has_legal_move(self)
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        is_in_check(self, &self.side_to_move()) && !self.has_legal_move()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !is_in_check(self, &self.side_to_move()) && !self.has_legal_move()
+    }
+
+    pub fn file_state(&self, file: usize) -> FileState {
+        let pawn_sides: Vec<Side> = (rank::ONE..=rank::EIGHT)
+            .filter_map(|rank| self.piece_at(&Position::new_unchecked(file, rank)))
+            .filter(|piece| piece.piece_type == PieceType::Pawn)
+            .map(|piece| piece.side)
+            .collect();
+
+        match (
+            pawn_sides.contains(&Side::White),
+            pawn_sides.contains(&Side::Black),
+        ) {
+            (false, false) => FileState::Open,
+            (false, true) => FileState::HalfOpenFor(Side::White),
+            (true, false) => FileState::HalfOpenFor(Side::Black),
+            (true, true) => FileState::Closed,
+        }
+    }
+
+    // Whether two of `side`'s rooks defend each other along a rank or file.
+    pub fn rooks_connected(&self, side: &Side) -> bool {
+        let positions = match side {
+            Side::White => &self.white_positions,
+            Side::Black => &self.black_positions,
+        };
+        let rooks: Vec<Position> = positions
+            .iter()
+            .filter(|position| {
+                self.piece_at(position)
+                    .is_some_and(|piece| piece.piece_type == PieceType::Rook)
+            })
+            .collect();
+
+        rooks.iter().enumerate().any(|(index, rook)| {
+            rooks[index + 1..].iter().any(|other| {
+                (rook.file() == other.file() || rook.rank() == other.rank())
+                    && self.is_empty_between(rook, other)
+            })
+        })
+    }
+
+    // Pawns of the side to move that can legally capture en passant right now.
+    pub fn en_passant_capturers(&self) -> Vec<Position> {
+        let mut capturers: Vec<Position> = get_all_legal_moves(self, &self.side_to_move())
+            .into_iter()
+            .filter(|(_, moves)| {
+                moves
+                    .values()
+                    .any(|move_kind| matches!(move_kind, MoveKind::EnPassant(_)))
+            })
+            .map(|(position, _)| position)
+            .collect();
+        capturers.sort_by_key(Position::value);
+
+        capturers
+    }
+
+    pub fn from_moves(moves: &[&str]) -> Result<Board, ParseError> {
+        let mut board = Board::default();
+
+        for (index, san) in moves.iter().enumerate() {
+            let invalid_move = |error: &dyn std::fmt::Display| {
+                ParseError::new(&format!("Move {index} ({san}): {error}"))
+            };
+
+            let request = MoveRequest::from_san(&board, san).map_err(|e| invalid_move(&e))?;
+            move_piece(&mut board, request).map_err(|e| invalid_move(&e))?;
+        }
+
+        Ok(board)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::startpos()
     }
 }
 
@@ -263,8 +954,8 @@ impl std::fmt::Display for Board {
         for rank in (rank::ONE..=rank::EIGHT).rev() {
             let mut rank_string = String::new();
             for file in file::A..=file::H {
-                let position = Position::from_file_and_rank(file, rank);
-                let piece_notation = match self.get_piece(&position) {
+                let position = Position::new_unchecked(file, rank);
+                let piece_notation = match self.piece_at(&position) {
                     Some(piece) => piece.to_string(),
                     None => String::from(" "),
                 };
@@ -286,7 +977,7 @@ impl std::fmt::Display for Board {
 
 #[cfg(test)]
 mod tests {
-    use crate::board_position;
+    use crate::{assert_boards_eq, board_position, fen, piece_position, ParseError};
 
     use super::*;
 
@@ -362,17 +1053,17 @@ mod tests {
         ];
 
         for (position, piece) in position_tests {
-            assert_eq!(board.get_piece(&position), piece.as_ref());
+            assert_eq!(board.piece_at(&position), piece.as_ref());
         }
 
-        assert_eq!(*board.get_current_turn(), Side::White);
+        assert_eq!(board.side_to_move(), Side::White);
 
         assert_eq!(
             *board.get_castle_rights(),
             CastleRights::new(true, true, true, true)
         );
 
-        assert_eq!(*board.get_en_passant_target(), None);
+        assert_eq!(board.en_passant_target(), None);
 
         assert_eq!(board.get_half_moves(), 0);
 
@@ -451,20 +1142,683 @@ mod tests {
         ];
 
         for (position, piece) in position_tests {
-            assert_eq!(board.get_piece(&position), piece.as_ref());
+            assert_eq!(board.piece_at(&position), piece.as_ref());
         }
 
-        assert_eq!(*board.get_current_turn(), Side::White);
+        assert_eq!(board.side_to_move(), Side::White);
 
         assert_eq!(
             *board.get_castle_rights(),
             CastleRights::new(true, true, true, true)
         );
 
-        assert_eq!(*board.get_en_passant_target(), None);
+        assert_eq!(board.en_passant_target(), None);
 
         assert_eq!(board.get_half_moves(), 0);
 
         assert_eq!(board.get_full_moves(), 1);
     }
+
+    #[test]
+    fn editor_test() -> Result<(), ParseError> {
+        let mut board = Board::default();
+
+        board.move_piece_raw(&Position::e2(), &Position::e4());
+        board.clear_square(&Position::d7());
+        board.set_turn(Side::Black);
+        board
+            .set_en_passant_target(Some(Position::e3()))
+            .expect("e3 is a valid target with black to move");
+
+        assert_eq!(board.piece_at(&Position::e2()), None);
+        assert_eq!(
+            board.piece_at(&Position::e4()),
+            Some(&Piece::new(PieceType::Pawn, Side::White))
+        );
+        assert!(!board.get_white_positions().contains(&Position::e2()));
+        assert!(board.get_white_positions().contains(&Position::e4()));
+        assert!(!board.get_black_positions().contains(&Position::d7()));
+
+        let board_fen = fen::generate(&board);
+        assert_eq!(
+            board_fen,
+            "rnbqkbnr/ppp1pppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        assert_eq!(fen::generate(&fen::parse(&board_fen)?), board_fen);
+
+        // Moving a rook away revokes the matching castle right.
+        board.move_piece_raw(&Position::h1(), &Position::h3());
+        assert!(board.check_consistency().is_ok());
+        assert_eq!(
+            *board.get_castle_rights(),
+            CastleRights::new(false, true, true, true)
+        );
+        assert!(board
+            .set_castle_rights(CastleRights::new(true, true, true, true))
+            .is_err());
+
+        // Moving a king away revokes both of that side's rights.
+        board.move_piece_raw(&Position::e8(), &Position::e7());
+        assert!(board.check_consistency().is_ok());
+        assert_eq!(
+            *board.get_castle_rights(),
+            CastleRights::new(false, true, false, false)
+        );
+        assert!(board
+            .set_castle_rights(CastleRights::new(false, true, false, true))
+            .is_err());
+        assert!(board
+            .set_castle_rights(CastleRights::new(false, true, true, false))
+            .is_err());
+
+        // En passant targets must be on the rank behind the opponent's double move.
+        assert!(board.set_en_passant_target(Some(Position::e6())).is_err());
+        assert!(board.set_en_passant_target(Some(Position::e4())).is_err());
+        assert_eq!(board.en_passant_target(), Some(Position::e3()));
+
+        // Handing the move back to White drops the target White's own pawn left behind.
+        board.set_turn(Side::White);
+        assert!(board.check_consistency().is_ok());
+        assert_eq!(board.en_passant_target(), None);
+        assert!(board.set_en_passant_target(Some(Position::e3())).is_err());
+        assert!(board.set_en_passant_target(Some(Position::d6())).is_err());
+        board.add_piece(&Position::d5(), Piece::new(PieceType::Pawn, Side::Black));
+        assert!(board.set_en_passant_target(Some(Position::d6())).is_ok());
+        assert!(board.check_consistency().is_ok());
+        assert!(board.set_en_passant_target(None).is_ok());
+        assert_eq!(board.en_passant_target(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn occupancy_sets_test() -> Result<(), ParseError> {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+
+        for board_fen in fens {
+            let mut board = fen::parse(board_fen)?;
+            board.move_piece_raw(&Position::a1(), &Position::a2());
+
+            for value in 0..NUM_SQUARES {
+                let position = Position::from_index(value).unwrap();
+                let side = board.piece_at(&position).map(|piece| piece.side);
+                assert_eq!(
+                    board.get_white_positions().contains(&position),
+                    side == Some(Side::White)
+                );
+                assert_eq!(
+                    board.get_black_positions().contains(&position),
+                    side == Some(Side::Black)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_moves_test() -> Result<(), ParseError> {
+        let board = Board::from_moves(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5"])?;
+        assert_eq!(
+            fen::generate(&board),
+            "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"
+        );
+
+        let error = Board::from_moves(&["e4", "e5", "Nf3", "Nc6", "Ke3"]).unwrap_err();
+        assert_eq!(error.to_string(), "Move 4 (Ke3): Illegal move.");
+
+        assert_eq!(Board::from_moves(&[])?, Board::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn startpos_test() -> Result<(), ParseError> {
+        let startpos = fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")?;
+
+        assert_eq!(Board::startpos(), startpos);
+        assert_eq!(Board::default(), startpos);
+        assert_eq!(Board::startpos().get_white_positions().len(), 16);
+        assert_eq!(Board::startpos().get_black_positions().len(), 16);
+
+        Ok(())
+    }
+
+    #[test]
+    fn castle_squares_test() {
+        assert_eq!(
+            castle_squares(&Side::White, CastleSide::Short),
+            (
+                &[Position::f1(), Position::g1()][..],
+                &[Position::f1(), Position::g1()][..]
+            )
+        );
+        assert_eq!(
+            castle_squares(&Side::White, CastleSide::Long),
+            (
+                &[Position::d1(), Position::c1(), Position::b1()][..],
+                &[Position::d1(), Position::c1()][..]
+            )
+        );
+        assert_eq!(
+            castle_squares(&Side::Black, CastleSide::Short),
+            (
+                &[Position::f8(), Position::g8()][..],
+                &[Position::f8(), Position::g8()][..]
+            )
+        );
+        assert_eq!(
+            castle_squares(&Side::Black, CastleSide::Long),
+            (
+                &[Position::d8(), Position::c8(), Position::b8()][..],
+                &[Position::d8(), Position::c8()][..]
+            )
+        );
+    }
+
+    #[test]
+    fn can_castle_test() -> Result<(), ParseError> {
+        let castle_moves = |board: &Board| {
+            get_all_legal_moves(board, &board.side_to_move())
+                .get(&Position::e1())
+                .map(|moves| {
+                    (
+                        moves.get(&Position::g1()) == Some(&MoveKind::ShortCastle),
+                        moves.get(&Position::c1()) == Some(&MoveKind::LongCastle),
+                    )
+                })
+                .unwrap_or((false, false))
+        };
+
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        assert_eq!(
+            board.can_castle(&Side::White, false),
+            CastleAvailability::Available
+        );
+        assert_eq!(
+            board.can_castle(&Side::White, true),
+            CastleAvailability::Available
+        );
+        assert_eq!(
+            board.can_castle(&Side::Black, true),
+            CastleAvailability::Available
+        );
+        assert_eq!(castle_moves(&board), (true, true));
+
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1")?;
+        assert_eq!(
+            board.can_castle(&Side::White, false),
+            CastleAvailability::NoRights
+        );
+        assert_eq!(castle_moves(&board), (false, true));
+
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/RN2K1NR w KQkq - 0 1")?;
+        assert_eq!(
+            board.can_castle(&Side::White, false),
+            CastleAvailability::BlockedBy(Position::g1())
+        );
+        // b1 doesn't need to be crossed by the king, but it still has to be empty.
+        assert_eq!(
+            board.can_castle(&Side::White, true),
+            CastleAvailability::BlockedBy(Position::b1())
+        );
+        assert_eq!(castle_moves(&board), (false, false));
+
+        let board = fen::parse("r3k2r/8/8/8/8/8/5q2/R3K2R w KQkq - 0 1")?;
+        assert_eq!(
+            board.can_castle(&Side::White, false),
+            CastleAvailability::InCheck
+        );
+        assert_eq!(castle_moves(&board), (false, false));
+
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        let mut through_check = board.clone();
+        through_check.add_piece(&Position::f5(), Piece::new(PieceType::Rook, Side::Black));
+        through_check.add_piece(&Position::c5(), Piece::new(PieceType::Rook, Side::Black));
+        assert_eq!(
+            through_check.can_castle(&Side::White, false),
+            CastleAvailability::ThroughCheck(Position::f1())
+        );
+        assert_eq!(
+            through_check.can_castle(&Side::White, true),
+            CastleAvailability::ThroughCheck(Position::c1())
+        );
+        assert_eq!(castle_moves(&through_check), (false, false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn en_passant_capturers_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/8/8/3PpP2/8/8/8/4K3 w - e6 0 1")?;
+        assert_eq!(
+            board.en_passant_capturers(),
+            vec![Position::d5(), Position::f5()]
+        );
+
+        // The d5 pawn is pinned along the diagonal, so it can't take.
+        let board = fen::parse("b3k3/8/8/3PpP2/8/8/6K1/8 w - e6 0 1")?;
+        assert_eq!(board.en_passant_capturers(), vec![Position::f5()]);
+
+        let board = fen::parse("4k3/8/8/3Pp3/8/8/8/4K3 w - - 0 1")?;
+        assert!(board.en_passant_capturers().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn castle_rights_test() {
+        let mut castle_rights = CastleRights::new(true, true, true, true);
+        assert_eq!(castle_rights.to_string(), "KQkq");
+        assert!(castle_rights.rights_for(&Side::Black).get(CastleSide::Long));
+
+        castle_rights.revoke(&Side::White, CastleSide::Long);
+        assert_eq!(
+            castle_rights.rights_for(&Side::White),
+            &SideCastleRights {
+                short: true,
+                long: false
+            }
+        );
+        assert_eq!(castle_rights.to_string(), "Kkq");
+
+        castle_rights.revoke_all(&Side::Black);
+        assert_eq!(castle_rights.to_string(), "K");
+        assert!(castle_rights.any());
+
+        castle_rights.revoke(&Side::White, CastleSide::Short);
+        assert!(!castle_rights.any());
+        assert_eq!(castle_rights.to_string(), "-");
+        assert_eq!(castle_rights, CastleRights::new(false, false, false, false));
+    }
+
+    #[test]
+    fn file_state_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/p1p5/8/8/8/8/2P1P3/4K3 w - - 0 1")?;
+        assert_eq!(
+            board.file_state(file::A),
+            FileState::HalfOpenFor(Side::White)
+        );
+        assert_eq!(board.file_state(file::B), FileState::Open);
+        assert_eq!(board.file_state(file::C), FileState::Closed);
+        assert_eq!(
+            board.file_state(file::E),
+            FileState::HalfOpenFor(Side::Black)
+        );
+
+        // Only pawns close a file.
+        let board = fen::parse("4k3/4r3/8/8/8/8/8/4K3 w - - 0 1")?;
+        assert_eq!(board.file_state(file::E), FileState::Open);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rooks_connected_test() -> Result<(), ParseError> {
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        assert!(!board.rooks_connected(&Side::White));
+        assert!(!board.rooks_connected(&Side::Black));
+
+        let board = fen::parse("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1")?;
+        assert!(board.rooks_connected(&Side::White));
+
+        let board = fen::parse("4k3/8/8/2r5/8/8/2r5/4K3 w - - 0 1")?;
+        assert!(board.rooks_connected(&Side::Black));
+
+        // Rooks on different ranks and files never defend each other.
+        let board = fen::parse("4k3/8/8/8/8/8/1R6/R3K3 w - - 0 1")?;
+        assert!(!board.rooks_connected(&Side::White));
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_occupied_test() {
+        let board = Board::startpos();
+        assert!(board.is_occupied(&Position::e2()));
+        assert!(!board.is_occupied(&Position::e4()));
+
+        assert!(board.is_occupied_by(&Position::e2(), &Side::White));
+        assert!(!board.is_occupied_by(&Position::e2(), &Side::Black));
+        assert!(board.is_occupied_by(&Position::e8(), &Side::Black));
+        assert!(!board.is_occupied_by(&Position::e4(), &Side::White));
+        assert!(!board.is_occupied_by(&Position::e4(), &Side::Black));
+    }
+
+    #[test]
+    fn king_position_test() -> Result<(), ParseError> {
+        let board = Board::startpos();
+        assert_eq!(board.king_position(&Side::White), Some(Position::e1()));
+        assert_eq!(board.king_position(&Side::Black), Some(Position::e8()));
+
+        let board = fen::parse("8/8/8/8/8/8/8/4K3 w - - 0 1")?;
+        assert_eq!(board.king_position(&Side::White), Some(Position::e1()));
+        assert_eq!(board.king_position(&Side::Black), None);
+        assert!(!board.has_king(&Side::Black));
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_occupancy_shims_test() {
+        let board = Board::startpos();
+        assert!(super::contains_piece(&board, &Position::e2()));
+        assert!(!super::contains_piece(&board, &Position::e4()));
+        assert!(super::contains_enemy_piece(
+            &board,
+            &Position::e7(),
+            &Side::White
+        ));
+        assert!(!super::contains_enemy_piece(
+            &board,
+            &Position::e2(),
+            &Side::White
+        ));
+        assert!(super::are_positions_empty(
+            &board,
+            &[Position::e3(), Position::e4()]
+        ));
+        assert!(!super::are_positions_empty(
+            &board,
+            &[Position::e2(), Position::e4()]
+        ));
+    }
+
+    #[test]
+    fn is_empty_between_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/8/8/3p4/8/8/8/R3K2R w KQ - 0 1")?;
+
+        // Along a rank, in either direction.
+        assert!(board.is_empty_between(&Position::e1(), &Position::h1()));
+        assert!(board.is_empty_between(&Position::h1(), &Position::e1()));
+        assert!(!board.is_empty_between(&Position::a1(), &Position::h1()));
+
+        // Along a file and a diagonal, where d5 is in the way.
+        assert!(board.is_empty_between(&Position::d1(), &Position::d4()));
+        assert!(!board.is_empty_between(&Position::d1(), &Position::d8()));
+        assert!(board.is_empty_between(&Position::a2(), &Position::c4()));
+        assert!(!board.is_empty_between(&Position::a2(), &Position::e6()));
+
+        // Neighbouring squares have nothing between them.
+        assert!(board.is_empty_between(&Position::e1(), &Position::f2()));
+
+        // Squares that don't share a line are never clear, nor is a square with itself.
+        assert!(!board.is_empty_between(&Position::a1(), &Position::b3()));
+        assert!(!board.is_empty_between(&Position::c1(), &Position::h8()));
+        assert!(!board.is_empty_between(&Position::e1(), &Position::e1()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn occupied_count_test() -> Result<(), ParseError> {
+        assert_eq!(Board::startpos().occupied_count(), 32);
+        assert_eq!(Board::empty().occupied_count(), 0);
+
+        let mut board = fen::parse("4k3/8/8/p7/8/8/8/R3K3 w - - 0 1")?;
+        assert_eq!(board.occupied_count(), 4);
+
+        move_piece(&mut board, MoveRequest::new(Position::a1(), Position::a5())).unwrap();
+        assert_eq!(board.occupied_count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn last_move_test() -> Result<(), ParseError> {
+        let mut board = fen::parse("r3k2r/8/8/8/8/8/4P3/R3K2R w KQkq - 0 1")?;
+        assert_eq!(board.last_move(), None);
+
+        let (_, move_undo) =
+            make_move(&mut board, MoveRequest::new(Position::e2(), Position::e4())).unwrap();
+        assert_eq!(board.last_move(), Some((Position::e2(), Position::e4())));
+
+        // Castling reports the king's move.
+        move_piece(&mut board, MoveRequest::new(Position::e8(), Position::c8())).unwrap();
+        assert_eq!(board.last_move(), Some((Position::e8(), Position::c8())));
+
+        // The last move doesn't take part in comparisons or the FEN.
+        let parsed = fen::parse(&fen::generate(&board))?;
+        assert_eq!(parsed.last_move(), None);
+        assert_eq!(parsed, board);
+
+        let mut board = fen::parse("r3k2r/8/8/8/8/8/4P3/R3K2R w KQkq - 0 1")?;
+        board.set_last_move(Some((Position::a1(), Position::a2())));
+        let (_, move_undo_after) =
+            make_move(&mut board, MoveRequest::new(Position::e2(), Position::e4())).unwrap();
+        board.undo(move_undo_after);
+        assert_eq!(board.last_move(), Some((Position::a1(), Position::a2())));
+        assert_eq!(move_undo.last_move, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn clone_for_simulation_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/8/8/3PpP2/8/8/8/4K3 w - e6 0 1")?;
+        let mut simulation = board.clone_for_simulation();
+        assert_eq!(simulation, board);
+
+        move_piece(
+            &mut simulation,
+            MoveRequest::new(Position::d5(), Position::e6()),
+        )
+        .unwrap();
+        assert_ne!(simulation, board);
+        assert_eq!(fen::generate(&board), "4k3/8/8/3PpP2/8/8/8/4K3 w - e6 0 1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn force_move_test() -> Result<(), ParseError> {
+        let mut board = fen::parse("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 4 10")?;
+
+        // A rook jumps over the king and takes its own side's rook, which no legal move could do.
+        let captured = board.force_move(&Position::a1(), &Position::h1(), None);
+        assert_eq!(captured, Some(Piece::new(PieceType::Rook, Side::White)));
+        assert_eq!(board.last_move(), Some((Position::a1(), Position::h1())));
+        assert!(board.check_consistency().is_ok());
+        assert_eq!(
+            fen::generate(&board),
+            "r3k2r/8/8/3pP3/8/8/8/4K2R b Kkq - 0 10"
+        );
+
+        // Black's king steps aside, losing both its castle rights.
+        assert_eq!(
+            board.force_move(&Position::e8(), &Position::e7(), None),
+            None
+        );
+        assert!(board.check_consistency().is_ok());
+        assert_eq!(
+            fen::generate(&board),
+            "r6r/4k3/8/3pP3/8/8/8/4K2R w K - 1 11"
+        );
+
+        // A pawn captures sideways, then promotes out of turn from the middle of the board.
+        let captured = board.force_move(&Position::e5(), &Position::d5(), None);
+        assert_eq!(captured, Some(Piece::new(PieceType::Pawn, Side::Black)));
+        board.force_move(
+            &Position::d5(),
+            &Position::d8(),
+            Some(PromotionType::Knight),
+        );
+        assert!(board.check_consistency().is_ok());
+        assert_eq!(fen::generate(&board), "r2N3r/4k3/8/8/8/8/8/4K2R w K - 0 12");
+
+        // Nothing happens without a piece to move.
+        let before = board.clone();
+        assert_eq!(
+            board.force_move(&Position::e4(), &Position::e5(), None),
+            None
+        );
+        assert_boards_eq!(board, before);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ply_test() -> Result<(), ParseError> {
+        assert_eq!(Board::startpos().ply(), 0);
+        assert_eq!(Board::from_moves(&["e4"])?.ply(), 1);
+        assert_eq!(Board::from_moves(&["e4", "e5", "Nf3"])?.ply(), 3);
+        assert_eq!(fen::parse("4k3/8/8/8/8/8/8/4K3 b - - 0 40")?.ply(), 79);
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_consistency_test() -> Result<(), ParseError> {
+        assert!(Board::startpos().check_consistency().is_ok());
+
+        let mut board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        board.positions[Position::h1().value()] = None;
+        assert!(board.check_consistency().is_err());
+
+        board.white_positions.remove(&Position::h1());
+        assert_eq!(
+            board.check_consistency().unwrap_err().to_string(),
+            "White short castle rights require a king on e1 and a rook on h1."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn relative_planes_test() -> Result<(), ParseError> {
+        let planes = Board::default().to_relative_planes();
+        assert_eq!(planes[0][PieceType::Pawn as usize], 0xFF00);
+        assert_eq!(planes[1][PieceType::Pawn as usize], 0x00FF_0000_0000_0000);
+
+        // With Black to move, Black's pieces are seen from rank one.
+        let board = fen::parse("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")?;
+        let planes = board.to_relative_planes();
+        assert_eq!(planes[0][PieceType::Pawn as usize], 0xFF00);
+        assert_eq!(planes[1][PieceType::Pawn as usize], 0x00EF_0010_0000_0000);
+        assert_eq!(
+            planes[0][PieceType::King as usize],
+            1 << Position::e1().value()
+        );
+
+        for board_fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        ] {
+            let board = fen::parse(board_fen)?;
+            let side = board.side_to_move();
+            let planes = board.to_relative_planes();
+
+            let count = |owner: usize| -> u32 {
+                planes[owner].iter().map(|plane| plane.count_ones()).sum()
+            };
+            let own_count = match side {
+                Side::White => board.get_white_positions().len(),
+                Side::Black => board.get_black_positions().len(),
+            };
+            let opponent_count = match side {
+                Side::White => board.get_black_positions().len(),
+                Side::Black => board.get_white_positions().len(),
+            };
+            assert_eq!(count(0) as usize, own_count);
+            assert_eq!(count(1) as usize, opponent_count);
+
+            // Every bit maps back to a piece of the right type and owner.
+            for (owner, owner_planes) in planes.iter().enumerate() {
+                for (piece_type, plane) in owner_planes.iter().enumerate() {
+                    for index in (0..NUM_SQUARES).filter(|index| plane & (1 << index) != 0) {
+                        let position = Position::from_index(index).unwrap().relative_to(&side);
+                        let piece = board.piece_at(&position).unwrap();
+                        assert_eq!(piece.piece_type as usize, piece_type);
+                        assert_eq!(piece.side == side, owner == 0);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn en_passant_target_validation_test() -> Result<(), ParseError> {
+        let pieces = vec![
+            piece_position!(e1, King, White),
+            piece_position!(e8, King, Black),
+            piece_position!(e4, Pawn, White),
+            piece_position!(d4, Pawn, Black),
+        ];
+        let no_castle_rights = CastleRights::new(false, false, false, false);
+
+        // Valid target behind a pawn that just double moved.
+        {
+            let board = fen::parse("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1")?;
+            assert_eq!(board.en_passant_target(), Some(Position::e3()));
+
+            let board = Board::try_new(
+                pieces.clone(),
+                Side::Black,
+                no_castle_rights,
+                Some(Position::e3()),
+                0,
+                1,
+            );
+            assert!(board.is_ok());
+        }
+
+        // Target on the wrong rank for the side to move.
+        {
+            assert!(fen::parse("4k3/8/8/8/3pP3/8/8/4K3 w - e3 0 1").is_err());
+
+            let board = Board::try_new(
+                pieces.clone(),
+                Side::White,
+                no_castle_rights,
+                Some(Position::e3()),
+                0,
+                1,
+            );
+            assert!(board.is_err());
+
+            let board = Board::new(
+                pieces.clone(),
+                Side::White,
+                no_castle_rights,
+                Some(Position::e3()),
+                0,
+                1,
+            );
+            assert_eq!(board.en_passant_target(), None);
+        }
+
+        // No pawn in front of the target.
+        {
+            assert!(fen::parse("4k3/8/8/8/3pP3/8/8/4K3 b - d3 0 1").is_err());
+
+            let board = Board::try_new(
+                pieces.clone(),
+                Side::Black,
+                no_castle_rights,
+                Some(Position::d3()),
+                0,
+                1,
+            );
+            assert!(board.is_err());
+
+            let board = Board::new(
+                pieces,
+                Side::Black,
+                no_castle_rights,
+                Some(Position::d3()),
+                0,
+                1,
+            );
+            assert_eq!(board.en_passant_target(), None);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,137 @@
+use super::{file, position::Position, rank, Board};
+
+// Both boards side by side, with the squares that differ in braces instead of brackets, followed
+// by any of the fields outside the squares that differ. Meant for test failure output, where the
+// Debug output of two boards is too long to compare by eye.
+pub fn diff_display(left: &Board, right: &Board) -> String {
+    let mut display = String::new();
+
+    for rank in (rank::ONE..=rank::EIGHT).rev() {
+        let left_rank = rank_display(left, right, rank);
+        let right_rank = rank_display(right, left, rank);
+        let rank_char = rank::to_char(rank);
+        display.push_str(&format!(
+            "{rank_char} {left_rank}   {rank_char} {right_rank}\n"
+        ));
+    }
+
+    let files: String = (file::A..=file::H)
+        .map(|file| format!(" {} ", file::to_char(file)))
+        .collect();
+    display.push_str(&format!("  {files}     {files}"));
+
+    let fields = [
+        (
+            "turn",
+            left.side_to_move().to_string(),
+            right.side_to_move().to_string(),
+        ),
+        (
+            "castle rights",
+            left.get_castle_rights().to_string(),
+            right.get_castle_rights().to_string(),
+        ),
+        (
+            "en passant",
+            en_passant_display(left),
+            en_passant_display(right),
+        ),
+        (
+            "half moves",
+            left.get_half_moves().to_string(),
+            right.get_half_moves().to_string(),
+        ),
+        (
+            "full moves",
+            left.get_full_moves().to_string(),
+            right.get_full_moves().to_string(),
+        ),
+    ];
+    for (name, left_value, right_value) in fields {
+        if left_value != right_value {
+            display.push_str(&format!("\n{name}: {left_value} != {right_value}"));
+        }
+    }
+
+    display
+}
+
+fn rank_display(board: &Board, other: &Board, rank: usize) -> String {
+    (file::A..=file::H)
+        .map(|file| {
+            let position = Position::new_unchecked(file, rank);
+            let piece = board.piece_at(&position);
+            let piece_notation = piece.map_or(String::from(" "), |piece| piece.to_string());
+            if piece == other.piece_at(&position) {
+                format!("[{piece_notation}]")
+            } else {
+                format!("{{{piece_notation}}}")
+            }
+        })
+        .collect()
+}
+
+fn en_passant_display(board: &Board) -> String {
+    board
+        .en_passant_target()
+        .map_or(String::from("-"), |position| position.to_string())
+}
+
+// Like assert_eq! for boards, but a failure shows the boards side by side with diff_display.
+#[cfg(any(test, feature = "test-util"))]
+#[macro_export]
+macro_rules! assert_boards_eq {
+    ( $left:expr, $right:expr $(,)? ) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    panic!(
+                        "assertion `left == right` failed for boards\n{}",
+                        $crate::board::diff_display(left, right)
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen, ParseError};
+
+    #[test]
+    fn diff_display_test() -> Result<(), ParseError> {
+        let before = Board::default();
+        let after = fen::parse("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")?;
+
+        let display = diff_display(&before, &after);
+        let lines: Vec<&str> = display.lines().collect();
+        assert_eq!(display.matches('{').count(), 4);
+        assert_eq!(
+            lines[4],
+            "4 [ ][ ][ ][ ]{ }[ ][ ][ ]   4 [ ][ ][ ][ ]{P}[ ][ ][ ]"
+        );
+        assert_eq!(
+            lines[6],
+            "2 [P][P][P][P]{P}[P][P][P]   2 [P][P][P][P]{ }[P][P][P]"
+        );
+        assert_eq!(lines[9], "turn: w != b");
+        assert_eq!(lines[10], "en passant: - != e3");
+        assert_eq!(lines.len(), 11);
+
+        // Equal boards have nothing marked and no fields listed.
+        let display = diff_display(&before, &Board::default());
+        assert!(!display.contains('{'));
+        assert_eq!(display.lines().count(), 9);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "half moves: 0 != 1")]
+    fn assert_boards_eq_test() {
+        let board = fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1 1").unwrap();
+        crate::assert_boards_eq!(Board::default(), board);
+    }
+}
@@ -7,7 +7,7 @@ pub const F: usize = 5;
 pub const G: usize = 6;
 pub const H: usize = 7;
 
-pub const LENGTH: usize = 8;
+pub const LENGTH: usize = super::geometry::BOARD_WIDTH;
 
 pub fn valid(file: i32) -> bool {
     file >= A as i32 && file <= H as i32
@@ -0,0 +1,48 @@
+use super::{file, rank};
+
+// Everything that depends on the board being 8x8 is derived from these.
+pub const BOARD_WIDTH: usize = 8;
+pub const NUM_SQUARES: usize = BOARD_WIDTH * BOARD_WIDTH;
+
+// Squares are numbered rank by rank from a1, so a1 is 0, h1 is 7 and h8 is 63.
+pub const fn square_index(file: usize, rank: usize) -> usize {
+    rank * BOARD_WIDTH + file
+}
+
+pub const fn square_file(index: usize) -> usize {
+    index % BOARD_WIDTH
+}
+
+pub const fn square_rank(index: usize) -> usize {
+    index / BOARD_WIDTH
+}
+
+pub const fn on_board(file: i32, rank: i32) -> bool {
+    file >= 0 && file < BOARD_WIDTH as i32 && rank >= 0 && rank < BOARD_WIDTH as i32
+}
+
+// A SquareSet keeps one bit per square in a u64.
+const _: () = assert!(NUM_SQUARES <= u64::BITS as usize);
+const _: () = assert!(square_index(file::H, rank::EIGHT) == NUM_SQUARES - 1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_index_test() {
+        assert_eq!(square_index(file::A, rank::ONE), 0);
+        assert_eq!(square_index(file::H, rank::ONE), 7);
+        assert_eq!(square_index(file::A, rank::TWO), 8);
+        assert_eq!(square_index(file::H, rank::EIGHT), NUM_SQUARES - 1);
+
+        for index in 0..NUM_SQUARES {
+            assert_eq!(square_index(square_file(index), square_rank(index)), index);
+        }
+
+        assert!(on_board(0, 0));
+        assert!(on_board(7, 7));
+        assert!(!on_board(-1, 0));
+        assert!(!on_board(0, 8));
+    }
+}
@@ -1,5 +1,9 @@
+use std::ops::Add;
+
 use super::file;
+use super::geometry;
 use super::rank;
+use crate::piece::Side;
 
 pub const A1: usize = 0;
 pub const B1: usize = 1;
@@ -66,31 +70,111 @@ pub const F8: usize = 61;
 pub const G8: usize = 62;
 pub const H8: usize = 63;
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub struct Position(usize);
 
 impl Position {
-    pub fn from_file_and_rank(file: usize, rank: usize) -> Position {
-        if !file::valid(file as i32) || !rank::valid(rank as i32) {
-            panic!("Passed an invalid file or rank value into from_file_and_rank().");
+    pub const A1: Position = Position(A1);
+    pub const B1: Position = Position(B1);
+    pub const C1: Position = Position(C1);
+    pub const D1: Position = Position(D1);
+    pub const E1: Position = Position(E1);
+    pub const F1: Position = Position(F1);
+    pub const G1: Position = Position(G1);
+    pub const H1: Position = Position(H1);
+    pub const A2: Position = Position(A2);
+    pub const B2: Position = Position(B2);
+    pub const C2: Position = Position(C2);
+    pub const D2: Position = Position(D2);
+    pub const E2: Position = Position(E2);
+    pub const F2: Position = Position(F2);
+    pub const G2: Position = Position(G2);
+    pub const H2: Position = Position(H2);
+    pub const A3: Position = Position(A3);
+    pub const B3: Position = Position(B3);
+    pub const C3: Position = Position(C3);
+    pub const D3: Position = Position(D3);
+    pub const E3: Position = Position(E3);
+    pub const F3: Position = Position(F3);
+    pub const G3: Position = Position(G3);
+    pub const H3: Position = Position(H3);
+    pub const A4: Position = Position(A4);
+    pub const B4: Position = Position(B4);
+    pub const C4: Position = Position(C4);
+    pub const D4: Position = Position(D4);
+    pub const E4: Position = Position(E4);
+    pub const F4: Position = Position(F4);
+    pub const G4: Position = Position(G4);
+    pub const H4: Position = Position(H4);
+    pub const A5: Position = Position(A5);
+    pub const B5: Position = Position(B5);
+    pub const C5: Position = Position(C5);
+    pub const D5: Position = Position(D5);
+    pub const E5: Position = Position(E5);
+    pub const F5: Position = Position(F5);
+    pub const G5: Position = Position(G5);
+    pub const H5: Position = Position(H5);
+    pub const A6: Position = Position(A6);
+    pub const B6: Position = Position(B6);
+    pub const C6: Position = Position(C6);
+    pub const D6: Position = Position(D6);
+    pub const E6: Position = Position(E6);
+    pub const F6: Position = Position(F6);
+    pub const G6: Position = Position(G6);
+    pub const H6: Position = Position(H6);
+    pub const A7: Position = Position(A7);
+    pub const B7: Position = Position(B7);
+    pub const C7: Position = Position(C7);
+    pub const D7: Position = Position(D7);
+    pub const E7: Position = Position(E7);
+    pub const F7: Position = Position(F7);
+    pub const G7: Position = Position(G7);
+    pub const H7: Position = Position(H7);
+    pub const A8: Position = Position(A8);
+    pub const B8: Position = Position(B8);
+    pub const C8: Position = Position(C8);
+    pub const D8: Position = Position(D8);
+    pub const E8: Position = Position(E8);
+    pub const F8: Position = Position(F8);
+    pub const G8: Position = Position(G8);
+    pub const H8: Position = Position(H8);
+
+    pub const fn try_new(file: usize, rank: usize) -> Option<Position> {
+        if file >= file::LENGTH || rank >= rank::LENGTH {
+            return None;
+        }
+
+        Some(Position(geometry::square_index(file, rank)))
+    }
+
+    pub const fn from_index(index: usize) -> Option<Position> {
+        match Position::try_new(geometry::square_file(index), geometry::square_rank(index)) {
+            Some(position) if position.value() == index => Some(position),
+            _ => None,
+        }
+    }
+
+    // For callers that have already checked the file and rank are on the board.
+    pub const fn new_unchecked(file: usize, rank: usize) -> Position {
+        match Position::try_new(file, rank) {
+            Some(position) => position,
+            None => panic!("Passed an invalid file or rank value into new_unchecked()."),
         }
+    }
 
-        let position = (rank * 8) + file;
-        Position(position)
+    pub const fn from_file_and_rank(file: usize, rank: usize) -> Position {
+        Position::new_unchecked(file, rank)
     }
 
     pub fn from_offset(start: &Position, offset: &Offset) -> Option<Position> {
         let new_file = start.file() as i32 + offset.file_offset;
         let new_rank = start.rank() as i32 + offset.rank_offset;
 
-        if !file::valid(new_file) || !rank::valid(new_rank) {
+        if !geometry::on_board(new_file, new_rank) {
             return None;
         }
 
-        Some(Position::from_file_and_rank(
-            new_file as usize,
-            new_rank as usize,
-        ))
+        Position::try_new(new_file as usize, new_rank as usize)
     }
 
     pub fn from_notation(position: &str) -> Option<Position> {
@@ -104,21 +188,43 @@ impl Position {
         let rank: char = position.chars().nth(1).unwrap();
 
         match (file::from_char(file), rank::from_char(rank)) {
-            (Some(file), Some(rank)) => Some(Position::from_file_and_rank(file, rank)),
+            (Some(file), Some(rank)) => Position::try_new(file, rank),
             _ => None,
         }
     }
 
-    pub fn value(&self) -> usize {
+    pub const fn value(&self) -> usize {
         self.0
     }
 
-    pub fn rank(&self) -> usize {
-        self.0 / rank::LENGTH
+    pub const fn rank(&self) -> usize {
+        geometry::square_rank(self.0)
+    }
+
+    pub const fn file(&self) -> usize {
+        geometry::square_file(self.0)
+    }
+
+    // The square a pawn on `pawn` would promote on, straight ahead on its file.
+    pub fn promotion_square(pawn: &Position, side: &Side) -> Position {
+        Position::new_unchecked(pawn.file(), side.promotion_rank())
+    }
+
+    // The square as seen from `side`'s end of the board: unchanged for White, mirrored top to
+    // bottom for Black, so e2 becomes e7 and a1 becomes a8. Files are never swapped. Mirroring is
+    // its own inverse, so the same call turns a relative square back into an absolute one.
+    pub fn relative_to(&self, side: &Side) -> Position {
+        match side {
+            Side::White => *self,
+            Side::Black => Position::new_unchecked(self.file(), rank::EIGHT - self.rank()),
+        }
     }
 
-    pub fn file(&self) -> usize {
-        self.0 % file::LENGTH
+    // The number of king moves between two squares.
+    pub fn chebyshev_distance(&self, other: &Position) -> usize {
+        self.file()
+            .abs_diff(other.file())
+            .max(self.rank().abs_diff(other.rank()))
     }
 }
 
@@ -133,213 +239,258 @@ impl std::fmt::Display for Position {
     }
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct Offset {
     pub file_offset: i32,
     pub rank_offset: i32,
 }
 
 impl Offset {
-    pub fn new(file_offset: i32, rank_offset: i32) -> Offset {
+    pub const NORTH: Offset = Offset::new(0, 1);
+    pub const NORTH_EAST: Offset = Offset::new(1, 1);
+    pub const EAST: Offset = Offset::new(1, 0);
+    pub const SOUTH_EAST: Offset = Offset::new(1, -1);
+    pub const SOUTH: Offset = Offset::new(0, -1);
+    pub const SOUTH_WEST: Offset = Offset::new(-1, -1);
+    pub const WEST: Offset = Offset::new(-1, 0);
+    pub const NORTH_WEST: Offset = Offset::new(-1, 1);
+
+    pub const ORTHOGONAL: [Offset; 4] = [Offset::NORTH, Offset::EAST, Offset::SOUTH, Offset::WEST];
+    pub const DIAGONAL: [Offset; 4] = [
+        Offset::NORTH_EAST,
+        Offset::SOUTH_EAST,
+        Offset::SOUTH_WEST,
+        Offset::NORTH_WEST,
+    ];
+
+    pub const fn new(file_offset: i32, rank_offset: i32) -> Offset {
         Offset {
             file_offset,
             rank_offset,
         }
     }
+
+    pub fn scaled(self, n: i32) -> Offset {
+        Offset::new(self.file_offset * n, self.rank_offset * n)
+    }
+
+    // Offsets are written from White's point of view, so Black's moves are mirrored vertically.
+    pub fn for_side(self, side: &Side) -> Offset {
+        match side {
+            Side::White => self,
+            Side::Black => Offset::new(self.file_offset, -self.rank_offset),
+        }
+    }
+}
+
+impl Add<Offset> for Position {
+    type Output = Option<Position>;
+
+    fn add(self, offset: Offset) -> Option<Position> {
+        Position::from_offset(&self, &offset)
+    }
+}
+
+impl Add<Offset> for &Position {
+    type Output = Option<Position>;
+
+    fn add(self, offset: Offset) -> Option<Position> {
+        Position::from_offset(self, &offset)
+    }
 }
 
 impl Position {
-    pub fn a1() -> Position {
-        Position(A1)
+    pub const fn a1() -> Position {
+        Position::A1
     }
-    pub fn a2() -> Position {
-        Position(A2)
+    pub const fn a2() -> Position {
+        Position::A2
     }
-    pub fn a3() -> Position {
-        Position(A3)
+    pub const fn a3() -> Position {
+        Position::A3
     }
-    pub fn a4() -> Position {
-        Position(A4)
+    pub const fn a4() -> Position {
+        Position::A4
     }
-    pub fn a5() -> Position {
-        Position(A5)
+    pub const fn a5() -> Position {
+        Position::A5
     }
-    pub fn a6() -> Position {
-        Position(A6)
+    pub const fn a6() -> Position {
+        Position::A6
     }
-    pub fn a7() -> Position {
-        Position(A7)
+    pub const fn a7() -> Position {
+        Position::A7
     }
-    pub fn a8() -> Position {
-        Position(A8)
+    pub const fn a8() -> Position {
+        Position::A8
     }
-    pub fn b1() -> Position {
-        Position(B1)
+    pub const fn b1() -> Position {
+        Position::B1
     }
-    pub fn b2() -> Position {
-        Position(B2)
+    pub const fn b2() -> Position {
+        Position::B2
     }
-    pub fn b3() -> Position {
-        Position(B3)
+    pub const fn b3() -> Position {
+        Position::B3
     }
-    pub fn b4() -> Position {
-        Position(B4)
+    pub const fn b4() -> Position {
+        Position::B4
     }
-    pub fn b5() -> Position {
-        Position(B5)
+    pub const fn b5() -> Position {
+        Position::B5
     }
-    pub fn b6() -> Position {
-        Position(B6)
+    pub const fn b6() -> Position {
+        Position::B6
     }
-    pub fn b7() -> Position {
-        Position(B7)
+    pub const fn b7() -> Position {
+        Position::B7
     }
-    pub fn b8() -> Position {
-        Position(B8)
+    pub const fn b8() -> Position {
+        Position::B8
     }
-    pub fn c1() -> Position {
-        Position(C1)
+    pub const fn c1() -> Position {
+        Position::C1
     }
-    pub fn c2() -> Position {
-        Position(C2)
+    pub const fn c2() -> Position {
+        Position::C2
     }
-    pub fn c3() -> Position {
-        Position(C3)
+    pub const fn c3() -> Position {
+        Position::C3
     }
-    pub fn c4() -> Position {
-        Position(C4)
+    pub const fn c4() -> Position {
+        Position::C4
     }
-    pub fn c5() -> Position {
-        Position(C5)
+    pub const fn c5() -> Position {
+        Position::C5
     }
-    pub fn c6() -> Position {
-        Position(C6)
+    pub const fn c6() -> Position {
+        Position::C6
     }
-    pub fn c7() -> Position {
-        Position(C7)
+    pub const fn c7() -> Position {
+        Position::C7
     }
-    pub fn c8() -> Position {
-        Position(C8)
+    pub const fn c8() -> Position {
+        Position::C8
     }
-    pub fn d1() -> Position {
-        Position(D1)
+    pub const fn d1() -> Position {
+        Position::D1
     }
-    pub fn d2() -> Position {
-        Position(D2)
+    pub const fn d2() -> Position {
+        Position::D2
     }
-    pub fn d3() -> Position {
-        Position(D3)
+    pub const fn d3() -> Position {
+        Position::D3
     }
-    pub fn d4() -> Position {
-        Position(D4)
+    pub const fn d4() -> Position {
+        Position::D4
     }
-    pub fn d5() -> Position {
-        Position(D5)
+    pub const fn d5() -> Position {
+        Position::D5
     }
-    pub fn d6() -> Position {
-        Position(D6)
+    pub const fn d6() -> Position {
+        Position::D6
     }
-    pub fn d7() -> Position {
-        Position(D7)
+    pub const fn d7() -> Position {
+        Position::D7
     }
-    pub fn d8() -> Position {
-        Position(D8)
+    pub const fn d8() -> Position {
+        Position::D8
     }
-    pub fn e1() -> Position {
-        Position(E1)
+    pub const fn e1() -> Position {
+        Position::E1
     }
-    pub fn e2() -> Position {
-        Position(E2)
+    pub const fn e2() -> Position {
+        Position::E2
     }
-    pub fn e3() -> Position {
-        Position(E3)
+    pub const fn e3() -> Position {
+        Position::E3
     }
-    pub fn e4() -> Position {
-        Position(E4)
+    pub const fn e4() -> Position {
+        Position::E4
     }
-    pub fn e5() -> Position {
-        Position(E5)
+    pub const fn e5() -> Position {
+        Position::E5
     }
-    pub fn e6() -> Position {
-        Position(E6)
+    pub const fn e6() -> Position {
+        Position::E6
     }
-    pub fn e7() -> Position {
-        Position(E7)
+    pub const fn e7() -> Position {
+        Position::E7
     }
-    pub fn e8() -> Position {
-        Position(E8)
+    pub const fn e8() -> Position {
+        Position::E8
     }
-    pub fn f1() -> Position {
-        Position(F1)
+    pub const fn f1() -> Position {
+        Position::F1
     }
-    pub fn f2() -> Position {
-        Position(F2)
+    pub const fn f2() -> Position {
+        Position::F2
     }
-    pub fn f3() -> Position {
-        Position(F3)
+    pub const fn f3() -> Position {
+        Position::F3
     }
-    pub fn f4() -> Position {
-        Position(F4)
+    pub const fn f4() -> Position {
+        Position::F4
     }
-    pub fn f5() -> Position {
-        Position(F5)
+    pub const fn f5() -> Position {
+        Position::F5
     }
-    pub fn f6() -> Position {
-        Position(F6)
+    pub const fn f6() -> Position {
+        Position::F6
     }
-    pub fn f7() -> Position {
-        Position(F7)
+    pub const fn f7() -> Position {
+        Position::F7
     }
-    pub fn f8() -> Position {
-        Position(F8)
+    pub const fn f8() -> Position {
+        Position::F8
     }
-    pub fn g1() -> Position {
-        Position(G1)
+    pub const fn g1() -> Position {
+        Position::G1
     }
-    pub fn g2() -> Position {
-        Position(G2)
+    pub const fn g2() -> Position {
+        Position::G2
     }
-    pub fn g3() -> Position {
-        Position(G3)
+    pub const fn g3() -> Position {
+        Position::G3
     }
-    pub fn g4() -> Position {
-        Position(G4)
+    pub const fn g4() -> Position {
+        Position::G4
     }
-    pub fn g5() -> Position {
-        Position(G5)
+    pub const fn g5() -> Position {
+        Position::G5
     }
-    pub fn g6() -> Position {
-        Position(G6)
+    pub const fn g6() -> Position {
+        Position::G6
     }
-    pub fn g7() -> Position {
-        Position(G7)
+    pub const fn g7() -> Position {
+        Position::G7
     }
-    pub fn g8() -> Position {
-        Position(G8)
+    pub const fn g8() -> Position {
+        Position::G8
     }
-    pub fn h1() -> Position {
-        Position(H1)
+    pub const fn h1() -> Position {
+        Position::H1
     }
-    pub fn h2() -> Position {
-        Position(H2)
+    pub const fn h2() -> Position {
+        Position::H2
     }
-    pub fn h3() -> Position {
-        Position(H3)
+    pub const fn h3() -> Position {
+        Position::H3
     }
-    pub fn h4() -> Position {
-        Position(H4)
+    pub const fn h4() -> Position {
+        Position::H4
     }
-    pub fn h5() -> Position {
-        Position(H5)
+    pub const fn h5() -> Position {
+        Position::H5
     }
-    pub fn h6() -> Position {
-        Position(H6)
+    pub const fn h6() -> Position {
+        Position::H6
     }
-    pub fn h7() -> Position {
-        Position(H7)
+    pub const fn h7() -> Position {
+        Position::H7
     }
-    pub fn h8() -> Position {
-        Position(H8)
+    pub const fn h8() -> Position {
+        Position::H8
     }
 }
 
@@ -384,6 +535,157 @@ mod tests {
         Position::from_file_and_rank(0, 8);
     }
 
+    #[test]
+    fn square_constants_test() {
+        const CORNERS: [Position; 4] = [
+            Position::A1,
+            Position::from_file_and_rank(file::H, rank::ONE),
+            Position::A8,
+            Position::H8,
+        ];
+        assert_eq!(CORNERS[1], Position::h1());
+        assert_eq!(
+            Position::from_index(Position::H8.value()),
+            Some(Position::H8)
+        );
+
+        for index in 0..geometry::NUM_SQUARES {
+            let position = Position::from_index(index).unwrap();
+            let constant = match position {
+                Position::E4 => "e4",
+                Position::D5 => "d5",
+                _ => continue,
+            };
+            assert_eq!(position.to_string(), constant);
+        }
+    }
+
+    #[test]
+    fn relative_to_test() {
+        assert_eq!(Position::e2().relative_to(&Side::White), Position::e2());
+        assert_eq!(Position::e2().relative_to(&Side::Black), Position::e7());
+        assert_eq!(Position::a1().relative_to(&Side::Black), Position::a8());
+
+        for index in 0..geometry::NUM_SQUARES {
+            let position = Position::from_index(index).unwrap();
+            let relative = position.relative_to(&Side::Black);
+            assert_eq!(relative.file(), position.file());
+            assert_eq!(relative.relative_to(&Side::Black), position);
+        }
+    }
+
+    #[test]
+    fn constructors_stay_on_board_test() {
+        let on_board = |position: Position| assert!(position.value() < geometry::NUM_SQUARES);
+
+        for file in 0..12 {
+            for rank in 0..12 {
+                Position::try_new(file, rank).map(on_board);
+            }
+        }
+
+        for index in 0..100 {
+            Position::from_index(index).map(on_board);
+        }
+
+        for file in 'a'..='j' {
+            for rank in '0'..='9' {
+                Position::from_notation(&format!("{file}{rank}")).map(on_board);
+            }
+        }
+
+        for index in 0..geometry::NUM_SQUARES {
+            let start = Position::from_index(index).unwrap();
+            for file_offset in -9..=9 {
+                for rank_offset in -9..=9 {
+                    let offset = Offset::new(file_offset, rank_offset);
+                    Position::from_offset(&start, &offset).map(on_board);
+                    (start + offset).map(on_board);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn try_new_test() {
+        assert_eq!(Position::try_new(file::A, rank::ONE), Some(Position::a1()));
+        assert_eq!(
+            Position::try_new(file::H, rank::EIGHT),
+            Some(Position::h8())
+        );
+        assert_eq!(Position::try_new(file::C, rank::SIX), Some(Position::c6()));
+        assert_eq!(Position::try_new(8, 0), None);
+        assert_eq!(Position::try_new(0, 8), None);
+        assert_eq!(Position::try_new(usize::MAX, usize::MAX), None);
+    }
+
+    #[test]
+    fn from_index_test() {
+        for index in 0..64 {
+            assert_eq!(Position::from_index(index).unwrap().value(), index);
+        }
+
+        assert_eq!(Position::from_index(42), Some(Position::c6()));
+        assert_eq!(Position::from_index(64), None);
+        assert_eq!(Position::from_index(usize::MAX), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_unchecked_invalid() {
+        Position::new_unchecked(8, 8);
+    }
+
+    #[test]
+    fn offset_helpers() {
+        assert_eq!(Offset::NORTH.scaled(2), Offset::new(0, 2));
+        assert_eq!(Offset::SOUTH_WEST.scaled(3), Offset::new(-3, -3));
+        assert_eq!(
+            Offset::NORTH_EAST.for_side(&Side::White),
+            Offset::NORTH_EAST
+        );
+        assert_eq!(
+            Offset::NORTH_EAST.for_side(&Side::Black),
+            Offset::SOUTH_EAST
+        );
+        assert_eq!(Offset::SOUTH.for_side(&Side::Black), Offset::NORTH);
+
+        assert_eq!(
+            Position::e2() + Offset::NORTH.scaled(2),
+            Some(Position::e4())
+        );
+        assert_eq!(
+            Position::e7() + Offset::NORTH.for_side(&Side::Black),
+            Some(Position::e6())
+        );
+        assert_eq!(Position::a1() + Offset::WEST, None);
+        assert_eq!(Position::h8() + Offset::NORTH_EAST, None);
+
+        for offset in Offset::ORTHOGONAL.iter().chain(Offset::DIAGONAL.iter()) {
+            assert_eq!(
+                Position::d4() + *offset,
+                Position::from_offset(&Position::d4(), offset)
+            );
+        }
+    }
+
+    #[test]
+    fn promotion_square_and_distance() {
+        assert_eq!(
+            Position::promotion_square(&Position::c2(), &Side::White),
+            Position::c8()
+        );
+        assert_eq!(
+            Position::promotion_square(&Position::c7(), &Side::Black),
+            Position::c1()
+        );
+
+        assert_eq!(Position::a1().chebyshev_distance(&Position::h8()), 7);
+        assert_eq!(Position::d6().chebyshev_distance(&Position::a8()), 3);
+        assert_eq!(Position::e4().chebyshev_distance(&Position::e4()), 0);
+        assert_eq!(Position::e4().chebyshev_distance(&Position::f6()), 2);
+    }
+
     #[test]
     fn from_offset() {
         // Valid forward file move
@@ -7,7 +7,7 @@ pub const SIX: usize = 5;
 pub const SEVEN: usize = 6;
 pub const EIGHT: usize = 7;
 
-pub const LENGTH: usize = 8;
+pub const LENGTH: usize = super::geometry::BOARD_WIDTH;
 
 pub fn valid(rank: i32) -> bool {
     rank >= ONE as i32 && rank <= EIGHT as i32
@@ -0,0 +1,127 @@
+use super::{
+    geometry::{self, NUM_SQUARES},
+    position::Offset,
+    square_set::SquareSet,
+};
+
+// Directions in the same order as the Offset constants, north first and clockwise from there.
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+// RAYS[direction][square] holds every square from `square` to the edge of the board in
+// `direction`, not including `square` itself.
+const RAYS: [[u64; NUM_SQUARES]; 8] = build_rays();
+
+const fn build_rays() -> [[u64; NUM_SQUARES]; 8] {
+    let mut rays = [[0; NUM_SQUARES]; 8];
+
+    let mut direction = 0;
+    while direction < DIRECTIONS.len() {
+        let (file_step, rank_step) = DIRECTIONS[direction];
+
+        let mut square = 0;
+        while square < NUM_SQUARES {
+            let mut file = geometry::square_file(square) as i32 + file_step;
+            let mut rank = geometry::square_rank(square) as i32 + rank_step;
+
+            let mut ray = 0;
+            while geometry::on_board(file, rank) {
+                ray |= 1 << geometry::square_index(file as usize, rank as usize);
+                file += file_step;
+                rank += rank_step;
+            }
+
+            rays[direction][square] = ray;
+            square += 1;
+        }
+
+        direction += 1;
+    }
+
+    rays
+}
+
+fn direction_index(offset: &Offset) -> Option<usize> {
+    DIRECTIONS.iter().position(|&(file_step, rank_step)| {
+        offset.file_offset == file_step && offset.rank_offset == rank_step
+    })
+}
+
+// The squares a slider on `square` sees along `offset`, up to and including the first occupied
+// square. Returns None if `offset` is not a single step in one of the eight directions.
+pub fn ray_attacks(square: usize, offset: &Offset, occupancy: &SquareSet) -> Option<SquareSet> {
+    let direction = direction_index(offset)?;
+    let ray = RAYS[direction][square];
+
+    let blockers = ray & occupancy.bits();
+    if blockers == 0 {
+        return Some(SquareSet::from_bits(ray));
+    }
+
+    // Rays heading towards h8 meet their nearest blocker at the lowest bit, the rest at the
+    // highest. Everything past the blocker is its own ray in the same direction.
+    let increasing = offset.rank_offset > 0 || (offset.rank_offset == 0 && offset.file_offset > 0);
+    let blocker = if increasing {
+        blockers.trailing_zeros() as usize
+    } else {
+        63 - blockers.leading_zeros() as usize
+    };
+
+    Some(SquareSet::from_bits(ray ^ RAYS[direction][blocker]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::Position;
+
+    #[test]
+    fn ray_attacks_test() {
+        let empty = SquareSet::new();
+
+        let north = ray_attacks(Position::a1().value(), &Offset::NORTH, &empty).unwrap();
+        assert_eq!(north.len(), 7);
+        assert!(north.contains(&Position::a8()));
+
+        let south_west = ray_attacks(Position::d4().value(), &Offset::SOUTH_WEST, &empty).unwrap();
+        let squares: Vec<Position> = south_west.iter().collect();
+        assert_eq!(
+            squares,
+            vec![Position::a1(), Position::b2(), Position::c3()]
+        );
+
+        assert!(ray_attacks(Position::h4().value(), &Offset::EAST, &empty)
+            .unwrap()
+            .is_empty());
+
+        let occupancy: SquareSet = [Position::d6(), Position::d2(), Position::b4()]
+            .into_iter()
+            .collect();
+        let north = ray_attacks(Position::d4().value(), &Offset::NORTH, &occupancy).unwrap();
+        assert_eq!(
+            north.iter().collect::<Vec<Position>>(),
+            vec![Position::d5(), Position::d6()]
+        );
+        let south = ray_attacks(Position::d4().value(), &Offset::SOUTH, &occupancy).unwrap();
+        assert_eq!(
+            south.iter().collect::<Vec<Position>>(),
+            vec![Position::d2(), Position::d3()]
+        );
+        let west = ray_attacks(Position::d4().value(), &Offset::WEST, &occupancy).unwrap();
+        assert_eq!(
+            west.iter().collect::<Vec<Position>>(),
+            vec![Position::b4(), Position::c4()]
+        );
+
+        assert!(ray_attacks(Position::d4().value(), &Offset::new(1, 2), &empty).is_none());
+        assert!(ray_attacks(Position::d4().value(), &Offset::NORTH.scaled(2), &empty).is_none());
+    }
+}
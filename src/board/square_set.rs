@@ -0,0 +1,231 @@
+use std::ops::{BitAnd, BitOr, Not, Sub};
+
+use super::position::Position;
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Default, Debug)]
+pub struct SquareSet(u64);
+
+impl SquareSet {
+    pub const fn new() -> SquareSet {
+        SquareSet(0)
+    }
+
+    pub const fn from_bits(bits: u64) -> SquareSet {
+        SquareSet(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn insert(&mut self, position: &Position) -> bool {
+        let inserted = !self.contains(position);
+        self.0 |= 1 << position.value();
+        inserted
+    }
+
+    pub fn remove(&mut self, position: &Position) -> bool {
+        let removed = self.contains(position);
+        self.0 &= !(1 << position.value());
+        removed
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        self.0 & (1 << position.value()) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = 0;
+    }
+
+    pub fn union(&self, other: &SquareSet) -> SquareSet {
+        SquareSet(self.0 | other.0)
+    }
+
+    pub fn intersection(&self, other: &SquareSet) -> SquareSet {
+        SquareSet(self.0 & other.0)
+    }
+
+    pub fn difference(&self, other: &SquareSet) -> SquareSet {
+        SquareSet(self.0 & !other.0)
+    }
+
+    pub fn iter(&self) -> SquareSetIter {
+        SquareSetIter(self.0)
+    }
+}
+
+impl BitOr for SquareSet {
+    type Output = SquareSet;
+
+    fn bitor(self, other: SquareSet) -> SquareSet {
+        self.union(&other)
+    }
+}
+
+impl BitAnd for SquareSet {
+    type Output = SquareSet;
+
+    fn bitand(self, other: SquareSet) -> SquareSet {
+        self.intersection(&other)
+    }
+}
+
+impl Sub for SquareSet {
+    type Output = SquareSet;
+
+    fn sub(self, other: SquareSet) -> SquareSet {
+        self.difference(&other)
+    }
+}
+
+impl Not for SquareSet {
+    type Output = SquareSet;
+
+    fn not(self) -> SquareSet {
+        SquareSet(!self.0)
+    }
+}
+
+impl FromIterator<Position> for SquareSet {
+    fn from_iter<I: IntoIterator<Item = Position>>(iter: I) -> SquareSet {
+        let mut square_set = SquareSet::new();
+        square_set.extend(iter);
+        square_set
+    }
+}
+
+impl Extend<Position> for SquareSet {
+    fn extend<I: IntoIterator<Item = Position>>(&mut self, iter: I) {
+        for position in iter {
+            self.insert(&position);
+        }
+    }
+}
+
+impl IntoIterator for SquareSet {
+    type Item = Position;
+    type IntoIter = SquareSetIter;
+
+    fn into_iter(self) -> SquareSetIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for &SquareSet {
+    type Item = Position;
+    type IntoIter = SquareSetIter;
+
+    fn into_iter(self) -> SquareSetIter {
+        self.iter()
+    }
+}
+
+// Yields positions from a1 to h8 by repeatedly popping the lowest set bit.
+pub struct SquareSetIter(u64);
+
+impl Iterator for SquareSetIter {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Position> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let value = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+
+        Position::from_index(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SquareSetIter {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn insert_remove_test() {
+        let mut square_set = SquareSet::new();
+        assert!(square_set.is_empty());
+
+        assert!(square_set.insert(&Position::a1()));
+        assert!(square_set.insert(&Position::h8()));
+        assert!(!square_set.insert(&Position::a1()));
+        assert_eq!(square_set.len(), 2);
+        assert!(square_set.contains(&Position::a1()));
+        assert!(square_set.contains(&Position::h8()));
+        assert!(!square_set.contains(&Position::e4()));
+
+        assert!(square_set.remove(&Position::a1()));
+        assert!(!square_set.remove(&Position::a1()));
+        assert_eq!(square_set.len(), 1);
+        assert_eq!(square_set.bits(), 1 << 63);
+    }
+
+    #[test]
+    fn set_operations_test() {
+        let first: SquareSet = [Position::a1(), Position::b2(), Position::c3()]
+            .into_iter()
+            .collect();
+        let second: SquareSet = [Position::b2(), Position::c3(), Position::d4()]
+            .into_iter()
+            .collect();
+
+        let union: Vec<Position> = (first | second).iter().collect();
+        assert_eq!(
+            union,
+            vec![
+                Position::a1(),
+                Position::b2(),
+                Position::c3(),
+                Position::d4()
+            ]
+        );
+
+        let intersection: Vec<Position> = (first & second).iter().collect();
+        assert_eq!(intersection, vec![Position::b2(), Position::c3()]);
+
+        let difference: Vec<Position> = (first - second).iter().collect();
+        assert_eq!(difference, vec![Position::a1()]);
+
+        assert_eq!((!SquareSet::new()).len(), 64);
+    }
+
+    #[test]
+    fn hash_set_equivalence_test() {
+        // Every square round trips and iteration visits the same squares as a HashSet would.
+        let mut square_set = SquareSet::new();
+        let mut hash_set = HashSet::new();
+        for value in (0..64).step_by(3) {
+            let position = Position::from_index(value).unwrap();
+            square_set.insert(&position);
+            hash_set.insert(position);
+        }
+
+        assert_eq!(square_set.len(), hash_set.len());
+        assert_eq!(square_set.iter().len(), hash_set.len());
+        assert_eq!(square_set.iter().collect::<HashSet<Position>>(), hash_set);
+
+        let values: Vec<usize> = square_set.iter().map(|position| position.value()).collect();
+        let mut sorted_values = values.clone();
+        sorted_values.sort();
+        assert_eq!(values, sorted_values);
+    }
+}
@@ -1,12 +1,12 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use crate::{
     board::position::{Offset, Position},
     piece::{Piece, PieceType, PromotionType, Side},
-    ParseError,
+    stats, ParseError,
 };
 
-use super::{file, rank, Board};
+use super::{file, rank, rays, square_set::SquareSet, Board, CastleRights, CastleSide};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum MoveState {
@@ -14,20 +14,88 @@ pub enum MoveState {
     Stalemate,
     Check,
     Checkmate,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    Invalid, // a king is missing, so the rules can't say how the game stands
 }
 
-#[derive(Debug)]
-pub struct MoveError(String);
+impl MoveState {
+    pub fn is_game_over(&self) -> bool {
+        !matches!(
+            self,
+            MoveState::CanMove | MoveState::Check | MoveState::Invalid
+        )
+    }
+}
 
-impl MoveError {
-    pub fn new(error: &str) -> MoveError {
-        MoveError(String::from(error))
+impl std::fmt::Display for MoveState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            MoveState::CanMove => "can move",
+            MoveState::Stalemate => "stalemate",
+            MoveState::Check => "check",
+            MoveState::Checkmate => "checkmate",
+            MoveState::FiftyMoveRule => "draw by the fifty-move rule",
+            MoveState::SeventyFiveMoveRule => "draw by the seventy-five-move rule",
+            MoveState::ThreefoldRepetition => "draw by threefold repetition",
+            MoveState::FivefoldRepetition => "draw by fivefold repetition",
+            MoveState::Invalid => "invalid position, a king is missing",
+        };
+
+        write!(f, "{description}")
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum MoveError {
+    EmptySquare { position: Position },
+    NotYourPiece { position: Position, piece: Piece }, // the piece belongs to the side not to move
+    InvalidMove,                                       // the piece can't reach the end square
+    ThroughCheck,                                      // castling across an attacked square
+    MissingPromotion, // a pawn reaching the last rank needs a promotion piece
+    UnexpectedPromotion,
+    GameOver,
+    NotLatestPosition, // a move was attempted while viewing an earlier ply
+    TouchedPiece { position: Position }, // touch move requires the touched piece to be moved
+}
+
 impl std::fmt::Display for MoveError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            MoveError::EmptySquare { position } => write!(f, "There is no piece on {position}."),
+            MoveError::NotYourPiece { position, piece } => {
+                let owner = match piece.side {
+                    Side::White => "White",
+                    Side::Black => "Black",
+                };
+                let piece_name = piece.piece_type.name();
+                write!(
+                    f,
+                    "The {piece_name} on {position} belongs to {owner}, who is not to move."
+                )
+            }
+            MoveError::InvalidMove => write!(f, "Invalid move."),
+            MoveError::ThroughCheck => write!(f, "Invalid move, cannot move through check."),
+            MoveError::MissingPromotion => {
+                write!(f, "Invalid move request, missing promotion data.")
+            }
+            MoveError::UnexpectedPromotion => {
+                write!(f, "Invalid move request, the move is not a promotion.")
+            }
+            MoveError::GameOver => write!(f, "Game is over."),
+            MoveError::NotLatestPosition => write!(
+                f,
+                "Moves can only be played from the latest position, go forward to it first."
+            ),
+            MoveError::TouchedPiece { position } => {
+                write!(
+                    f,
+                    "The piece on {position} was touched, so it has to be moved."
+                )
+            }
+        }
     }
 }
 
@@ -42,7 +110,29 @@ pub enum MoveKind {
     Promotion(bool), // capture
 }
 
-#[derive(PartialEq, Eq, Debug)]
+impl std::fmt::Display for MoveKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            MoveKind::Move => "move",
+            MoveKind::DoubleMove(_) => "double pawn move",
+            MoveKind::Capture => "capture",
+            MoveKind::EnPassant(_) => "en passant",
+            MoveKind::ShortCastle => "kingside castle",
+            MoveKind::LongCastle => "queenside castle",
+            MoveKind::Promotion(false) => "promotion",
+            MoveKind::Promotion(true) => "capture with promotion",
+        };
+
+        write!(f, "{description}")
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct CoordinateOptions {
+    pub default_promotion: Option<PromotionType>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct MoveRequest {
     pub start: Position,
     pub end: Position,
@@ -66,6 +156,8 @@ impl MoveRequest {
         }
     }
 
+    // Only the notation is checked here. A promotion suffix on a move that isn't a promotion,
+    // such as "e2e4q", parses but is rejected with UnexpectedPromotion when the move is made.
     pub fn from_coordinate(coordinate_notation: &str) -> Result<MoveRequest, ParseError> {
         if coordinate_notation.len() < 4 {
             return Err(ParseError::new("Notation is incomplete."));
@@ -85,6 +177,216 @@ impl MoveRequest {
             None => Ok(MoveRequest::new(start, end)),
         }
     }
+
+    pub fn to_coordinate(&self) -> String {
+        let promotion = self
+            .promotion
+            .map(|promotion| String::from(promotion.to_algebraic().to_ascii_lowercase()))
+            .unwrap_or_default();
+
+        format!("{}{}{promotion}", self.start, self.end)
+    }
+
+    pub fn from_coordinate_with(
+        coordinate_notation: &str,
+        options: &CoordinateOptions,
+    ) -> Result<MoveRequest, ParseError> {
+        let mut request = MoveRequest::from_coordinate(coordinate_notation)?;
+
+        // Without a board we can only tell that the move has the shape of a pawn promotion. If it
+        // turns out not to be a pawn move, making it fails with UnexpectedPromotion.
+        let promotion_shape = match (request.start.rank(), request.end.rank()) {
+            (rank::SEVEN, rank::EIGHT) | (rank::TWO, rank::ONE) => {
+                request.start.file().abs_diff(request.end.file()) <= 1
+            }
+            _ => false,
+        };
+
+        if request.promotion.is_none() && promotion_shape {
+            request.promotion = options.default_promotion;
+        }
+
+        Ok(request)
+    }
+
+    // Turns a move given in `side`'s relative coordinates, see Position::relative_to, back into
+    // board coordinates.
+    pub fn from_relative(relative: &MoveRequest, side: &Side) -> MoveRequest {
+        MoveRequest {
+            start: relative.start.relative_to(side),
+            end: relative.end.relative_to(side),
+            promotion: relative.promotion,
+        }
+    }
+
+    // Reads long algebraic notation as written by MoveInfo::to_lan. The piece letter has to match
+    // the piece on the start square, but the move itself isn't checked.
+    pub fn from_lan(board: &Board, lan: &str) -> Result<MoveRequest, ParseError> {
+        let notation = lan.trim().trim_end_matches(['+', '#', '!', '?']);
+
+        let side = board.side_to_move();
+        let back_rank = match side {
+            Side::White => rank::ONE,
+            Side::Black => rank::EIGHT,
+        };
+        match notation {
+            "O-O" | "0-0" => {
+                return Ok(MoveRequest::new(
+                    Position::new_unchecked(file::E, back_rank),
+                    Position::new_unchecked(file::G, back_rank),
+                ))
+            }
+            "O-O-O" | "0-0-0" => {
+                return Ok(MoveRequest::new(
+                    Position::new_unchecked(file::E, back_rank),
+                    Position::new_unchecked(file::C, back_rank),
+                ))
+            }
+            _ => (),
+        }
+
+        let (notation, promotion) = match notation.split_once('=') {
+            Some((notation, promotion)) => {
+                let promotion = promotion
+                    .chars()
+                    .next()
+                    .and_then(|promotion| {
+                        PromotionType::from_coordinate(promotion.to_ascii_lowercase())
+                    })
+                    .ok_or(ParseError::new("Invalid promotion notation."))?;
+                (notation, Some(promotion))
+            }
+            None => (notation, None),
+        };
+
+        let piece_type = match notation.chars().next() {
+            Some('K') => Some(PieceType::King),
+            Some('Q') => Some(PieceType::Queen),
+            Some('R') => Some(PieceType::Rook),
+            Some('B') => Some(PieceType::Bishop),
+            Some('N') => Some(PieceType::Knight),
+            _ => None,
+        };
+        let squares = match piece_type {
+            Some(_) => &notation[1..],
+            None => notation,
+        };
+
+        if squares.len() != 5 || !matches!(squares.as_bytes()[2], b'-' | b'x') {
+            return Err(ParseError::new("Expected a move like Ng1-f3 or e4xd5."));
+        }
+
+        let start = Position::from_notation(&squares[0..2])
+            .ok_or(ParseError::new("Invalid start position."))?;
+        let end = Position::from_notation(&squares[3..5])
+            .ok_or(ParseError::new("Invalid end position."))?;
+
+        let moving_type = board.piece_at(&start).map(|piece| &piece.piece_type);
+        if moving_type != Some(piece_type.as_ref().unwrap_or(&PieceType::Pawn)) {
+            return Err(ParseError::new("The piece doesn't match the start square."));
+        }
+
+        Ok(MoveRequest {
+            start,
+            end,
+            promotion,
+        })
+    }
+
+    pub fn from_san(board: &Board, san: &str) -> Result<MoveRequest, ParseError> {
+        let notation = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let all_legal_moves = get_all_legal_moves(board, &board.side_to_move());
+
+        let castle_kind = match notation {
+            "O-O" | "0-0" => Some(MoveKind::ShortCastle),
+            "O-O-O" | "0-0-0" => Some(MoveKind::LongCastle),
+            _ => None,
+        };
+
+        if let Some(castle_kind) = castle_kind {
+            for (start, piece_moves) in &all_legal_moves {
+                for (end, move_kind) in piece_moves {
+                    if *move_kind == castle_kind {
+                        return Ok(MoveRequest::new(*start, *end));
+                    }
+                }
+            }
+
+            return Err(ParseError::new("Castling is not legal in this position."));
+        }
+
+        // Both "e8=Q" and "e8Q" are accepted for promotions.
+        let mut notation = notation.replace('=', "");
+        let promotion = match notation.chars().last() {
+            Some(promotion) if "QRBN".contains(promotion) => {
+                notation.pop();
+                PromotionType::from_coordinate(promotion.to_ascii_lowercase())
+            }
+            _ => None,
+        };
+
+        let piece_type = match notation.chars().next() {
+            Some('K') => PieceType::King,
+            Some('Q') => PieceType::Queen,
+            Some('R') => PieceType::Rook,
+            Some('B') => PieceType::Bishop,
+            Some('N') => PieceType::Knight,
+            _ => PieceType::Pawn,
+        };
+
+        if piece_type != PieceType::Pawn {
+            notation.remove(0);
+        }
+
+        let notation = notation.replace('x', "");
+        if notation.len() < 2 || !notation.is_ascii() {
+            return Err(ParseError::new("Notation is incomplete."));
+        }
+
+        let (disambiguation, end) = notation.split_at(notation.len() - 2);
+        let end = Position::from_notation(end).ok_or(ParseError::new("Invalid end position."))?;
+
+        let mut start_file = None;
+        let mut start_rank = None;
+        for notation in disambiguation.chars() {
+            match (file::from_char(notation), rank::from_char(notation)) {
+                (Some(file), _) if start_file.is_none() => start_file = Some(file),
+                (_, Some(rank)) if start_rank.is_none() => start_rank = Some(rank),
+                _ => return Err(ParseError::new("Invalid disambiguation.")),
+            }
+        }
+
+        let mut candidates = Vec::new();
+        for (start, piece_moves) in &all_legal_moves {
+            let Some(move_kind) = piece_moves.get(&end) else {
+                continue;
+            };
+
+            let matches_piece =
+                board.piece_at(start).map(|piece| &piece.piece_type) == Some(&piece_type);
+            let matches_file = start_file.is_none_or(|file| start.file() == file);
+            let matches_rank = start_rank.is_none_or(|rank| start.rank() == rank);
+
+            if matches_piece && matches_file && matches_rank {
+                candidates.push((*start, move_kind));
+            }
+        }
+
+        match candidates.as_slice() {
+            [] => Err(ParseError::new("Illegal move.")),
+            [(start, MoveKind::Promotion(_))] => match promotion {
+                Some(promotion) => Ok(MoveRequest::promotion(*start, end, promotion)),
+                None => Err(ParseError::new("Promotion piece is missing.")),
+            },
+            [(start, _)] => match promotion {
+                Some(_) => Err(ParseError::new(
+                    "Only a pawn reaching the last rank can promote.",
+                )),
+                None => Ok(MoveRequest::new(*start, end)),
+            },
+            _ => Err(ParseError::new("Ambiguous move.")),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -98,9 +400,29 @@ pub struct MoveInfo {
     pub move_kind: MoveKind,
     pub move_state: Option<MoveState>,
     pub promotion: Option<PromotionType>,
+    pub is_forced: bool,
+}
+
+fn piece_letter(piece_type: &PieceType) -> Option<char> {
+    match piece_type {
+        PieceType::Pawn => None,
+        PieceType::Knight => Some('N'),
+        PieceType::Bishop => Some('B'),
+        PieceType::Rook => Some('R'),
+        PieceType::Queen => Some('Q'),
+        PieceType::King => Some('K'),
+    }
 }
 
 impl MoveInfo {
+    pub fn to_request(&self) -> MoveRequest {
+        MoveRequest {
+            start: self.start,
+            end: self.end,
+            promotion: self.promotion,
+        }
+    }
+
     pub fn to_notation(&self) -> String {
         let mut notation = String::new();
 
@@ -171,12 +493,142 @@ impl MoveInfo {
 
         notation
     }
+
+    // Long algebraic notation, which always names the start square so it never needs
+    // disambiguating, e.g. Ng1-f3, e4xd5 or Rh1xh8+.
+    pub fn to_lan(&self) -> String {
+        let mut lan = match self.move_kind {
+            MoveKind::ShortCastle => String::from("O-O"),
+            MoveKind::LongCastle => String::from("O-O-O"),
+            _ => {
+                let piece = piece_letter(&self.piece_type)
+                    .map(String::from)
+                    .unwrap_or_default();
+                let separator = if self.is_capture { 'x' } else { '-' };
+                let promotion = self
+                    .promotion
+                    .as_ref()
+                    .map(|promotion| format!("={}", promotion.to_algebraic()))
+                    .unwrap_or_default();
+
+                format!("{piece}{}{separator}{}{promotion}", self.start, self.end)
+            }
+        };
+
+        // The check suffix is the same as in SAN.
+        let san = self.to_notation();
+        if let Some(suffix) = san.chars().last().filter(|last| matches!(last, '+' | '#')) {
+            lan.push(suffix);
+        }
+
+        lan
+    }
+
+    pub fn describe(&self) -> String {
+        let piece = self.piece_type.name();
+        let mut description = match self.move_kind {
+            MoveKind::ShortCastle => String::from("King castles kingside"),
+            MoveKind::LongCastle => String::from("King castles queenside"),
+            _ if self.is_capture => format!("{piece} captures on {}", self.end),
+            _ => format!("{piece} from {} to {}", self.start, self.end),
+        };
+
+        // Piece names are lowercase, sentences aren't.
+        description[..1].make_ascii_uppercase();
+
+        if let MoveKind::EnPassant(_) = self.move_kind {
+            description.push_str(" en passant");
+        }
+
+        if let Some(promotion) = &self.promotion {
+            let promotion = promotion.to_piece_type().name();
+            description.push_str(&format!(", promoting to a {promotion}"));
+        }
+
+        match &self.move_state {
+            Some(MoveState::Check) => description.push_str(", giving check"),
+            Some(MoveState::Checkmate) => description.push_str(", giving checkmate"),
+            Some(MoveState::Stalemate) => description.push_str(", leading to stalemate"),
+            Some(move_state) if move_state.is_game_over() => {
+                description.push_str(&format!(", leading to a {move_state}"));
+            }
+            _ => (),
+        }
+
+        description
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct MoveUndo {
+    pub start: Position,
+    pub end: Position,
+    pub moved_piece: Piece,
+    pub captured: Option<(Position, Piece)>, // captured piece and the position it was taken from
+    pub rook_move: Option<(Position, Position)>, // castling rook start and end positions
+    pub castle_rights: CastleRights,
+    pub en_passant_target: Option<Position>,
+    pub half_moves: u32,
+    pub full_moves: u32,
+    pub last_move: Option<(Position, Position)>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EscapeBlocker {
+    Attacked,
+    Occupied,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct MateDetails {
+    pub king_position: Position,
+    pub checking_pieces: Vec<(Position, Piece)>,
+    pub blocked_escapes: Vec<(Position, EscapeBlocker)>,
+    pub interposition_squares: Vec<Position>, // squares between a single sliding checker and the king
+}
+
+impl std::fmt::Display for MateDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let checkers: Vec<String> = self
+            .checking_pieces
+            .iter()
+            .map(|(position, piece)| format!("the {} on {position}", piece.piece_type.name()))
+            .collect();
+        write!(f, "checkmated by {}", join_with_and(&checkers))?;
+
+        let covered: Vec<String> = self
+            .blocked_escapes
+            .iter()
+            .filter(|(_, blocker)| *blocker == EscapeBlocker::Attacked)
+            .map(|(position, _)| position.to_string())
+            .collect();
+        match covered.len() {
+            0 => Ok(()),
+            1 => write!(f, "; {} was covered", covered[0]),
+            _ => write!(f, "; {} were covered", join_with_and(&covered)),
+        }
+    }
+}
+
+fn join_with_and(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
 }
 
 pub fn move_piece(board: &mut Board, request: MoveRequest) -> Result<MoveInfo, MoveError> {
+    make_move(board, request).map(|(move_info, _)| move_info)
+}
+
+pub fn make_move(
+    board: &mut Board,
+    request: MoveRequest,
+) -> Result<(MoveInfo, MoveUndo), MoveError> {
     let move_kind = get_move(board, &request)?;
 
-    let side = board.get_current_turn();
+    let side = board.side_to_move();
 
     // Filter out invalid castles that pass through check
     if move_kind == MoveKind::ShortCastle || move_kind == MoveKind::LongCastle {
@@ -185,31 +637,56 @@ pub fn move_piece(board: &mut Board, request: MoveRequest) -> Result<MoveInfo, M
 
         let pass_through_check = match (side, &move_kind) {
             (Side::White, MoveKind::ShortCastle) => {
-                opponent_target_positions.contains(&Position::f1())
-                    || opponent_target_positions.contains(&Position::e1())
+                opponent_target_positions.contains(&Position::F1)
+                    || opponent_target_positions.contains(&Position::E1)
             }
             (Side::White, MoveKind::LongCastle) => {
-                opponent_target_positions.contains(&Position::d1())
-                    || opponent_target_positions.contains(&Position::e1())
+                opponent_target_positions.contains(&Position::D1)
+                    || opponent_target_positions.contains(&Position::E1)
             }
             (Side::Black, MoveKind::ShortCastle) => {
-                opponent_target_positions.contains(&Position::f8())
-                    || opponent_target_positions.contains(&Position::e8())
+                opponent_target_positions.contains(&Position::F8)
+                    || opponent_target_positions.contains(&Position::E8)
             }
             (Side::Black, MoveKind::LongCastle) => {
-                opponent_target_positions.contains(&Position::d8())
-                    || opponent_target_positions.contains(&Position::e8())
+                opponent_target_positions.contains(&Position::D8)
+                    || opponent_target_positions.contains(&Position::E8)
             }
             _ => false,
         };
 
         if pass_through_check {
-            return Err(MoveError::new("Invalid move, cannot move through check."));
+            return Err(MoveError::ThroughCheck);
         }
     }
 
+    // Record the state that the move is about to overwrite.
+    let previous_castle_rights = board.castle_rights;
+    let previous_en_passant_target = board.en_passant_target;
+    let previous_half_moves = board.half_moves;
+    let previous_full_moves = board.full_moves;
+    let previous_last_move = board.last_move;
+
     // Always take the piece from the start square.
     let moving_piece = board.take_piece(&request.start).unwrap();
+    let moved_piece = moving_piece;
+
+    let captured_position = match &move_kind {
+        MoveKind::EnPassant(en_passant_capture) => *en_passant_capture,
+        _ => request.end,
+    };
+    let captured = board
+        .piece_at(&captured_position)
+        .copied()
+        .map(|piece| (captured_position, piece));
+
+    let rook_move = match (&moving_piece.side, &move_kind) {
+        (Side::White, MoveKind::ShortCastle) => Some((Position::H1, Position::F1)),
+        (Side::White, MoveKind::LongCastle) => Some((Position::A1, Position::D1)),
+        (Side::Black, MoveKind::ShortCastle) => Some((Position::H8, Position::F8)),
+        (Side::Black, MoveKind::LongCastle) => Some((Position::A8, Position::D8)),
+        _ => None,
+    };
 
     // Special handling for en passant because the position of the captured piece is not on the end position.
     // Note that this must happen before we update the en passant target.
@@ -219,60 +696,35 @@ pub fn move_piece(board: &mut Board, request: MoveRequest) -> Result<MoveInfo, M
 
     // Set the en passant target
     if let MoveKind::DoubleMove(en_passant_target) = &move_kind {
-        board.en_passant_target = Some(en_passant_target.clone());
+        board.en_passant_target = Some(*en_passant_target);
     } else {
         board.en_passant_target = None;
     }
 
-    // Handle castling
-    match (&moving_piece.piece_type, &moving_piece.side) {
-        (PieceType::Rook, Side::White) => {
-            if request.start == Position::a1() {
-                board.castle_rights.white_long_castle_rights = false;
-            } else if request.start == Position::h1() {
-                board.castle_rights.white_short_castle_rights = false;
-            }
-        }
-        (PieceType::Rook, Side::Black) => {
-            if request.start == Position::a8() {
-                board.castle_rights.black_long_castle_rights = false;
-            } else if request.start == Position::h8() {
-                board.castle_rights.black_short_castle_rights = false;
-            }
-        }
-        (PieceType::King, Side::White) => {
-            board.castle_rights.white_long_castle_rights = false;
-            board.castle_rights.white_short_castle_rights = false;
-
-            match &move_kind {
-                MoveKind::ShortCastle => {
-                    let rook = board.take_piece(&Position::h1()).unwrap();
-                    board.set_position(&Position::f1(), Some(rook));
+    // Moving the king or a rook, or losing a rook on its starting square, gives up castling.
+    match moving_piece.piece_type {
+        PieceType::King => board.castle_rights.revoke_all(&moving_piece.side),
+        PieceType::Rook => {
+            if let Some((side, castle_side)) = rook_corner(&request.start) {
+                if side == moving_piece.side {
+                    board.castle_rights.revoke(&side, castle_side);
                 }
-                MoveKind::LongCastle => {
-                    let rook = board.take_piece(&Position::a1()).unwrap();
-                    board.set_position(&Position::d1(), Some(rook));
-                }
-                _ => (),
             }
         }
-        (PieceType::King, Side::Black) => {
-            board.castle_rights.black_long_castle_rights = false;
-            board.castle_rights.black_short_castle_rights = false;
+        _ => (),
+    }
 
-            match &move_kind {
-                MoveKind::ShortCastle => {
-                    let rook = board.take_piece(&Position::h8()).unwrap();
-                    board.set_position(&Position::f8(), Some(rook));
-                }
-                MoveKind::LongCastle => {
-                    let rook = board.take_piece(&Position::a8()).unwrap();
-                    board.set_position(&Position::d8(), Some(rook));
-                }
-                _ => (),
+    if let Some((captured_position, captured_piece)) = &captured {
+        if captured_piece.piece_type == PieceType::Rook {
+            if let Some((side, castle_side)) = rook_corner(captured_position) {
+                board.castle_rights.revoke(&side, castle_side);
             }
         }
-        _ => (),
+    }
+
+    if let Some((rook_start, rook_end)) = &rook_move {
+        let rook = board.take_piece(rook_start).unwrap();
+        board.set_position(rook_end, Some(rook));
     }
 
     // Update the have move counter
@@ -289,21 +741,35 @@ pub fn move_piece(board: &mut Board, request: MoveRequest) -> Result<MoveInfo, M
         board.half_moves += 1;
     }
 
-    let initial_piece_type = moving_piece.piece_type.clone();
+    let initial_piece_type = moving_piece.piece_type;
     let piece = match move_kind {
         MoveKind::Promotion(_) => {
             // We would not get the MoveKind promotion if it was an invalid request.
             let promotion_piece_type = request.promotion.as_ref().unwrap().to_piece_type();
-            Piece::new(promotion_piece_type, board.get_current_turn().clone())
+            Piece::new(promotion_piece_type, board.side_to_move())
         }
         _ => moving_piece,
     };
 
     // Place the piece on it's destination square.
     board.set_position(&request.end, Some(piece));
+    board.last_move = Some((request.start, request.end));
 
     board.change_turn();
 
+    let move_undo = MoveUndo {
+        start: request.start,
+        end: request.end,
+        moved_piece,
+        captured,
+        rook_move,
+        castle_rights: previous_castle_rights,
+        en_passant_target: previous_en_passant_target,
+        half_moves: previous_half_moves,
+        full_moves: previous_full_moves,
+        last_move: previous_last_move,
+    };
+
     let move_info = MoveInfo {
         start: request.start,
         end: request.end,
@@ -314,24 +780,89 @@ pub fn move_piece(board: &mut Board, request: MoveRequest) -> Result<MoveInfo, M
         move_kind,
         move_state: None,
         promotion: request.promotion,
+        is_forced: false,
     };
 
+    Ok((move_info, move_undo))
+}
+
+// The full MoveInfo for a legal move, including the disambiguation and the state of the board
+// after it, without changing `board`. Unlike Game::preview_move, repetitions aren't known here.
+pub fn preview_move(board: &Board, request: &MoveRequest) -> Result<MoveInfo, MoveError> {
+    let all_legal_moves = get_all_legal_moves(board, &board.side_to_move());
+    let is_legal = all_legal_moves
+        .get(&request.start)
+        .is_some_and(|piece_moves| piece_moves.contains_key(&request.end));
+    if !is_legal {
+        return Err(MoveError::InvalidMove);
+    }
+
+    let (file_disambiguation, rank_disambiguation) =
+        get_disambiguation(board, &all_legal_moves, request);
+    let legal_move_count: usize = all_legal_moves.values().map(HashMap::len).sum();
+
+    let mut new_board = board.clone_for_simulation();
+    let mut move_info = move_piece(&mut new_board, request.clone())?;
+    move_info.file_disambiguation = file_disambiguation;
+    move_info.rank_disambiguation = rank_disambiguation;
+    move_info.is_forced = legal_move_count == 1 && request.promotion.is_none();
+    move_info.move_state = Some(get_move_state(&new_board));
+
     Ok(move_info)
 }
 
-pub fn get_move(board: &Board, request: &MoveRequest) -> Result<MoveKind, MoveError> {
-    let moves = get_piece_moves(board, board.get_current_turn(), &request.start)?;
-    let move_kind = moves
-        .get(&request.end)
-        .ok_or(MoveError::new("Provided move is not valid."))?;
-
-    if let (MoveKind::Promotion(_), None) = (move_kind, &request.promotion) {
-        return Err(MoveError::new(
-            "Invalid move request, missing promotion data.",
-        ));
+// Whether SAN needs the start file and/or rank to tell the moving piece apart from others of
+// the same type that can reach the same square. The file is preferred, then the rank, and both
+// are only used if neither is enough on its own.
+pub fn get_disambiguation(
+    board: &Board,
+    all_legal_moves: &HashMap<Position, HashMap<Position, MoveKind>>,
+    request: &MoveRequest,
+) -> (bool, bool) {
+    let Some(moving_piece) = board.piece_at(&request.start) else {
+        return (false, false);
+    };
+
+    // Pawn captures always name their file, and pawn pushes can't be ambiguous.
+    if moving_piece.piece_type == PieceType::Pawn {
+        return (false, false);
     }
 
-    Ok(move_kind.clone())
+    let competitors: Vec<&Position> = all_legal_moves
+        .iter()
+        .filter(|(piece_position, moves)| {
+            **piece_position != request.start
+                && moves.contains_key(&request.end)
+                && board
+                    .piece_at(piece_position)
+                    .is_some_and(|piece| piece.piece_type == moving_piece.piece_type)
+        })
+        .map(|(piece_position, _)| piece_position)
+        .collect();
+
+    let shares_file = competitors
+        .iter()
+        .any(|position| position.file() == request.start.file());
+    let shares_rank = competitors
+        .iter()
+        .any(|position| position.rank() == request.start.rank());
+
+    let file_disambiguation = !competitors.is_empty() && (!shares_file || shares_rank);
+    let rank_disambiguation = shares_file;
+
+    (file_disambiguation, rank_disambiguation)
+}
+
+pub fn get_move(board: &Board, request: &MoveRequest) -> Result<MoveKind, MoveError> {
+    let moves = get_piece_moves(board, &board.side_to_move(), &request.start)?;
+    let move_kind = moves.get(&request.end).ok_or(MoveError::InvalidMove)?;
+
+    match (move_kind, &request.promotion) {
+        (MoveKind::Promotion(_), None) => Err(MoveError::MissingPromotion),
+        (MoveKind::Promotion(_), Some(_)) => Ok(move_kind.clone()),
+        (_, Some(_)) => Err(MoveError::UnexpectedPromotion),
+        (_, None) => Ok(move_kind.clone()),
+    }
 }
 
 pub fn get_piece_moves(
@@ -339,54 +870,91 @@ pub fn get_piece_moves(
     side: &Side,
     start: &Position,
 ) -> Result<HashMap<Position, MoveKind>, MoveError> {
-    match board.get_piece(start) {
+    stats::count_piece_move_generation();
+
+    match board.piece_at(start) {
         Some(piece) => {
             if piece.side == *side {
-                let moves = match piece.piece_type {
-                    PieceType::Pawn => get_pawn_moves(board, start, &piece.side),
-                    PieceType::Rook => get_rook_moves(board, start, &piece.side),
-                    PieceType::Knight => get_knight_moves(board, start, &piece.side),
-                    PieceType::Bishop => get_bishop_moves(board, start, &piece.side),
-                    PieceType::King => get_king_moves(board, start, &piece.side),
-                    PieceType::Queen => get_queen_moves(board, start, &piece.side),
-                };
-
-                Ok(moves)
-            } else {
-                Err(MoveError::new(
-                    "Unable to find a piece for the current player at the provided position.",
+                Ok(moves_for_piece_type(
+                    board,
+                    &piece.piece_type,
+                    &piece.side,
+                    start,
                 ))
+            } else {
+                Err(MoveError::NotYourPiece {
+                    position: *start,
+                    piece: *piece,
+                })
             }
         }
-        None => Err(MoveError::new("No piece found at the provided position.")),
+        None => Err(MoveError::EmptySquare { position: *start }),
     }
 }
 
-pub fn get_pawn_moves(board: &Board, start: &Position, side: &Side) -> HashMap<Position, MoveKind> {
-    let mut valid_positions = HashMap::new();
+// The moves of the piece on `start` without checking whose turn it is or whether they leave the
+// king in check. A piece of the side not to move is treated as if the other side had passed.
+pub fn pseudo_moves_ignoring_turn(
+    board: &Board,
+    start: &Position,
+) -> Result<HashMap<Position, MoveKind>, MoveError> {
+    let piece = board
+        .piece_at(start)
+        .ok_or(MoveError::EmptySquare { position: *start })?;
+
+    let board = board_with_side_to_move(board, &piece.side);
+    Ok(moves_for_piece_type(
+        &board,
+        &piece.piece_type,
+        &piece.side,
+        start,
+    ))
+}
 
-    let forward_one = match side {
-        Side::White => Offset::new(0, 1),
-        Side::Black => Offset::new(0, -1),
-    };
+// What `side` could play if it were its turn, for showing threats and premoves.
+pub fn legal_moves_if_to_move(
+    board: &Board,
+    side: &Side,
+) -> HashMap<Position, HashMap<Position, MoveKind>> {
+    get_all_legal_moves(&board_with_side_to_move(board, side), side)
+}
 
-    let left_diagonal = match side {
-        Side::White => Offset::new(-1, 1),
-        Side::Black => Offset::new(1, -1),
-    };
+fn board_with_side_to_move(board: &Board, side: &Side) -> Board {
+    let mut board = board.clone_for_simulation();
+    if board.side_to_move() != *side {
+        board.make_null_move();
+    }
 
-    let right_diagonal = match side {
-        Side::White => Offset::new(1, 1),
-        Side::Black => Offset::new(-1, -1),
-    };
+    board
+}
 
-    let promotion_rank = match side {
-        Side::White => rank::EIGHT,
-        Side::Black => rank::ONE,
-    };
+// Generates moves as if the given piece stood on `from`, whatever currently occupies it.
+pub fn moves_for_piece_type(
+    board: &Board,
+    piece_type: &PieceType,
+    side: &Side,
+    from: &Position,
+) -> HashMap<Position, MoveKind> {
+    match piece_type {
+        PieceType::Pawn => get_pawn_moves(board, from, side),
+        PieceType::Rook => get_rook_moves(board, from, side),
+        PieceType::Knight => get_knight_moves(board, from, side),
+        PieceType::Bishop => get_bishop_moves(board, from, side),
+        PieceType::King => get_king_moves(board, from, side),
+        PieceType::Queen => get_queen_moves(board, from, side),
+    }
+}
+
+pub fn get_pawn_moves(board: &Board, start: &Position, side: &Side) -> HashMap<Position, MoveKind> {
+    let mut valid_positions = HashMap::new();
 
-    if let Some(new_position) = Position::from_offset(start, &forward_one) {
-        if !contains_piece(board, &new_position) {
+    let forward_one = side.pawn_direction();
+    let left_diagonal = Offset::NORTH_WEST.for_side(side);
+    let right_diagonal = Offset::NORTH_EAST.for_side(side);
+    let promotion_rank = side.promotion_rank();
+
+    if let Some(new_position) = start + forward_one {
+        if !board.is_occupied(&new_position) {
             let move_kind = if new_position.rank() == promotion_rank {
                 MoveKind::Promotion(false)
             } else {
@@ -396,23 +964,15 @@ pub fn get_pawn_moves(board: &Board, start: &Position, side: &Side) -> HashMap<P
         }
     }
 
-    let double_move_positions = match side {
-        Side::White if start.rank() == rank::TWO => {
-            let forward_one = Position::from_file_and_rank(start.file(), start.rank() + 1);
-            let forward_two = Position::from_file_and_rank(start.file(), start.rank() + 2);
-            Some((forward_one, forward_two))
-        }
-        Side::Black if start.rank() == rank::SEVEN => {
-            let forward_one = Position::from_file_and_rank(start.file(), start.rank() - 1);
-            let forward_two = Position::from_file_and_rank(start.file(), start.rank() - 2);
-            Some((forward_one, forward_two))
-        }
-        _ => None,
+    let double_move_positions = if start.rank() == side.pawn_start_rank() {
+        (start + forward_one).zip(start + forward_one.scaled(2))
+    } else {
+        None
     };
 
     if let Some((forward_one, forward_two)) = double_move_positions {
-        let forward_one_empty = !contains_piece(board, &forward_one);
-        let forward_two_empty = !contains_piece(board, &forward_two);
+        let forward_one_empty = !board.is_occupied(&forward_one);
+        let forward_two_empty = !board.is_occupied(&forward_two);
 
         if forward_one_empty && forward_two_empty {
             valid_positions.insert(forward_two, MoveKind::DoubleMove(forward_one));
@@ -420,26 +980,17 @@ pub fn get_pawn_moves(board: &Board, start: &Position, side: &Side) -> HashMap<P
     }
 
     let en_passant_move = |new_position: &Position| {
-        let en_passant_target = match side {
-            Side::White => {
-                Position::from_file_and_rank(new_position.file(), new_position.rank() - 1)
-            }
-            Side::Black => {
-                Position::from_file_and_rank(new_position.file(), new_position.rank() + 1)
-            }
-        };
-
-        if is_en_passant_target(board, &en_passant_target) {
-            Some(en_passant_target)
-        } else {
-            None
+        if !is_en_passant_target(board, new_position) {
+            return None;
         }
+
+        // The pawn being captured sits directly behind the en passant target.
+        new_position + side.opponent().pawn_direction()
     };
 
-    let diagonal_moves = vec![left_diagonal, right_diagonal];
-    for diagonal_move in diagonal_moves {
-        if let Some(new_position) = Position::from_offset(start, &diagonal_move) {
-            if contains_enemy_piece(board, &new_position, side) {
+    for diagonal_move in [left_diagonal, right_diagonal] {
+        if let Some(new_position) = start + diagonal_move {
+            if board.is_occupied_by(&new_position, &side.opponent()) {
                 let move_kind = if new_position.rank() == promotion_rank {
                     MoveKind::Promotion(true)
                 } else {
@@ -479,9 +1030,9 @@ pub fn get_knight_moves(
 
     for offset in offsets {
         if let Some(new_position) = Position::from_offset(start, &offset) {
-            if contains_enemy_piece(board, &new_position, side) {
+            if board.is_occupied_by(&new_position, &side.opponent()) {
                 valid_positions.insert(new_position, MoveKind::Capture);
-            } else if !contains_piece(board, &new_position) {
+            } else if !board.is_occupied(&new_position) {
                 valid_positions.insert(new_position, MoveKind::Move);
             }
         }
@@ -491,14 +1042,7 @@ pub fn get_knight_moves(
 }
 
 pub fn get_rook_moves(board: &Board, start: &Position, side: &Side) -> HashMap<Position, MoveKind> {
-    let offsets = vec![
-        Offset::new(1, 0),
-        Offset::new(0, 1),
-        Offset::new(-1, 0),
-        Offset::new(0, -1),
-    ];
-
-    get_while_valid(board, start, side, &offsets)
+    get_while_valid(board, start, side, &Offset::ORTHOGONAL)
 }
 
 pub fn get_bishop_moves(
@@ -506,13 +1050,7 @@ pub fn get_bishop_moves(
     start: &Position,
     side: &Side,
 ) -> HashMap<Position, MoveKind> {
-    let offsets = vec![
-        Offset::new(1, 1),
-        Offset::new(-1, 1),
-        Offset::new(1, -1),
-        Offset::new(-1, -1),
-    ];
-    get_while_valid(board, start, side, &offsets)
+    get_while_valid(board, start, side, &Offset::DIAGONAL)
 }
 
 pub fn get_queen_moves(
@@ -520,16 +1058,7 @@ pub fn get_queen_moves(
     start: &Position,
     side: &Side,
 ) -> HashMap<Position, MoveKind> {
-    let offsets = vec![
-        Offset::new(1, 0),
-        Offset::new(0, 1),
-        Offset::new(-1, 0),
-        Offset::new(0, -1),
-        Offset::new(1, 1),
-        Offset::new(-1, 1),
-        Offset::new(1, -1),
-        Offset::new(-1, -1),
-    ];
+    let offsets = [Offset::ORTHOGONAL, Offset::DIAGONAL].concat();
     get_while_valid(board, start, side, &offsets)
 }
 
@@ -537,58 +1066,38 @@ pub fn get_king_moves(board: &Board, start: &Position, side: &Side) -> HashMap<P
     let mut valid_positions = HashMap::new();
 
     // Regular moves
-    let offsets = vec![
-        Offset::new(1, 0),
-        Offset::new(0, 1),
-        Offset::new(-1, 0),
-        Offset::new(0, -1),
-        Offset::new(1, 1),
-        Offset::new(-1, 1),
-        Offset::new(1, -1),
-        Offset::new(-1, -1),
-    ];
-
-    for offset in offsets {
-        if let Some(new_position) = Position::from_offset(start, &offset) {
-            if contains_enemy_piece(board, &new_position, side) {
+    for offset in Offset::ORTHOGONAL.iter().chain(Offset::DIAGONAL.iter()) {
+        if let Some(new_position) = start + *offset {
+            if board.is_occupied_by(&new_position, &side.opponent()) {
                 valid_positions.insert(new_position, MoveKind::Capture);
-            } else if !contains_piece(board, &new_position) {
+            } else if !board.is_occupied(&new_position) {
                 valid_positions.insert(new_position, MoveKind::Move);
             }
         }
     }
 
     // Castling
-    match side {
-        Side::White => {
-            if board.castle_rights.white_short_castle_rights {
-                let castle_positions = vec![Position::f1(), Position::g1()];
-                if are_positions_empty(board, &castle_positions) {
-                    valid_positions.insert(Position::g1(), MoveKind::ShortCastle);
-                }
-            }
+    let back_rank = match side {
+        Side::White => rank::ONE,
+        Side::Black => rank::EIGHT,
+    };
+    let on_back_rank = |file| Position::new_unchecked(file, back_rank);
+
+    if *start == on_back_rank(file::E) {
+        let rights = board.castle_rights.rights_for(side);
+        // Boards built without validation can keep a right whose rook is gone.
+        let can_castle_with = |rook_file| {
+            let rook_position = on_back_rank(rook_file);
+            board.piece_at(&rook_position) == Some(&Piece::new(PieceType::Rook, *side))
+                && board.is_empty_between(start, &rook_position)
+        };
 
-            if board.castle_rights.white_long_castle_rights {
-                let castle_positions = vec![Position::b1(), Position::c1(), Position::d1()];
-                if are_positions_empty(board, &castle_positions) {
-                    valid_positions.insert(Position::c1(), MoveKind::LongCastle);
-                }
-            }
+        if rights.short && can_castle_with(file::H) {
+            valid_positions.insert(on_back_rank(file::G), MoveKind::ShortCastle);
         }
-        Side::Black => {
-            if board.castle_rights.black_short_castle_rights {
-                let castle_positions = vec![Position::f8(), Position::g8()];
-                if are_positions_empty(board, &castle_positions) {
-                    valid_positions.insert(Position::g8(), MoveKind::ShortCastle);
-                }
-            }
 
-            if board.castle_rights.black_long_castle_rights {
-                let castle_positions = vec![Position::b8(), Position::c8(), Position::d8()];
-                if are_positions_empty(board, &castle_positions) {
-                    valid_positions.insert(Position::c8(), MoveKind::LongCastle);
-                }
-            }
+        if rights.long && can_castle_with(file::A) {
+            valid_positions.insert(on_back_rank(file::C), MoveKind::LongCastle);
         }
     }
 
@@ -599,14 +1108,20 @@ pub fn get_while_valid(
     board: &Board,
     position: &Position,
     side: &Side,
-    offsets: &Vec<Offset>,
+    offsets: &[Offset],
 ) -> HashMap<Position, MoveKind> {
     let mut valid_positions = HashMap::new();
 
+    let (own_positions, enemy_positions) = match side {
+        Side::White => (board.get_white_positions(), board.get_black_positions()),
+        Side::Black => (board.get_black_positions(), board.get_white_positions()),
+    };
+    let occupancy = *own_positions | *enemy_positions;
+
     let filter = |new_position: &Position| {
-        if !contains_piece(board, new_position) {
+        if !board.is_occupied(new_position) {
             WhileMoveResult::Continue
-        } else if contains_enemy_piece(board, new_position, side) {
+        } else if board.is_occupied_by(new_position, &side.opponent()) {
             WhileMoveResult::Capture
         } else {
             WhileMoveResult::Stop
@@ -614,7 +1129,20 @@ pub fn get_while_valid(
     };
 
     for offset in offsets {
-        add_while_valid(position, offset, filter, &mut valid_positions);
+        match rays::ray_attacks(position.value(), offset, &occupancy) {
+            Some(attacks) => {
+                for new_position in attacks - *own_positions {
+                    let move_kind = if enemy_positions.contains(&new_position) {
+                        MoveKind::Capture
+                    } else {
+                        MoveKind::Move
+                    };
+                    valid_positions.insert(new_position, move_kind);
+                }
+            }
+            // Anything other than a single step in one of the eight directions is walked square by square.
+            None => add_while_valid(position, offset, filter, &mut valid_positions),
+        }
     }
 
     valid_positions
@@ -639,11 +1167,11 @@ pub fn add_while_valid<F>(
         return;
     }
 
-    let mut current_position = start.clone();
+    let mut current_position = *start;
     while let Some(new_position) = Position::from_offset(&current_position, offset) {
         match filter(&new_position) {
             WhileMoveResult::Continue => {
-                current_position = new_position.clone();
+                current_position = new_position;
                 valid_positions.insert(new_position, MoveKind::Move);
             }
             WhileMoveResult::Capture => {
@@ -664,16 +1192,16 @@ pub fn get_all_moves(board: &Board, side: &Side) -> HashMap<Position, HashMap<Po
     };
 
     for position in piece_positions {
-        if let Ok(moves) = get_piece_moves(board, side, position) {
-            all_moves.insert(position.clone(), moves);
+        if let Ok(moves) = get_piece_moves(board, side, &position) {
+            all_moves.insert(position, moves);
         }
     }
 
     all_moves
 }
 
-pub fn get_all_target_positions(board: &Board, side: &Side) -> HashSet<Position> {
-    let mut all_target_positions = HashSet::new();
+pub fn get_all_target_positions(board: &Board, side: &Side) -> SquareSet {
+    let mut all_target_positions = SquareSet::new();
 
     let piece_positions = match side {
         Side::White => board.get_white_positions(),
@@ -681,7 +1209,18 @@ pub fn get_all_target_positions(board: &Board, side: &Side) -> HashSet<Position>
     };
 
     for position in piece_positions {
-        if let Ok(moves) = get_piece_moves(board, side, position) {
+        // Pawns only attack diagonally, whether or not there is anything there to capture.
+        if board.piece_at(&position).map(|piece| &piece.piece_type) == Some(&PieceType::Pawn) {
+            for offset in [Offset::NORTH_WEST, Offset::NORTH_EAST] {
+                if let Some(target_position) = position + offset.for_side(side) {
+                    all_target_positions.insert(&target_position);
+                }
+            }
+
+            continue;
+        }
+
+        if let Ok(moves) = get_piece_moves(board, side, &position) {
             all_target_positions.extend(moves.into_keys());
         }
     }
@@ -689,13 +1228,14 @@ pub fn get_all_target_positions(board: &Board, side: &Side) -> HashSet<Position>
     all_target_positions
 }
 
+// A side without a king is never in check.
 pub fn is_in_check(board: &Board, side: &Side) -> bool {
     let opponent_side = side.opponent();
 
     let all_opponent_target_positions = get_all_target_positions(board, &opponent_side);
 
     for target_position in all_opponent_target_positions {
-        if board.get_piece(&target_position) == Some(&Piece::new(PieceType::King, side.clone())) {
+        if board.piece_at(&target_position) == Some(&Piece::new(PieceType::King, *side)) {
             return true;
         }
     }
@@ -704,42 +1244,89 @@ pub fn is_in_check(board: &Board, side: &Side) -> bool {
 }
 
 pub fn get_move_state(board: &Board) -> MoveState {
-    let all_legal_moves = get_all_legal_moves(board, board.get_current_turn());
+    if !board.has_king(&Side::White) || !board.has_king(&Side::Black) {
+        return MoveState::Invalid;
+    }
+
+    let in_check = is_in_check(board, &board.side_to_move());
 
-    if all_legal_moves.is_empty() {
-        if is_in_check(board, board.get_current_turn()) {
+    if !board.has_legal_move() {
+        if in_check {
             MoveState::Checkmate
         } else {
             MoveState::Stalemate
         }
-    } else if board.get_half_moves() == 100 {
-        MoveState::Stalemate
-    } else if is_in_check(board, board.get_current_turn()) {
+    } else if board.get_half_moves() >= 150 {
+        // The 75 move rule ends the game automatically, unlike the claimable 50 move rule.
+        MoveState::SeventyFiveMoveRule
+    } else if in_check {
         MoveState::Check
     } else {
         MoveState::CanMove
     }
 }
 
+pub fn can_claim_fifty_move_rule(board: &Board) -> bool {
+    board.get_half_moves() >= 100
+}
+
+// Non-pawn material at or below this leaves too few spare moves to rely on having a useful one.
+const ZUGZWANG_MATERIAL_THRESHOLD: i32 = 3;
+
+// Whether `side` is likely to be in zugzwang territory: nothing but king and pawns, or at most a
+// single minor piece besides them. Skipping a move (as null-move pruning assumes is never better
+// than the best move) is unsound in these positions.
+pub fn is_zugzwang_risky(board: &Board, side: &Side) -> bool {
+    let positions = match side {
+        Side::White => board.get_white_positions(),
+        Side::Black => board.get_black_positions(),
+    };
+
+    let piece_material: i32 = positions
+        .iter()
+        .filter_map(|position| board.piece_at(&position))
+        .filter(|piece| piece.piece_type != PieceType::Pawn)
+        .map(|piece| piece.piece_type.value())
+        .sum();
+
+    piece_material <= ZUGZWANG_MATERIAL_THRESHOLD
+}
+
 pub fn get_all_legal_moves(
     board: &Board,
     side: &Side,
+) -> HashMap<Position, HashMap<Position, MoveKind>> {
+    stats::count_legal_move_generation();
+
+    // The filter reasons about the side to move, anything else is left to simulation.
+    if *side != board.side_to_move() {
+        return get_simulated_legal_moves(board, side);
+    }
+
+    let Some(all_legal_moves) = get_filtered_legal_moves(board, side) else {
+        return get_simulated_legal_moves(board, side);
+    };
+
+    debug_assert_eq!(
+        all_legal_moves,
+        stats::uncounted(|| get_simulated_legal_moves(board, side)),
+        "legal move filter disagrees with simulation for {}",
+        crate::fen::generate(board)
+    );
+
+    all_legal_moves
+}
+
+// Plays every pseudo-legal move on a copy of the board and keeps the ones that don't leave
+// the king in check.
+fn get_simulated_legal_moves(
+    board: &Board,
+    side: &Side,
 ) -> HashMap<Position, HashMap<Position, MoveKind>> {
     let mut all_legal_moves = HashMap::new();
     let all_moves = get_all_moves(board, side);
     for (start, mut piece_moves) in all_moves {
-        piece_moves.retain(|end, move_kind| {
-            let move_request = match move_kind {
-                // Just pick a promotion type, it's just to ensure that the move_piece() call succeeds.
-                MoveKind::Promotion(_) => {
-                    MoveRequest::promotion(start.clone(), end.clone(), PromotionType::Queen)
-                }
-                _ => MoveRequest::new(start.clone(), end.clone()),
-            };
-
-            let mut new_board = board.clone();
-            move_piece(&mut new_board, move_request).is_ok() && !is_in_check(&new_board, side)
-        });
+        piece_moves.retain(|end, move_kind| is_legal_move(board, side, &start, end, move_kind));
 
         if !piece_moves.is_empty() {
             all_legal_moves.insert(start, piece_moves);
@@ -749,573 +1336,985 @@ pub fn get_all_legal_moves(
     all_legal_moves
 }
 
-pub fn contains_piece(board: &Board, position: &Position) -> bool {
-    board.get_piece(position).is_some()
-}
+fn get_filtered_legal_moves(
+    board: &Board,
+    side: &Side,
+) -> Option<HashMap<Position, HashMap<Position, MoveKind>>> {
+    let filter = LegalityFilter::new(board, side)?;
 
-pub fn contains_enemy_piece(board: &Board, position: &Position, side: &Side) -> bool {
-    match board.get_piece(position) {
-        Some(piece) => piece.side != *side,
-        None => false,
-    }
-}
+    let mut all_legal_moves = HashMap::new();
+    for (start, mut piece_moves) in get_all_moves(board, side) {
+        piece_moves.retain(|end, move_kind| filter.allows(&start, end, move_kind));
 
-pub fn are_positions_empty(board: &Board, positions: &Vec<Position>) -> bool {
-    let mut empty = true;
-    for position in positions {
-        if contains_piece(board, position) {
-            empty = false;
-            break;
+        if !piece_moves.is_empty() {
+            all_legal_moves.insert(start, piece_moves);
         }
     }
 
-    empty
+    Some(all_legal_moves)
 }
 
-pub fn is_en_passant_target(board: &Board, position: &Position) -> bool {
-    match board.get_en_passant_target() {
-        Some(en_passant_target) => position == en_passant_target,
-        None => false,
-    }
+// Decides legality from the checking and pinning pieces instead of playing each move out.
+// Castling and en passant still go through simulation since they move more than one piece.
+struct LegalityFilter<'a> {
+    board: &'a Board,
+    side: &'a Side,
+    king_position: Position,
+    checkers: Vec<Position>,
+    pins: HashMap<Position, Vec<Position>>,
 }
 
-pub fn possible_en_passant_capture(board: &Board) -> bool {
-    match board.get_en_passant_target() {
-        Some(target) => {
-            let side = board.get_current_turn();
-            let left_diagonal = match side {
-                Side::White => Position::from_offset(target, &Offset::new(-1, -1)),
-                Side::Black => Position::from_offset(target, &Offset::new(-1, 1)),
-            };
+impl<'a> LegalityFilter<'a> {
+    // Returns None if the side has no king to protect.
+    fn new(board: &'a Board, side: &'a Side) -> Option<LegalityFilter<'a>> {
+        let king_position = board.king_position(side)?;
+
+        let checkers = attackers_of(board, &king_position, &side.opponent(), None);
+        let pins = pinned_pieces(board, &king_position, side);
+
+        Some(LegalityFilter {
+            board,
+            side,
+            king_position,
+            checkers,
+            pins,
+        })
+    }
 
-            let right_diagonal = match side {
-                Side::White => Position::from_offset(target, &Offset::new(1, -1)),
-                Side::Black => Position::from_offset(target, &Offset::new(-1, -1)),
+    fn allows(&self, start: &Position, end: &Position, move_kind: &MoveKind) -> bool {
+        if *start == self.king_position {
+            return match move_kind {
+                MoveKind::ShortCastle | MoveKind::LongCastle => {
+                    is_legal_move(self.board, self.side, start, end, move_kind)
+                }
+                // The king can't hide behind itself from a slider, so it doesn't block.
+                _ => attackers_of(
+                    self.board,
+                    end,
+                    &self.side.opponent(),
+                    Some(&self.king_position),
+                )
+                .is_empty(),
             };
+        }
 
-            let mut valid_capture = false;
-            if let Some(left_diagonal) = left_diagonal {
-                if let Ok(moves) = get_piece_moves(board, side, &left_diagonal) {
-                    valid_capture = moves.contains_key(target);
-                };
-            };
+        // Only the king can answer a double check.
+        if self.checkers.len() > 1 {
+            return false;
+        }
 
-            // Only check the next position if we didn't already find a valid capture.
-            if !valid_capture {
-                if let Some(right_diagonal) = right_diagonal {
-                    if let Ok(moves) = get_piece_moves(board, side, &right_diagonal) {
-                        valid_capture = moves.contains_key(target);
-                    };
-                }
+        if let MoveKind::EnPassant(_) = move_kind {
+            return is_legal_move(self.board, self.side, start, end, move_kind);
+        }
+
+        if let Some(pin_line) = self.pins.get(start) {
+            if !pin_line.contains(end) {
+                return false;
             }
+        }
 
-            valid_capture
+        match self.checkers.first() {
+            Some(checker) => {
+                end == checker || squares_between(&self.king_position, checker).contains(end)
+            }
+            None => true,
         }
-        None => false,
     }
 }
 
-#[macro_export]
-macro_rules! board_position {
-    ( $position:ident, None ) => {
-        (Position::$position(), None)
-    };
+// Stops at the first legal move found. That is a single pass over the pseudo-legal moves at
+// worst, when there are no legal moves, and usually far less.
+pub fn has_legal_move(board: &Board) -> bool {
+    let side = board.side_to_move();
+    let filter = LegalityFilter::new(board, &side);
 
-    ( $position:ident, $piece_type:ident, $side:ident ) => {
-        (
-            Position::$position(),
-            Some(Piece::new(PieceType::$piece_type, Side::$side)),
-        )
-    };
+    side_positions(board, &side).into_iter().any(|start| {
+        let Ok(piece_moves) = get_piece_moves(board, &side, &start) else {
+            return false;
+        };
+
+        piece_moves.iter().any(|(end, move_kind)| match &filter {
+            Some(filter) => filter.allows(&start, end, move_kind),
+            None => is_legal_move(board, &side, &start, end, move_kind),
+        })
+    })
 }
 
-#[macro_export]
-macro_rules! piece_position {
-    ( $position:ident, $piece_type:ident, $side:ident ) => {
-        (
-            Position::$position(),
-            Piece::new(PieceType::$piece_type, Side::$side),
-        )
+// Pieces of `side` attacking `square`, treating `ignoring` as empty.
+pub(crate) fn attackers_of(
+    board: &Board,
+    square: &Position,
+    side: &Side,
+    ignoring: Option<&Position>,
+) -> Vec<Position> {
+    let mut attackers = Vec::new();
+
+    let is_piece = |position: &Position, piece_types: &[PieceType]| {
+        board
+            .piece_at(position)
+            .is_some_and(|piece| piece.side == *side && piece_types.contains(&piece.piece_type))
     };
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::fen;
+    let pawn_offsets = [Offset::SOUTH_WEST, Offset::SOUTH_EAST].map(|offset| offset.for_side(side));
+    for position in pawn_offsets
+        .into_iter()
+        .filter_map(|offset| square + offset)
+    {
+        if is_piece(&position, &[PieceType::Pawn]) {
+            attackers.push(position);
+        }
+    }
 
-    use super::*;
+    let knight_offsets = [
+        Offset::new(1, 2),
+        Offset::new(2, 1),
+        Offset::new(1, -2),
+        Offset::new(2, -1),
+        Offset::new(-1, 2),
+        Offset::new(-2, 1),
+        Offset::new(-2, -1),
+        Offset::new(-1, -2),
+    ];
+    for position in knight_offsets
+        .into_iter()
+        .filter_map(|offset| square + offset)
+    {
+        if is_piece(&position, &[PieceType::Knight]) {
+            attackers.push(position);
+        }
+    }
 
-    #[test]
-    fn move_request_test() {
-        // Normal move request
-        {
-            let move_request = MoveRequest::new(Position::a2(), Position::a3());
-            let expected_move_request = MoveRequest {
-                start: Position::a2(),
-                end: Position::a3(),
-                promotion: None,
-            };
-            assert_eq!(move_request, expected_move_request);
+    for offset in Offset::ORTHOGONAL.iter().chain(Offset::DIAGONAL.iter()) {
+        if let Some(position) = square + *offset {
+            if is_piece(&position, &[PieceType::King]) {
+                attackers.push(position);
+            }
         }
+    }
 
-        // Promotion move request
-        {
-            let move_request =
-                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Queen);
-            let expected_move_request = MoveRequest {
-                start: Position::a7(),
-                end: Position::a8(),
-                promotion: Some(PromotionType::Queen),
-            };
-            assert_eq!(move_request, expected_move_request);
+    let slider_directions = [
+        (Offset::ORTHOGONAL, [PieceType::Rook, PieceType::Queen]),
+        (Offset::DIAGONAL, [PieceType::Bishop, PieceType::Queen]),
+    ];
+    for (offsets, piece_types) in &slider_directions {
+        for offset in offsets {
+            let mut current = square + *offset;
+            while let Some(position) = current {
+                if Some(&position) != ignoring && board.piece_at(&position).is_some() {
+                    if is_piece(&position, piece_types) {
+                        attackers.push(position);
+                    }
+                    break;
+                }
+                current = position + *offset;
+            }
         }
     }
 
-    #[test]
-    fn move_request_from_coordinate_test() -> Result<(), ParseError> {
-        // Normal move
-        {
-            let move_request = MoveRequest::from_coordinate("e3e4").unwrap();
-            let expected_move_request = MoveRequest::new(Position::e3(), Position::e4());
+    attackers
+}
 
-            assert_eq!(move_request, expected_move_request);
-        }
+// Maps each piece pinned to the king onto the squares it can still move to, which are the ones
+// between the king and the pinning piece, including the pinning piece itself.
+fn pinned_pieces(
+    board: &Board,
+    king_position: &Position,
+    side: &Side,
+) -> HashMap<Position, Vec<Position>> {
+    let mut pins = HashMap::new();
 
-        // Invalid start position
-        assert!(MoveRequest::from_coordinate("e9e4").is_err());
+    let slider_directions = [
+        (Offset::ORTHOGONAL, [PieceType::Rook, PieceType::Queen]),
+        (Offset::DIAGONAL, [PieceType::Bishop, PieceType::Queen]),
+    ];
+    for (offsets, piece_types) in &slider_directions {
+        for offset in offsets {
+            let mut line = Vec::new();
+            let mut pinned = None;
+
+            let mut current = king_position + *offset;
+            while let Some(position) = current {
+                current = position + *offset;
+                line.push(position);
+
+                let Some(piece) = board.piece_at(&position) else {
+                    continue;
+                };
 
-        // Invalid end position
-        assert!(MoveRequest::from_coordinate("e3x2").is_err());
+                match &pinned {
+                    None if piece.side == *side => pinned = Some(position),
+                    Some(pinned)
+                        if piece.side != *side && piece_types.contains(&piece.piece_type) =>
+                    {
+                        pins.insert(*pinned, line);
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+    }
 
-        // Too small
-        assert!(MoveRequest::from_coordinate("e3e").is_err());
+    pins
+}
 
-        // Queen promotion
-        {
-            let move_request = MoveRequest::from_coordinate("a7a8q")?;
-            let expected_move_request =
-                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Queen);
+// The squares strictly between two squares on the same rank, file or diagonal.
+pub(super) fn squares_between(from: &Position, to: &Position) -> Vec<Position> {
+    let file_difference = to.file() as i32 - from.file() as i32;
+    let rank_difference = to.rank() as i32 - from.rank() as i32;
+    if file_difference != 0
+        && rank_difference != 0
+        && file_difference.abs() != rank_difference.abs()
+    {
+        return Vec::new();
+    }
 
-            assert_eq!(move_request, expected_move_request);
+    let step = Offset::new(file_difference.signum(), rank_difference.signum());
+    let mut squares = Vec::new();
+    let mut current = from + step;
+    while let Some(position) = current {
+        if position == *to {
+            break;
         }
+        current = position + step;
+        squares.push(position);
+    }
 
-        // Knight promotion
-        {
-            let move_request = MoveRequest::from_coordinate("a7a8n")?;
-            let expected_move_request =
-                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Knight);
+    squares
+}
 
-            assert_eq!(move_request, expected_move_request);
-        }
+fn side_positions(board: &Board, side: &Side) -> SquareSet {
+    match side {
+        Side::White => *board.get_white_positions(),
+        Side::Black => *board.get_black_positions(),
+    }
+}
 
-        // Bishop promotion
-        {
-            let move_request = MoveRequest::from_coordinate("a7a8b")?;
-            let expected_move_request =
-                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Bishop);
+fn is_legal_move(
+    board: &Board,
+    side: &Side,
+    start: &Position,
+    end: &Position,
+    move_kind: &MoveKind,
+) -> bool {
+    let move_request = match move_kind {
+        // Just pick a promotion type, it's just to ensure that the move_piece() call succeeds.
+        MoveKind::Promotion(_) => MoveRequest::promotion(*start, *end, PromotionType::Queen),
+        _ => MoveRequest::new(*start, *end),
+    };
 
-            assert_eq!(move_request, expected_move_request);
-        }
+    stats::count_legality_simulation();
 
-        // Rook promotion
-        {
-            let move_request = MoveRequest::from_coordinate("a7a8r")?;
-            let expected_move_request =
-                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Rook);
+    let mut new_board = board.clone_for_simulation();
+    move_piece(&mut new_board, move_request).is_ok() && !is_in_check(&new_board, side)
+}
 
-            assert_eq!(move_request, expected_move_request);
-        }
+pub fn only_move(board: &Board) -> Option<MoveRequest> {
+    let side = board.side_to_move();
 
-        // Invalid promotion
-        assert!(MoveRequest::from_coordinate("a7a8p").is_err());
+    let mut found_move = None;
+    for (start, piece_moves) in get_all_moves(board, &side) {
+        for (end, move_kind) in piece_moves {
+            if !is_legal_move(board, &side, &start, &end, &move_kind) {
+                continue;
+            }
 
-        Ok(())
+            // Each promotion choice is a different move, so a promotion is never the only move.
+            if found_move.is_some() || matches!(move_kind, MoveKind::Promotion(_)) {
+                return None;
+            }
+
+            found_move = Some(MoveRequest::new(start, end));
+        }
     }
 
-    #[test]
-    fn get_pawn_moves_white() -> Result<(), ParseError> {
-        // White starting line
-        {
-            let board = Board::default();
-            let moves = get_pawn_moves(&board, &Position::f2(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::f3(), MoveKind::Move),
-                (Position::f4(), MoveKind::DoubleMove(Position::f3())),
-            ]);
+    found_move
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+pub fn checkmate_details(board: &Board) -> Option<MateDetails> {
+    if get_move_state(board) != MoveState::Checkmate {
+        return None;
+    }
 
-        // White single move
-        {
-            let board = fen::parse("rnbqkbnr/ppp1pppp/3p4/8/8/3P4/PPP1PPPP/RNBQKBNR w KQkq - 0 2")?;
-            let moves = get_pawn_moves(&board, &Position::d3(), &Side::White);
-            let expected_moves = HashMap::from([(Position::d4(), MoveKind::Move)]);
+    let side = board.side_to_move();
+    let king_position = board.king_position(&side)?;
 
-            assert_eq!(moves, expected_moves);
+    let mut checking_pieces = Vec::new();
+    for (position, moves) in get_all_moves(board, &side.opponent()) {
+        if moves.contains_key(&king_position) {
+            let piece = *board.piece_at(&position).unwrap();
+            checking_pieces.push((position, piece));
         }
+    }
+    checking_pieces.sort_by_key(|(position, _)| position.value());
 
-        // White right diagonal captures
-        {
-            let board =
-                fen::parse("rnbqkbnr/pppp1ppp/8/4p3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 2")?;
-            let moves = get_pawn_moves(&board, &Position::d4(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d5(), MoveKind::Move),
-                (Position::e5(), MoveKind::Capture),
-            ]);
-
-            assert_eq!(moves, expected_moves);
+    // Every escape square is unusable in a checkmate, either because one of our own pieces
+    // is in the way or because the king would still be in check there.
+    let mut blocked_escapes = Vec::new();
+    for offset in [
+        Offset::new(-1, -1),
+        Offset::new(0, -1),
+        Offset::new(1, -1),
+        Offset::new(-1, 0),
+        Offset::new(1, 0),
+        Offset::new(-1, 1),
+        Offset::new(0, 1),
+        Offset::new(1, 1),
+    ] {
+        if let Some(position) = Position::from_offset(&king_position, &offset) {
+            let blocker = match board.piece_at(&position) {
+                Some(piece) if piece.side == side => EscapeBlocker::Occupied,
+                _ => EscapeBlocker::Attacked,
+            };
+            blocked_escapes.push((position, blocker));
         }
+    }
 
-        // White left diagonal captures
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1ppppp/8/2p5/3P4/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 2")?;
-            let moves = get_pawn_moves(&board, &Position::d4(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d5(), MoveKind::Move),
-                (Position::c5(), MoveKind::Capture),
-            ]);
-
-            assert_eq!(moves, expected_moves);
+    let mut interposition_squares = Vec::new();
+    if let [(checker_position, checker)] = checking_pieces.as_slice() {
+        if matches!(
+            checker.piece_type,
+            PieceType::Bishop | PieceType::Rook | PieceType::Queen
+        ) {
+            let file_step = (king_position.file() as i32 - checker_position.file() as i32).signum();
+            let rank_step = (king_position.rank() as i32 - checker_position.rank() as i32).signum();
+            let step = Offset::new(file_step, rank_step);
+
+            let mut current = Position::from_offset(checker_position, &step);
+            while let Some(position) = current {
+                if position == king_position {
+                    break;
+                }
+                current = Position::from_offset(&position, &step);
+                interposition_squares.push(position);
+            }
         }
+    }
 
-        // White can't move
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1ppppp/8/3P4/8/P1p5/1PP1PPPP/RNBQKBNR w KQkq - 0 4")?;
-            let moves = get_pawn_moves(&board, &Position::c2(), &Side::White);
-            let expected_moves = HashMap::new();
-
-            assert_eq!(moves, expected_moves);
-        }
+    Some(MateDetails {
+        king_position,
+        checking_pieces,
+        blocked_escapes,
+        interposition_squares,
+    })
+}
 
-        // White en passant left
-        {
-            let board =
-                fen::parse("rnbqkbnr/1p1ppppp/3P4/p1p5/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 4")?;
-            let moves = get_pawn_moves(&board, &Position::d6(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::c7(), MoveKind::EnPassant(Position::c6())),
-                (Position::e7(), MoveKind::Capture),
-            ]);
+// Counts the leaf nodes of the legal move tree, with each promotion choice as its own move.
+pub fn perft(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
 
-            assert_eq!(moves, expected_moves);
-        }
+    let side = board.side_to_move();
+    let mut board = board.clone();
+    let mut nodes = 0;
+    for (start, piece_moves) in get_all_legal_moves(&board, &side) {
+        for (end, move_kind) in piece_moves {
+            let requests = perft_requests(start, end, &move_kind);
 
-        // White en passant right
-        {
-            let board =
-                fen::parse("rnbqkbnr/pppp1pp1/3P4/4p2p/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 4")?;
-            let moves = get_pawn_moves(&board, &Position::d6(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::e7(), MoveKind::EnPassant(Position::e6())),
-                (Position::c7(), MoveKind::Capture),
-            ]);
+            if depth == 1 {
+                nodes += requests.len() as u64;
+                continue;
+            }
 
-            assert_eq!(moves, expected_moves);
+            for request in requests {
+                let (_, move_undo) = make_move(&mut board, request).unwrap();
+                nodes += perft(&board, depth - 1);
+                board.undo(move_undo);
+            }
         }
+    }
 
-        // White promotion
-        {
-            let board =
-                fen::parse("rn1qkbnr/ppP1ppp1/3p3p/5b2/8/8/P1PPPPPP/RNBQKBNR w KQkq - 0 5")?;
-            let moves = get_pawn_moves(&board, &Position::c7(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::b8(), MoveKind::Promotion(true)),
-                (Position::c8(), MoveKind::Promotion(false)),
-                (Position::d8(), MoveKind::Promotion(true)),
-            ]);
+    nodes
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+// Perft split by the first move, keyed by the move in coordinate notation, as engines print for
+// "go perft".
+pub fn perft_divide(board: &Board, depth: u32) -> HashMap<String, u64> {
+    let mut divide = HashMap::new();
+    if depth == 0 {
+        return divide;
+    }
 
-        Ok(())
+    let side = board.side_to_move();
+    let mut board = board.clone();
+    for (start, piece_moves) in get_all_legal_moves(&board, &side) {
+        for (end, move_kind) in piece_moves {
+            for request in perft_requests(start, end, &move_kind) {
+                let coordinate = request.to_coordinate();
+                let (_, move_undo) = make_move(&mut board, request).unwrap();
+                divide.insert(coordinate, perft(&board, depth - 1));
+                board.undo(move_undo);
+            }
+        }
     }
 
-    #[test]
-    fn get_pawn_moves_black() -> Result<(), ParseError> {
-        // Black starting line
-        {
-            let board = Board::default();
-            let moves = get_pawn_moves(&board, &Position::f7(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::f6(), MoveKind::Move),
-                (Position::f5(), MoveKind::DoubleMove(Position::f6())),
-            ]);
+    divide
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+// Where perft_divide first disagrees with a reference. `moves` leads from the starting position
+// to `fen`, and `mismatches` lists each move whose count differs there as (move, ours, reference),
+// with None for a move only one side generated.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DivergencePath {
+    pub moves: Vec<String>,
+    pub fen: String,
+    pub depth: u32,
+    pub mismatches: Vec<(String, Option<u64>, Option<u64>)>,
+}
 
-        // Black single move
-        {
-            let board = fen::parse("rnbqkbnr/ppp1pppp/3p4/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 2")?;
-            let moves = get_pawn_moves(&board, &Position::d6(), &Side::Black);
-            let expected_moves = HashMap::from([(Position::d5(), MoveKind::Move)]);
+// Compares perft_divide against `reference`, which is given a FEN and a depth and returns its own
+// divide, and follows the first move with differing counts down until depth 1 or a move that only
+// one side generates. Returns None if the counts agree.
+pub fn find_divergence(
+    board: &Board,
+    depth: u32,
+    reference: &dyn Fn(&str, u32) -> HashMap<String, u64>,
+) -> Option<DivergencePath> {
+    let mut board = board.clone();
+    let mut depth = depth;
+    let mut moves = Vec::new();
+
+    loop {
+        let fen = crate::fen::generate(&board);
+        let ours = perft_divide(&board, depth);
+        let theirs = reference(&fen, depth);
+
+        let mut all_moves: Vec<&String> = ours.keys().chain(theirs.keys()).collect();
+        all_moves.sort();
+        all_moves.dedup();
+
+        let mismatches: Vec<(String, Option<u64>, Option<u64>)> = all_moves
+            .into_iter()
+            .map(|coordinate| {
+                (
+                    coordinate.clone(),
+                    ours.get(coordinate).copied(),
+                    theirs.get(coordinate).copied(),
+                )
+            })
+            .filter(|(_, ours, theirs)| ours != theirs)
+            .collect();
+        if mismatches.is_empty() {
+            return None;
+        }
 
-            assert_eq!(moves, expected_moves);
+        // A move both sides generate but count differently is where to look further.
+        let next_move = mismatches
+            .iter()
+            .find(|(_, ours, theirs)| ours.is_some() && theirs.is_some())
+            .map(|(coordinate, _, _)| coordinate.clone());
+        let request =
+            next_move.and_then(|coordinate| MoveRequest::from_coordinate(&coordinate).ok());
+        match request {
+            Some(request) if depth > 1 => {
+                moves.push(request.to_coordinate());
+                make_move(&mut board, request).unwrap();
+                depth -= 1;
+            }
+            _ => {
+                return Some(DivergencePath {
+                    moves,
+                    fen,
+                    depth,
+                    mismatches,
+                })
+            }
         }
+    }
+}
 
-        // Black right diagonal captures
-        {
-            let board =
-                fen::parse("rnbqkbnr/pppp1ppp/8/4p3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 2")?;
-            let moves = get_pawn_moves(&board, &Position::e5(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::e4(), MoveKind::Move),
-                (Position::d4(), MoveKind::Capture),
-            ]);
+// The leaf move breakdown from the perft results tables. Captures include en passant captures
+// and promotions that capture.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passant: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub checkmates: u64,
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+impl std::ops::AddAssign for PerftStats {
+    fn add_assign(&mut self, other: PerftStats) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.checkmates += other.checkmates;
+    }
+}
 
-        // Black left diagonal captures
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1ppppp/8/2p5/3P4/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 2")?;
-            let moves = get_pawn_moves(&board, &Position::c5(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d4(), MoveKind::Capture),
-                (Position::c4(), MoveKind::Move),
-            ]);
+impl std::fmt::Display for PerftStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Nodes:      {}", self.nodes)?;
+        writeln!(f, "Captures:   {}", self.captures)?;
+        writeln!(f, "E.p.:       {}", self.en_passant)?;
+        writeln!(f, "Castles:    {}", self.castles)?;
+        writeln!(f, "Promotions: {}", self.promotions)?;
+        writeln!(f, "Checks:     {}", self.checks)?;
+        write!(f, "Checkmates: {}", self.checkmates)
+    }
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+// Like perft, but also classifies the moves played at the last ply.
+pub fn perft_stats(board: &Board, depth: u32) -> PerftStats {
+    if depth == 0 {
+        return PerftStats {
+            nodes: 1,
+            ..PerftStats::default()
+        };
+    }
 
-        // Black can't move
-        {
-            let board = fen::parse("rnbqkbnr/pp1ppppp/3P4/8/2p5/8/PPP1PPPP/RNBQKBNR b KQkq - 0 3")?;
-            let moves = get_pawn_moves(&board, &Position::d7(), &Side::Black);
-            let expected_moves = HashMap::new();
+    let side = board.side_to_move();
+    let mut board = board.clone();
+    let mut stats = PerftStats::default();
+    for (start, piece_moves) in get_all_legal_moves(&board, &side) {
+        for (end, move_kind) in piece_moves {
+            for request in perft_requests(start, end, &move_kind) {
+                let (_, move_undo) = make_move(&mut board, request).unwrap();
 
-            assert_eq!(moves, expected_moves);
+                if depth == 1 {
+                    stats += leaf_stats(&board, &move_kind);
+                } else {
+                    stats += perft_stats(&board, depth - 1);
+                }
+
+                board.undo(move_undo);
+            }
         }
+    }
 
-        // Black en passant left
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppp1pppp/7P/8/4P3/3p4/PPPP1PP1/RNBQKBNR b KQkq e3 0 4")?;
-            let moves = get_pawn_moves(&board, &Position::d3(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::e2(), MoveKind::EnPassant(Position::e3())),
-                (Position::c2(), MoveKind::Capture),
-            ]);
+    stats
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+fn leaf_stats(board: &Board, move_kind: &MoveKind) -> PerftStats {
+    let is_check = is_in_check(board, &board.side_to_move());
+
+    PerftStats {
+        nodes: 1,
+        captures: u64::from(matches!(
+            move_kind,
+            MoveKind::Capture | MoveKind::EnPassant(_) | MoveKind::Promotion(true)
+        )),
+        en_passant: u64::from(matches!(move_kind, MoveKind::EnPassant(_))),
+        castles: u64::from(matches!(
+            move_kind,
+            MoveKind::ShortCastle | MoveKind::LongCastle
+        )),
+        promotions: u64::from(matches!(move_kind, MoveKind::Promotion(_))),
+        checks: u64::from(is_check),
+        checkmates: u64::from(is_check && !board.has_legal_move()),
+    }
+}
 
-        // Black en passant right
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppp1pppp/7P/8/2P5/3p4/PP1PPPP1/RNBQKBNR b KQkq c3 0 4")?;
-            let moves = get_pawn_moves(&board, &Position::d3(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::c2(), MoveKind::EnPassant(Position::c3())),
-                (Position::e2(), MoveKind::Capture),
-            ]);
+// Each promotion choice is its own move.
+fn perft_requests(start: Position, end: Position, move_kind: &MoveKind) -> Vec<MoveRequest> {
+    match move_kind {
+        MoveKind::Promotion(_) => vec![
+            MoveRequest::promotion(start, end, PromotionType::Queen),
+            MoveRequest::promotion(start, end, PromotionType::Rook),
+            MoveRequest::promotion(start, end, PromotionType::Bishop),
+            MoveRequest::promotion(start, end, PromotionType::Knight),
+        ],
+        _ => vec![MoveRequest::new(start, end)],
+    }
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+// Superseded by the Board occupancy methods, re-exported from board so older call sites still
+// compile.
+#[deprecated(note = "use Board::is_occupied")]
+pub fn contains_piece(board: &Board, position: &Position) -> bool {
+    board.is_occupied(position)
+}
 
-        // Black promotion
-        {
-            let board = fen::parse("rnbqkbnr/p1pppppp/8/6B1/8/3P4/PPp1PPPP/RN1QKBNR b KQkq - 1 5")?;
-            let moves = get_pawn_moves(&board, &Position::c2(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::b1(), MoveKind::Promotion(true)),
-                (Position::c1(), MoveKind::Promotion(false)),
-                (Position::d1(), MoveKind::Promotion(true)),
-            ]);
+#[deprecated(note = "use Board::is_occupied_by with the opponent")]
+pub fn contains_enemy_piece(board: &Board, position: &Position, side: &Side) -> bool {
+    board.is_occupied_by(position, &side.opponent())
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+#[deprecated(note = "use Board::is_empty_between")]
+pub fn are_positions_empty(board: &Board, positions: &[Position]) -> bool {
+    positions
+        .iter()
+        .all(|position| !board.is_occupied(position))
+}
 
-        Ok(())
+// The side and castle that a rook on one of the four corner squares belongs to.
+fn rook_corner(position: &Position) -> Option<(Side, CastleSide)> {
+    match *position {
+        Position::A1 => Some((Side::White, CastleSide::Long)),
+        Position::H1 => Some((Side::White, CastleSide::Short)),
+        Position::A8 => Some((Side::Black, CastleSide::Long)),
+        Position::H8 => Some((Side::Black, CastleSide::Short)),
+        _ => None,
     }
+}
 
-    #[test]
-    fn get_knight_moves_test() -> Result<(), ParseError> {
-        // All moves
-        {
-            let board =
-                fen::parse("rnbqkbnr/3ppppp/ppp5/8/4N3/3P1P2/PPP1P1PP/R1BQKBNR b KQkq - 0 4")?;
-            let moves = get_knight_moves(&board, &Position::e4(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::f6(), MoveKind::Move),
-                (Position::g5(), MoveKind::Move),
-                (Position::g3(), MoveKind::Move),
-                (Position::f2(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::c3(), MoveKind::Move),
-                (Position::c5(), MoveKind::Move),
-                (Position::d6(), MoveKind::Move),
-            ]);
+// Whether the request is a legal pawn move onto the promotion rank, so a caller can ask for the
+// promotion piece before attempting it.
+pub fn is_promotion_move(board: &Board, request: &MoveRequest) -> bool {
+    let side = board.side_to_move();
+    let is_own_pawn = board.piece_at(&request.start) == Some(&Piece::new(PieceType::Pawn, side));
+    if !is_own_pawn || request.end.rank() != side.promotion_rank() {
+        return false;
+    }
 
-            assert_eq!(moves, expected_moves);
-        }
+    get_all_legal_moves(board, &side)
+        .get(&request.start)
+        .and_then(|piece_moves| piece_moves.get(&request.end))
+        .is_some_and(|move_kind| matches!(move_kind, MoveKind::Promotion(_)))
+}
 
-        // No moves
-        {
-            let board = fen::parse("rnbqkbnr/1ppppppp/p7/8/8/P1P5/1P1PPPPP/RNBQKBNR b KQkq - 0 2")?;
-            let moves = get_knight_moves(&board, &Position::b1(), &Side::White);
-            let expected_moves = HashMap::new();
+// Answers whether each candidate is legal for the side to move, generating the legal moves once.
+// A promotion only matches with a promotion piece, and any other move only without one.
+pub fn filter_legal(board: &Board, candidates: &[MoveRequest]) -> Vec<bool> {
+    let all_legal_moves = get_all_legal_moves(board, &board.side_to_move());
+
+    candidates
+        .iter()
+        .map(|candidate| {
+            let move_kind = all_legal_moves
+                .get(&candidate.start)
+                .and_then(|piece_moves| piece_moves.get(&candidate.end));
+
+            match move_kind {
+                Some(MoveKind::Promotion(_)) => candidate.promotion.is_some(),
+                Some(_) => candidate.promotion.is_none(),
+                None => false,
+            }
+        })
+        .collect()
+}
 
-            assert_eq!(moves, expected_moves);
-        }
+pub fn is_en_passant_target(board: &Board, position: &Position) -> bool {
+    match board.en_passant_target() {
+        Some(en_passant_target) => *position == en_passant_target,
+        None => false,
+    }
+}
 
-        // Left side of board
-        {
-            let board = fen::parse("rnbqkbnr/2pppppp/pp6/8/8/N1P5/PP1PPPPP/R1BQKBNR w KQkq - 0 3")?;
-            let moves = get_knight_moves(&board, &Position::a3(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::b5(), MoveKind::Move),
-                (Position::c4(), MoveKind::Move),
-                (Position::c2(), MoveKind::Move),
-                (Position::b1(), MoveKind::Move),
-            ]);
+pub fn possible_en_passant_capture(board: &Board) -> bool {
+    match board.en_passant_target() {
+        Some(target) => {
+            let side = board.side_to_move();
+            // The capturing pawn stands one rank behind the target, from the mover's point of view.
+            let left_diagonal = target + Offset::SOUTH_WEST.for_side(&side);
+            let right_diagonal = target + Offset::SOUTH_EAST.for_side(&side);
 
-            assert_eq!(moves, expected_moves);
-        }
+            let mut valid_capture = false;
+            if let Some(left_diagonal) = left_diagonal {
+                if let Ok(moves) = get_piece_moves(board, &side, &left_diagonal) {
+                    valid_capture = moves.contains_key(&target);
+                };
+            };
 
-        // Right side of board
-        {
-            let board = fen::parse("rnbqkbnr/pppppp2/6pp/8/8/5P1N/PPPPP1PP/RNBQKB1R w KQkq - 0 3")?;
-            let moves = get_knight_moves(&board, &Position::h3(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::g5(), MoveKind::Move),
-                (Position::f4(), MoveKind::Move),
-                (Position::f2(), MoveKind::Move),
-                (Position::g1(), MoveKind::Move),
-            ]);
+            // Only check the next position if we didn't already find a valid capture.
+            if !valid_capture {
+                if let Some(right_diagonal) = right_diagonal {
+                    if let Ok(moves) = get_piece_moves(board, &side, &right_diagonal) {
+                        valid_capture = moves.contains_key(&target);
+                    };
+                }
+            }
 
-            assert_eq!(moves, expected_moves);
+            valid_capture
         }
+        None => false,
+    }
+}
 
-        // Captures and own pieces
+#[macro_export]
+macro_rules! board_position {
+    ( $position:ident, None ) => {
+        (Position::$position(), None)
+    };
+
+    ( $position:ident, $piece_type:ident, $side:ident ) => {
+        (
+            Position::$position(),
+            Some(Piece::new(PieceType::$piece_type, Side::$side)),
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! piece_position {
+    ( $position:ident, $piece_type:ident, $side:ident ) => {
+        (
+            Position::$position(),
+            Piece::new(PieceType::$piece_type, Side::$side),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_boards_eq, fen};
+
+    use super::*;
+
+    #[test]
+    fn move_request_test() {
+        // Normal move request
         {
-            let board =
-                fen::parse("rnbqkbnr/p1p1ppp1/1p1p3p/8/4N3/3P4/PPP1PPPP/R1BQKBNR w KQkq - 0 4")?;
-            let moves = get_knight_moves(&board, &Position::e4(), &Side::White);
-            // No f2 because our piece is there, but still d6 because black's piece is there.
-            let expected_moves = HashMap::from([
-                (Position::f6(), MoveKind::Move),
-                (Position::g5(), MoveKind::Move),
-                (Position::g3(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::c3(), MoveKind::Move),
-                (Position::c5(), MoveKind::Move),
-                (Position::d6(), MoveKind::Capture),
-            ]);
+            let move_request = MoveRequest::new(Position::a2(), Position::a3());
+            let expected_move_request = MoveRequest {
+                start: Position::a2(),
+                end: Position::a3(),
+                promotion: None,
+            };
+            assert_eq!(move_request, expected_move_request);
+        }
 
-            assert_eq!(moves, expected_moves);
+        // Promotion move request
+        {
+            let move_request =
+                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Queen);
+            let expected_move_request = MoveRequest {
+                start: Position::a7(),
+                end: Position::a8(),
+                promotion: Some(PromotionType::Queen),
+            };
+            assert_eq!(move_request, expected_move_request);
+        }
+    }
+
+    #[test]
+    fn move_request_from_coordinate_test() -> Result<(), ParseError> {
+        // Normal move
+        {
+            let move_request = MoveRequest::from_coordinate("e3e4").unwrap();
+            let expected_move_request = MoveRequest::new(Position::e3(), Position::e4());
+
+            assert_eq!(move_request, expected_move_request);
+        }
+
+        // Invalid start position
+        assert!(MoveRequest::from_coordinate("e9e4").is_err());
+
+        // Invalid end position
+        assert!(MoveRequest::from_coordinate("e3x2").is_err());
+
+        // Too small
+        assert!(MoveRequest::from_coordinate("e3e").is_err());
+
+        // Queen promotion
+        {
+            let move_request = MoveRequest::from_coordinate("a7a8q")?;
+            let expected_move_request =
+                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Queen);
+
+            assert_eq!(move_request, expected_move_request);
+        }
+
+        // Knight promotion
+        {
+            let move_request = MoveRequest::from_coordinate("a7a8n")?;
+            let expected_move_request =
+                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Knight);
+
+            assert_eq!(move_request, expected_move_request);
+        }
+
+        // Bishop promotion
+        {
+            let move_request = MoveRequest::from_coordinate("a7a8b")?;
+            let expected_move_request =
+                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Bishop);
+
+            assert_eq!(move_request, expected_move_request);
+        }
+
+        // Rook promotion
+        {
+            let move_request = MoveRequest::from_coordinate("a7a8r")?;
+            let expected_move_request =
+                MoveRequest::promotion(Position::a7(), Position::a8(), PromotionType::Rook);
+
+            assert_eq!(move_request, expected_move_request);
         }
 
+        // Invalid promotion
+        assert!(MoveRequest::from_coordinate("a7a8p").is_err());
+
+        // The suffix is kept even when the move can't be a promotion, for the board to reject.
+        assert_eq!(
+            MoveRequest::from_coordinate("e2e4q")?,
+            MoveRequest::promotion(Position::e2(), Position::e4(), PromotionType::Queen)
+        );
+
         Ok(())
     }
 
     #[test]
-    fn get_rook_moves_test() -> Result<(), ParseError> {
-        // All directions empty to edge of board
+    fn promotion_data_mismatch_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/1P6/8/8/8/8/4P3/R3K3 w - - 0 1")?;
+
+        let mut new_board = board.clone();
+        assert_eq!(
+            move_piece(&mut new_board, MoveRequest::from_coordinate("e2e4q")?).unwrap_err(),
+            MoveError::UnexpectedPromotion
+        );
+        assert_eq!(
+            move_piece(&mut new_board, MoveRequest::from_coordinate("a1a8r")?).unwrap_err(),
+            MoveError::UnexpectedPromotion
+        );
+        assert_eq!(
+            move_piece(&mut new_board, MoveRequest::from_coordinate("b7b8")?).unwrap_err(),
+            MoveError::MissingPromotion
+        );
+        assert_boards_eq!(new_board, board);
+
+        assert!(move_piece(&mut new_board, MoveRequest::from_coordinate("b7b8q")?).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn move_request_from_coordinate_with_test() -> Result<(), ParseError> {
+        let strict = CoordinateOptions::default();
+        let auto_queen = CoordinateOptions {
+            default_promotion: Some(PromotionType::Queen),
+        };
+        let auto_knight = CoordinateOptions {
+            default_promotion: Some(PromotionType::Knight),
+        };
+
+        // Strict parsing leaves the promotion empty
+        assert_eq!(
+            MoveRequest::from_coordinate_with("e7e8", &strict)?,
+            MoveRequest::new(Position::e7(), Position::e8())
+        );
+
+        // Auto queen
+        assert_eq!(
+            MoveRequest::from_coordinate_with("e7e8", &auto_queen)?,
+            MoveRequest::promotion(Position::e7(), Position::e8(), PromotionType::Queen)
+        );
+
+        // Auto knight, including a capture promotion
+        assert_eq!(
+            MoveRequest::from_coordinate_with("d2c1", &auto_knight)?,
+            MoveRequest::promotion(Position::d2(), Position::c1(), PromotionType::Knight)
+        );
+
+        // An explicit promotion wins over the default
+        assert_eq!(
+            MoveRequest::from_coordinate_with("e7e8r", &auto_queen)?,
+            MoveRequest::promotion(Position::e7(), Position::e8(), PromotionType::Rook)
+        );
+
+        // Moves that can't be promotions are left alone
+        assert_eq!(
+            MoveRequest::from_coordinate_with("e2e4", &auto_queen)?,
+            MoveRequest::new(Position::e2(), Position::e4())
+        );
+        assert_eq!(
+            MoveRequest::from_coordinate_with("a7h8", &auto_queen)?,
+            MoveRequest::new(Position::a7(), Position::h8())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_pawn_moves_white() -> Result<(), ParseError> {
+        // White starting line
         {
-            let board = fen::parse("r1bqkbnr/3pppp1/P6p/2p5/1R6/2N5/2PPPPPP/2BQKBNR w Kkq - 0 9")?;
-            let moves = get_rook_moves(&board, &Position::b4(), &Side::White);
+            let board = Board::default();
+            let moves = get_pawn_moves(&board, &Position::f2(), &Side::White);
             let expected_moves = HashMap::from([
-                (Position::b1(), MoveKind::Move),
-                (Position::b2(), MoveKind::Move),
-                (Position::b3(), MoveKind::Move),
-                (Position::b5(), MoveKind::Move),
-                (Position::b6(), MoveKind::Move),
-                (Position::b7(), MoveKind::Move),
-                (Position::b8(), MoveKind::Move),
-                (Position::a4(), MoveKind::Move),
-                (Position::c4(), MoveKind::Move),
-                (Position::d4(), MoveKind::Move),
-                (Position::e4(), MoveKind::Move),
-                (Position::f4(), MoveKind::Move),
-                (Position::g4(), MoveKind::Move),
-                (Position::h4(), MoveKind::Move),
+                (Position::f3(), MoveKind::Move),
+                (Position::f4(), MoveKind::DoubleMove(Position::f3())),
             ]);
 
             assert_eq!(moves, expected_moves);
         }
 
-        // Captures & own pieces
+        // White single move
+        {
+            let board = fen::parse("rnbqkbnr/ppp1pppp/3p4/8/8/3P4/PPP1PPPP/RNBQKBNR w KQkq - 0 2")?;
+            let moves = get_pawn_moves(&board, &Position::d3(), &Side::White);
+            let expected_moves = HashMap::from([(Position::d4(), MoveKind::Move)]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White right diagonal captures
         {
             let board =
-                fen::parse("r1bqkbnr/3ppp2/P1p3pp/8/2Rn4/1P6/2PPPPPP/1NBQKBNR w Kkq - 0 8")?;
-            let moves = get_rook_moves(&board, &Position::c4(), &Side::White);
+                fen::parse("rnbqkbnr/pppp1ppp/8/4p3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 2")?;
+            let moves = get_pawn_moves(&board, &Position::d4(), &Side::White);
             let expected_moves = HashMap::from([
-                (Position::a4(), MoveKind::Move),
-                (Position::b4(), MoveKind::Move),
-                (Position::d4(), MoveKind::Capture),
-                (Position::c3(), MoveKind::Move),
-                (Position::c5(), MoveKind::Move),
-                (Position::c6(), MoveKind::Capture),
+                (Position::d5(), MoveKind::Move),
+                (Position::e5(), MoveKind::Capture),
             ]);
 
             assert_eq!(moves, expected_moves);
         }
 
-        // No moves
+        // White left diagonal captures
         {
-            let board = Board::default();
-            let moves = get_rook_moves(&board, &Position::a1(), &Side::White);
-            let expected_moves = HashMap::new();
+            let board =
+                fen::parse("rnbqkbnr/pp1ppppp/8/2p5/3P4/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 2")?;
+            let moves = get_pawn_moves(&board, &Position::d4(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d5(), MoveKind::Move),
+                (Position::c5(), MoveKind::Capture),
+            ]);
 
             assert_eq!(moves, expected_moves);
         }
 
-        Ok(())
-    }
-
-    #[test]
-    fn get_bishop_moves_test() -> Result<(), ParseError> {
-        // All directions empty to edge of board
+        // White can't move
         {
             let board =
-                fen::parse("rnbqkbnr/1p2pp1p/p1pp2p1/8/8/3PBP1N/PPP1P1PP/RN1QKB1R w KQkq - 0 5")?;
-            let moves = get_bishop_moves(&board, &Position::e3(), &Side::White);
+                fen::parse("rnbqkbnr/pp1ppppp/8/3P4/8/P1p5/1PP1PPPP/RNBQKBNR w KQkq - 0 4")?;
+            let moves = get_pawn_moves(&board, &Position::c2(), &Side::White);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White en passant left
+        {
+            let board = fen::parse("rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 3")?;
+            let moves = get_pawn_moves(&board, &Position::d5(), &Side::White);
             let expected_moves = HashMap::from([
-                (Position::c1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::f4(), MoveKind::Move),
-                (Position::g5(), MoveKind::Move),
-                (Position::h6(), MoveKind::Move),
-                (Position::a7(), MoveKind::Move),
-                (Position::b6(), MoveKind::Move),
-                (Position::c5(), MoveKind::Move),
-                (Position::d4(), MoveKind::Move),
-                (Position::f2(), MoveKind::Move),
-                (Position::g1(), MoveKind::Move),
+                (Position::c6(), MoveKind::EnPassant(Position::c5())),
+                (Position::d6(), MoveKind::Move),
             ]);
 
             assert_eq!(moves, expected_moves);
         }
 
-        // Captures & own pieces
+        // White en passant right
         {
-            let board =
-                fen::parse("rnbqkbnr/1p2ppp1/p2p3p/2p5/8/3PBP2/PPP1PNPP/RN1QKB1R w KQkq - 0 6")?;
-            let moves = get_bishop_moves(&board, &Position::e3(), &Side::White);
+            let board = fen::parse("rnbqkbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
+            let moves = get_pawn_moves(&board, &Position::d5(), &Side::White);
             let expected_moves = HashMap::from([
-                (Position::c1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::f4(), MoveKind::Move),
-                (Position::g5(), MoveKind::Move),
-                (Position::h6(), MoveKind::Capture),
-                (Position::c5(), MoveKind::Capture),
-                (Position::d4(), MoveKind::Move),
+                (Position::e6(), MoveKind::EnPassant(Position::e5())),
+                (Position::d6(), MoveKind::Move),
             ]);
 
             assert_eq!(moves, expected_moves);
         }
 
-        // No moves
+        // White promotion
         {
-            let board = Board::default();
-            let moves = get_bishop_moves(&board, &Position::c1(), &Side::White);
-            let expected_moves = HashMap::new();
+            let board =
+                fen::parse("rn1qkbnr/ppP1ppp1/3p3p/5b2/8/8/P1PPPPPP/RNBQKBNR w KQkq - 0 5")?;
+            let moves = get_pawn_moves(&board, &Position::c7(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::b8(), MoveKind::Promotion(true)),
+                (Position::c8(), MoveKind::Promotion(false)),
+                (Position::d8(), MoveKind::Promotion(true)),
+            ]);
 
             assert_eq!(moves, expected_moves);
         }
@@ -1324,40 +2323,204 @@ mod tests {
     }
 
     #[test]
-    fn get_queen_moves_test() -> Result<(), ParseError> {
-        // All directions empty to edge of board
+    fn get_pawn_moves_black() -> Result<(), ParseError> {
+        // Black starting line
+        {
+            let board = Board::default();
+            let moves = get_pawn_moves(&board, &Position::f7(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::f6(), MoveKind::Move),
+                (Position::f5(), MoveKind::DoubleMove(Position::f6())),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black single move
+        {
+            let board = fen::parse("rnbqkbnr/ppp1pppp/3p4/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 2")?;
+            let moves = get_pawn_moves(&board, &Position::d6(), &Side::Black);
+            let expected_moves = HashMap::from([(Position::d5(), MoveKind::Move)]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black right diagonal captures
         {
             let board =
-                fen::parse("r1b1kbn1/1p3p1r/p1n1p1p1/7p/3Q4/PP3P1N/R1P1P1PP/1NB1KB1R w Kq - 2 12")?;
-            let moves = get_queen_moves(&board, &Position::d4(), &Side::White);
+                fen::parse("rnbqkbnr/pppp1ppp/8/4p3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 2")?;
+            let moves = get_pawn_moves(&board, &Position::e5(), &Side::Black);
             let expected_moves = HashMap::from([
-                (Position::a4(), MoveKind::Move),
-                (Position::b4(), MoveKind::Move),
-                (Position::c4(), MoveKind::Move),
                 (Position::e4(), MoveKind::Move),
-                (Position::f4(), MoveKind::Move),
-                (Position::g4(), MoveKind::Move),
-                (Position::h4(), MoveKind::Move),
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
+                (Position::d4(), MoveKind::Capture),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black left diagonal captures
+        {
+            let board =
+                fen::parse("rnbqkbnr/pp1ppppp/8/2p5/3P4/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 2")?;
+            let moves = get_pawn_moves(&board, &Position::c5(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d4(), MoveKind::Capture),
+                (Position::c4(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black can't move
+        {
+            let board = fen::parse("rnbqkbnr/pp1ppppp/3P4/8/2p5/8/PPP1PPPP/RNBQKBNR b KQkq - 0 3")?;
+            let moves = get_pawn_moves(&board, &Position::d7(), &Side::Black);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black en passant left
+        {
+            let board = fen::parse("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3")?;
+            let moves = get_pawn_moves(&board, &Position::d4(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::e3(), MoveKind::EnPassant(Position::e4())),
                 (Position::d3(), MoveKind::Move),
-                (Position::d5(), MoveKind::Move),
-                (Position::d6(), MoveKind::Move),
-                (Position::d7(), MoveKind::Move),
-                (Position::d8(), MoveKind::Move),
-                (Position::a7(), MoveKind::Move),
-                (Position::b6(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black en passant right
+        {
+            let board = fen::parse("rnbqkbnr/ppp1pppp/8/8/2Pp4/8/PP1PPPPP/RNBQKBNR b KQkq c3 0 3")?;
+            let moves = get_pawn_moves(&board, &Position::d4(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::c3(), MoveKind::EnPassant(Position::c4())),
+                (Position::d3(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black promotion
+        {
+            let board = fen::parse("rnbqkbnr/p1pppppp/8/6B1/8/3P4/PPp1PPPP/RN1QKBNR b KQkq - 1 5")?;
+            let moves = get_pawn_moves(&board, &Position::c2(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::b1(), MoveKind::Promotion(true)),
+                (Position::c1(), MoveKind::Promotion(false)),
+                (Position::d1(), MoveKind::Promotion(true)),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_knight_moves_test() -> Result<(), ParseError> {
+        // All moves
+        {
+            let board =
+                fen::parse("rnbqkbnr/3ppppp/ppp5/8/4N3/3P1P2/PPP1P1PP/R1BQKBNR b KQkq - 0 4")?;
+            let moves = get_knight_moves(&board, &Position::e4(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::f6(), MoveKind::Move),
+                (Position::g5(), MoveKind::Move),
+                (Position::g3(), MoveKind::Move),
+                (Position::f2(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::c3(), MoveKind::Move),
                 (Position::c5(), MoveKind::Move),
-                (Position::e3(), MoveKind::Move),
+                (Position::d6(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // No moves
+        {
+            let board = fen::parse("rnbqkbnr/1ppppppp/p7/8/8/P1P5/1P1PPPPP/RNBQKBNR b KQkq - 0 2")?;
+            let moves = get_knight_moves(&board, &Position::b1(), &Side::White);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Left side of board
+        {
+            let board = fen::parse("rnbqkbnr/2pppppp/pp6/8/8/N1P5/PP1PPPPP/R1BQKBNR w KQkq - 0 3")?;
+            let moves = get_knight_moves(&board, &Position::a3(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::b5(), MoveKind::Move),
+                (Position::c4(), MoveKind::Move),
+                (Position::c2(), MoveKind::Move),
+                (Position::b1(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Right side of board
+        {
+            let board = fen::parse("rnbqkbnr/pppppp2/6pp/8/8/5P1N/PPPPP1PP/RNBQKB1R w KQkq - 0 3")?;
+            let moves = get_knight_moves(&board, &Position::h3(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::g5(), MoveKind::Move),
+                (Position::f4(), MoveKind::Move),
                 (Position::f2(), MoveKind::Move),
                 (Position::g1(), MoveKind::Move),
-                (Position::a1(), MoveKind::Move),
-                (Position::b2(), MoveKind::Move),
-                (Position::c3(), MoveKind::Move),
-                (Position::e5(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Captures and own pieces
+        {
+            let board =
+                fen::parse("rnbqkbnr/p1p1ppp1/1p1p3p/8/4N3/3P4/PPP1PPPP/R1BQKBNR w KQkq - 0 4")?;
+            let moves = get_knight_moves(&board, &Position::e4(), &Side::White);
+            // No f2 because our piece is there, but still d6 because black's piece is there.
+            let expected_moves = HashMap::from([
                 (Position::f6(), MoveKind::Move),
-                (Position::g7(), MoveKind::Move),
-                (Position::h8(), MoveKind::Move),
+                (Position::g5(), MoveKind::Move),
+                (Position::g3(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::c3(), MoveKind::Move),
+                (Position::c5(), MoveKind::Move),
+                (Position::d6(), MoveKind::Capture),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_rook_moves_test() -> Result<(), ParseError> {
+        // All directions empty to edge of board
+        {
+            let board = fen::parse("r1bqkbnr/3pppp1/P6p/2p5/1R6/2N5/2PPPPPP/2BQKBNR w Kkq - 0 9")?;
+            let moves = get_rook_moves(&board, &Position::b4(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::b1(), MoveKind::Move),
+                (Position::b2(), MoveKind::Move),
+                (Position::b3(), MoveKind::Move),
+                (Position::b5(), MoveKind::Move),
+                (Position::b6(), MoveKind::Move),
+                (Position::b7(), MoveKind::Move),
+                (Position::b8(), MoveKind::Move),
+                (Position::a4(), MoveKind::Move),
+                (Position::c4(), MoveKind::Move),
+                (Position::d4(), MoveKind::Move),
+                (Position::e4(), MoveKind::Move),
+                (Position::f4(), MoveKind::Move),
+                (Position::g4(), MoveKind::Move),
+                (Position::h4(), MoveKind::Move),
             ]);
 
             assert_eq!(moves, expected_moves);
@@ -1366,27 +2529,15 @@ mod tests {
         // Captures & own pieces
         {
             let board =
-                fen::parse("r3k1n1/3b1pbr/ppn1p1p1/7p/3Q1P2/PPP3PN/R3P2P/1NB1KB1R w Kq - 1 15")?;
-            let moves = get_queen_moves(&board, &Position::d4(), &Side::White);
+                fen::parse("r1bqkbnr/3ppp2/P1p3pp/8/2Rn4/1P6/2PPPPPP/1NBQKBNR w Kkq - 0 8")?;
+            let moves = get_rook_moves(&board, &Position::c4(), &Side::White);
             let expected_moves = HashMap::from([
                 (Position::a4(), MoveKind::Move),
                 (Position::b4(), MoveKind::Move),
-                (Position::c4(), MoveKind::Move),
-                (Position::e4(), MoveKind::Move),
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::d3(), MoveKind::Move),
-                (Position::d5(), MoveKind::Move),
-                (Position::d6(), MoveKind::Move),
-                (Position::d7(), MoveKind::Capture),
-                (Position::b6(), MoveKind::Capture),
+                (Position::d4(), MoveKind::Capture),
+                (Position::c3(), MoveKind::Move),
                 (Position::c5(), MoveKind::Move),
-                (Position::e3(), MoveKind::Move),
-                (Position::f2(), MoveKind::Move),
-                (Position::g1(), MoveKind::Move),
-                (Position::e5(), MoveKind::Move),
-                (Position::f6(), MoveKind::Move),
-                (Position::g7(), MoveKind::Capture),
+                (Position::c6(), MoveKind::Capture),
             ]);
 
             assert_eq!(moves, expected_moves);
@@ -1395,632 +2546,1825 @@ mod tests {
         // No moves
         {
             let board = Board::default();
-            let moves = get_queen_moves(&board, &Position::d1(), &Side::White);
+            let moves = get_rook_moves(&board, &Position::a1(), &Side::White);
             let expected_moves = HashMap::new();
 
-            assert_eq!(moves, expected_moves);
-        }
+            assert_eq!(moves, expected_moves);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_bishop_moves_test() -> Result<(), ParseError> {
+        // All directions empty to edge of board
+        {
+            let board =
+                fen::parse("rnbqkbnr/1p2pp1p/p1pp2p1/8/8/3PBP1N/PPP1P1PP/RN1QKB1R w KQkq - 0 5")?;
+            let moves = get_bishop_moves(&board, &Position::e3(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::c1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::f4(), MoveKind::Move),
+                (Position::g5(), MoveKind::Move),
+                (Position::h6(), MoveKind::Move),
+                (Position::a7(), MoveKind::Move),
+                (Position::b6(), MoveKind::Move),
+                (Position::c5(), MoveKind::Move),
+                (Position::d4(), MoveKind::Move),
+                (Position::f2(), MoveKind::Move),
+                (Position::g1(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Captures & own pieces
+        {
+            let board =
+                fen::parse("rnbqkbnr/1p2ppp1/p2p3p/2p5/8/3PBP2/PPP1PNPP/RN1QKB1R w KQkq - 0 6")?;
+            let moves = get_bishop_moves(&board, &Position::e3(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::c1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::f4(), MoveKind::Move),
+                (Position::g5(), MoveKind::Move),
+                (Position::h6(), MoveKind::Capture),
+                (Position::c5(), MoveKind::Capture),
+                (Position::d4(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // No moves
+        {
+            let board = Board::default();
+            let moves = get_bishop_moves(&board, &Position::c1(), &Side::White);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_queen_moves_test() -> Result<(), ParseError> {
+        // All directions empty to edge of board
+        {
+            let board =
+                fen::parse("r1b1kbn1/1p3p1r/p1n1p1p1/7p/3Q4/PP3P1N/R1P1P1PP/1NB1KB1R w Kq - 2 12")?;
+            let moves = get_queen_moves(&board, &Position::d4(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::a4(), MoveKind::Move),
+                (Position::b4(), MoveKind::Move),
+                (Position::c4(), MoveKind::Move),
+                (Position::e4(), MoveKind::Move),
+                (Position::f4(), MoveKind::Move),
+                (Position::g4(), MoveKind::Move),
+                (Position::h4(), MoveKind::Move),
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::d3(), MoveKind::Move),
+                (Position::d5(), MoveKind::Move),
+                (Position::d6(), MoveKind::Move),
+                (Position::d7(), MoveKind::Move),
+                (Position::d8(), MoveKind::Move),
+                (Position::a7(), MoveKind::Move),
+                (Position::b6(), MoveKind::Move),
+                (Position::c5(), MoveKind::Move),
+                (Position::e3(), MoveKind::Move),
+                (Position::f2(), MoveKind::Move),
+                (Position::g1(), MoveKind::Move),
+                (Position::a1(), MoveKind::Move),
+                (Position::b2(), MoveKind::Move),
+                (Position::c3(), MoveKind::Move),
+                (Position::e5(), MoveKind::Move),
+                (Position::f6(), MoveKind::Move),
+                (Position::g7(), MoveKind::Move),
+                (Position::h8(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Captures & own pieces
+        {
+            let board =
+                fen::parse("r3k1n1/3b1pbr/ppn1p1p1/7p/3Q1P2/PPP3PN/R3P2P/1NB1KB1R w Kq - 1 15")?;
+            let moves = get_queen_moves(&board, &Position::d4(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::a4(), MoveKind::Move),
+                (Position::b4(), MoveKind::Move),
+                (Position::c4(), MoveKind::Move),
+                (Position::e4(), MoveKind::Move),
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::d3(), MoveKind::Move),
+                (Position::d5(), MoveKind::Move),
+                (Position::d6(), MoveKind::Move),
+                (Position::d7(), MoveKind::Capture),
+                (Position::b6(), MoveKind::Capture),
+                (Position::c5(), MoveKind::Move),
+                (Position::e3(), MoveKind::Move),
+                (Position::f2(), MoveKind::Move),
+                (Position::g1(), MoveKind::Move),
+                (Position::e5(), MoveKind::Move),
+                (Position::f6(), MoveKind::Move),
+                (Position::g7(), MoveKind::Capture),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // No moves
+        {
+            let board = Board::default();
+            let moves = get_queen_moves(&board, &Position::d1(), &Side::White);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_king_moves_test() -> Result<(), ParseError> {
+        // All directions
+        {
+            let board = fen::parse("rnbqkbnr/2pppppp/4P3/1p6/3K4/p7/PPPP1PPP/RNBQ1BNR w kq - 0 7")?;
+            let moves = get_king_moves(&board, &Position::d4(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d5(), MoveKind::Move),
+                (Position::e5(), MoveKind::Move),
+                (Position::e4(), MoveKind::Move),
+                (Position::e3(), MoveKind::Move),
+                (Position::d3(), MoveKind::Move),
+                (Position::c3(), MoveKind::Move),
+                (Position::c4(), MoveKind::Move),
+                (Position::c5(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Captures & own pieces, no checks or castles
+        {
+            let board = fen::parse("rnbqkbnr/1p1pppp1/p6p/8/2pKP3/8/PPPP1PPP/RNBQ1BNR w kq - 0 5")?;
+            let moves = get_king_moves(&board, &Position::d4(), &Side::White);
+            // Still c4 as a capture, but not e4 because of our own piece
+            let expected_moves = HashMap::from([
+                (Position::d5(), MoveKind::Move),
+                (Position::e5(), MoveKind::Move),
+                (Position::e3(), MoveKind::Move),
+                (Position::d3(), MoveKind::Move),
+                (Position::c3(), MoveKind::Move),
+                (Position::c4(), MoveKind::Capture),
+                (Position::c5(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White short & long castles
+        {
+            let board =
+                fen::parse("r3k2r/ppp1pp1p/2nqbnpb/3p4/3P4/2NQBNPB/PPP1PP1P/R3K2R w KQkq - 4 8")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::f1(), MoveKind::Move),
+                (Position::c1(), MoveKind::LongCastle),
+                (Position::g1(), MoveKind::ShortCastle),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no long castle because rook move
+        {
+            let board =
+                fen::parse("r3k2r/ppp1ppbp/2nqbnp1/3p4/3P4/2NQBNPB/PPP1PP1P/1R2K2R w Kkq - 6 9")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::f1(), MoveKind::Move),
+                (Position::g1(), MoveKind::ShortCastle),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no short castle because rook move
+        {
+            let board =
+                fen::parse("r3k2r/ppp1ppbp/2nqbnp1/3p4/3P4/2NQBNPB/PPP1PP1P/R3K1R1 w Qkq - 6 9")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+                (Position::f1(), MoveKind::Move),
+                (Position::c1(), MoveKind::LongCastle),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no castle because king move
+        {
+            let board =
+                fen::parse("r3k2r/ppp1ppbp/2nqbnp1/3p4/3P4/2NQBNPB/PPP1PP1P/R2K3R w kq - 6 9")?;
+            let moves = get_king_moves(&board, &Position::d1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d2(), MoveKind::Move),
+                (Position::c1(), MoveKind::Move),
+                (Position::e1(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no long castle because piece on b1
+        {
+            let board =
+                fen::parse("rn2kbnr/ppp1pppp/3qb3/3p4/3P4/3QB3/PPP1PPPP/RN2KBNR w KQkq - 4 4")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no long castle because piece on c1
+        {
+            let board =
+                fen::parse("rnb1kbnr/pp2pppp/2pq4/3p4/3P4/2NQ4/PPP1PPPP/R1B1KBNR w KQkq - 0 4")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::d1(), MoveKind::Move),
+                (Position::d2(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no long castle because piece on d1
+        {
+            let board =
+                fen::parse("rnbqkbnr/pp3ppp/2p1p3/3p4/3P4/N3B3/PPP1PPPP/R2QKBNR w KQkq - 0 4")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([(Position::d2(), MoveKind::Move)]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no short castle because piece on f1
+        {
+            let board = fen::parse("rnbqkbnr/pppppp1p/6p1/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no short castle because piece on g1
+        {
+            let board =
+                fen::parse("rnbqkbnr/ppp2ppp/3pp3/8/8/3BP3/PPPP1PPP/RNBQK1NR w KQkq - 0 3")?;
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::from([
+                (Position::e2(), MoveKind::Move),
+                (Position::f1(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // White no moves
+        {
+            let board = Board::default();
+            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black short & long castles
+        {
+            let board =
+                fen::parse("r3k2r/ppp1pp1p/2nqbnpb/3p4/3P4/2PQPPP1/PP5P/RNB1KBNR b KQkq - 0 8")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d8(), MoveKind::Move),
+                (Position::d7(), MoveKind::Move),
+                (Position::f8(), MoveKind::Move),
+                (Position::c8(), MoveKind::LongCastle),
+                (Position::g8(), MoveKind::ShortCastle),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no long castle because rook move
+        {
+            let board =
+                fen::parse("1r2k2r/ppp1pp1p/2nqbnpb/3p4/3P1P2/2PQP1P1/PP5P/RNB1KBNR b KQk - 0 9")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d8(), MoveKind::Move),
+                (Position::d7(), MoveKind::Move),
+                (Position::f8(), MoveKind::Move),
+                (Position::g8(), MoveKind::ShortCastle),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no short castle because rook move
+        {
+            let board =
+                fen::parse("r3k1r1/ppp1pp1p/2nqbnpb/3p4/3P2P1/2PQPP2/PP5P/RNB1KBNR b KQq - 0 9")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d8(), MoveKind::Move),
+                (Position::d7(), MoveKind::Move),
+                (Position::f8(), MoveKind::Move),
+                (Position::c8(), MoveKind::LongCastle),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no castle because king move
+        {
+            let board =
+                fen::parse("r2k3r/ppp1pp1p/2nqbnpb/3p4/3P2P1/2PQPP2/PP5P/RNB1KBNR b KQ - 0 9")?;
+            let moves = get_king_moves(&board, &Position::d8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d7(), MoveKind::Move),
+                (Position::c8(), MoveKind::Move),
+                (Position::e8(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no long castle because piece on b8
+        {
+            let board =
+                fen::parse("rn2kbnr/ppp1pppp/3qb3/3p4/3P4/2P5/PP1QPPPP/RNB1KBNR b KQkq - 0 4")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d8(), MoveKind::Move),
+                (Position::d7(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no long castle because piece on c8
+        {
+            let board =
+                fen::parse("r1b1kbnr/ppp1pppp/2nq4/3p4/3P4/2P1P3/PP3PPP/RNBQKBNR b KQkq - 0 4")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::d8(), MoveKind::Move),
+                (Position::d7(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no long castle because piece on d8
+        {
+            let board =
+                fen::parse("r2qkbnr/ppp1pppp/2n5/3p1b2/3PP3/8/PPP2PPP/RNBQKBNR b KQkq - 0 4")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([(Position::d7(), MoveKind::Move)]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no short castle because piece on f8
+        {
+            let board =
+                fen::parse("rnbqkb1r/pppppppp/7n/8/8/2N2P2/PPPPP1PP/R1BQKBNR b KQkq - 0 2")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no short castle because piece on g8
+        {
+            let board =
+                fen::parse("rnbqk1nr/pppp1ppp/3bp3/8/8/3PPP2/PPP3PP/RNBQKBNR b KQkq - 0 3")?;
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::from([
+                (Position::e7(), MoveKind::Move),
+                (Position::f8(), MoveKind::Move),
+            ]);
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        // Black no moves
+        {
+            let board = Board::default();
+            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
+            let expected_moves = HashMap::new();
+
+            assert_eq!(moves, expected_moves);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_all_moves_test() -> Result<(), ParseError> {
+        let board =
+            fen::parse("r3k1n1/3b1pbr/ppn1p1p1/7p/3Q1P2/PPP3PN/R3P2P/1NB1KB1R w Kq - 1 15")?;
+
+        let all_white_moves = get_all_moves(&board, &Side::White);
+
+        let expected_white_moves = HashMap::from([
+            (
+                Position::a3(),
+                HashMap::from([(Position::a4(), MoveKind::Move)]),
+            ),
+            (
+                Position::b3(),
+                HashMap::from([(Position::b4(), MoveKind::Move)]),
+            ),
+            (
+                Position::c3(),
+                HashMap::from([(Position::c4(), MoveKind::Move)]),
+            ),
+            (
+                Position::e2(),
+                HashMap::from([
+                    (Position::e3(), MoveKind::Move),
+                    (Position::e4(), MoveKind::DoubleMove(Position::e3())),
+                ]),
+            ),
+            (
+                Position::f4(),
+                HashMap::from([(Position::f5(), MoveKind::Move)]),
+            ),
+            (
+                Position::g3(),
+                HashMap::from([(Position::g4(), MoveKind::Move)]),
+            ),
+            (Position::h2(), HashMap::from([])),
+            (
+                Position::a2(),
+                HashMap::from([
+                    (Position::a1(), MoveKind::Move),
+                    (Position::b2(), MoveKind::Move),
+                    (Position::c2(), MoveKind::Move),
+                    (Position::d2(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::b1(),
+                HashMap::from([(Position::d2(), MoveKind::Move)]),
+            ),
+            (
+                Position::c1(),
+                HashMap::from([
+                    (Position::b2(), MoveKind::Move),
+                    (Position::d2(), MoveKind::Move),
+                    (Position::e3(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::f1(),
+                HashMap::from([(Position::g2(), MoveKind::Move)]),
+            ),
+            (
+                Position::h1(),
+                HashMap::from([(Position::g1(), MoveKind::Move)]),
+            ),
+            (
+                Position::h3(),
+                HashMap::from([
+                    (Position::g5(), MoveKind::Move),
+                    (Position::g1(), MoveKind::Move),
+                    (Position::f2(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::e1(),
+                HashMap::from([
+                    (Position::d1(), MoveKind::Move),
+                    (Position::d2(), MoveKind::Move),
+                    (Position::f2(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::d4(),
+                HashMap::from([
+                    (Position::a4(), MoveKind::Move),
+                    (Position::b4(), MoveKind::Move),
+                    (Position::c4(), MoveKind::Move),
+                    (Position::e4(), MoveKind::Move),
+                    (Position::d1(), MoveKind::Move),
+                    (Position::d2(), MoveKind::Move),
+                    (Position::d3(), MoveKind::Move),
+                    (Position::d5(), MoveKind::Move),
+                    (Position::d6(), MoveKind::Move),
+                    (Position::d7(), MoveKind::Capture),
+                    (Position::b6(), MoveKind::Capture),
+                    (Position::c5(), MoveKind::Move),
+                    (Position::e3(), MoveKind::Move),
+                    (Position::f2(), MoveKind::Move),
+                    (Position::g1(), MoveKind::Move),
+                    (Position::e5(), MoveKind::Move),
+                    (Position::f6(), MoveKind::Move),
+                    (Position::g7(), MoveKind::Capture),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(all_white_moves, expected_white_moves);
+
+        let all_black_moves = get_all_moves(&board, &Side::Black);
+
+        let expected_black_moves = HashMap::from([
+            (
+                Position::a6(),
+                HashMap::from([(Position::a5(), MoveKind::Move)]),
+            ),
+            (
+                Position::b6(),
+                HashMap::from([(Position::b5(), MoveKind::Move)]),
+            ),
+            (
+                Position::e6(),
+                HashMap::from([(Position::e5(), MoveKind::Move)]),
+            ),
+            (
+                Position::f7(),
+                HashMap::from([
+                    (Position::f6(), MoveKind::Move),
+                    (Position::f5(), MoveKind::DoubleMove(Position::f6())),
+                ]),
+            ),
+            (
+                Position::g6(),
+                HashMap::from([(Position::g5(), MoveKind::Move)]),
+            ),
+            (
+                Position::h5(),
+                HashMap::from([(Position::h4(), MoveKind::Move)]),
+            ),
+            (
+                Position::a8(),
+                HashMap::from([
+                    (Position::a7(), MoveKind::Move),
+                    (Position::b8(), MoveKind::Move),
+                    (Position::c8(), MoveKind::Move),
+                    (Position::d8(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::c6(),
+                HashMap::from([
+                    (Position::a7(), MoveKind::Move),
+                    (Position::b8(), MoveKind::Move),
+                    (Position::d8(), MoveKind::Move),
+                    (Position::e7(), MoveKind::Move),
+                    (Position::e5(), MoveKind::Move),
+                    (Position::d4(), MoveKind::Capture),
+                    (Position::b4(), MoveKind::Move),
+                    (Position::a5(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::d7(),
+                HashMap::from([(Position::c8(), MoveKind::Move)]),
+            ),
+            (
+                Position::g8(),
+                HashMap::from([
+                    (Position::e7(), MoveKind::Move),
+                    (Position::f6(), MoveKind::Move),
+                    (Position::h6(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::g7(),
+                HashMap::from([
+                    (Position::f8(), MoveKind::Move),
+                    (Position::h8(), MoveKind::Move),
+                    (Position::h6(), MoveKind::Move),
+                    (Position::f6(), MoveKind::Move),
+                    (Position::e5(), MoveKind::Move),
+                    (Position::d4(), MoveKind::Capture),
+                ]),
+            ),
+            (
+                Position::h7(),
+                HashMap::from([
+                    (Position::h8(), MoveKind::Move),
+                    (Position::h6(), MoveKind::Move),
+                ]),
+            ),
+            (
+                Position::e8(),
+                HashMap::from([
+                    (Position::f8(), MoveKind::Move),
+                    (Position::e7(), MoveKind::Move),
+                    (Position::d8(), MoveKind::Move),
+                    (Position::c8(), MoveKind::LongCastle),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(all_black_moves, expected_black_moves);
 
         Ok(())
     }
 
     #[test]
-    fn get_king_moves_test() -> Result<(), ParseError> {
-        // All directions
+    fn is_in_check_test() -> Result<(), ParseError> {
+        // White in check
         {
-            let board = fen::parse("rnbqkbnr/2pppppp/4P3/1p6/3K4/p7/PPPP1PPP/RNBQ1BNR w kq - 0 7")?;
-            let moves = get_king_moves(&board, &Position::d4(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d5(), MoveKind::Move),
-                (Position::e5(), MoveKind::Move),
-                (Position::e4(), MoveKind::Move),
-                (Position::e3(), MoveKind::Move),
-                (Position::d3(), MoveKind::Move),
-                (Position::c3(), MoveKind::Move),
-                (Position::c4(), MoveKind::Move),
-                (Position::c5(), MoveKind::Move),
-            ]);
+            let board =
+                fen::parse("rnb1kbnr/pp1ppppp/8/q1p5/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert!(is_in_check(&board, &Side::White));
         }
 
-        // Captures & own pieces, no checks or castles
+        // White not in check
         {
-            let board = fen::parse("rnbqkbnr/1p1pppp1/p6p/8/2pKP3/8/PPPP1PPP/RNBQ1BNR w kq - 0 5")?;
-            let moves = get_king_moves(&board, &Position::d4(), &Side::White);
-            // Still c4 as a capture, but not e4 because of our own piece
-            let expected_moves = HashMap::from([
-                (Position::d5(), MoveKind::Move),
-                (Position::e5(), MoveKind::Move),
-                (Position::e3(), MoveKind::Move),
-                (Position::d3(), MoveKind::Move),
-                (Position::c3(), MoveKind::Move),
-                (Position::c4(), MoveKind::Capture),
-                (Position::c5(), MoveKind::Move),
-            ]);
+            let board =
+                fen::parse("rnbqkbnr/pp1ppppp/8/2p5/8/3P1P2/PPP1P1PP/RNBQKBNR b KQkq - 0 2")?;
 
-            assert_eq!(moves, expected_moves);
+            assert!(!is_in_check(&board, &Side::Black));
         }
 
-        // White short & long castles
+        // Black in check
         {
             let board =
-                fen::parse("r3k2r/ppp1pp1p/2nqbnpb/3p4/3P4/2NQBNPB/PPP1PP1P/R3K2R w KQkq - 4 8")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::f1(), MoveKind::Move),
-                (Position::c1(), MoveKind::LongCastle),
-                (Position::g1(), MoveKind::ShortCastle),
-            ]);
+                fen::parse("rnbqkbnr/ppppp2p/8/5ppQ/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert!(is_in_check(&board, &Side::Black));
         }
 
-        // White no long castle because rook move
+        // Black not in check
         {
             let board =
-                fen::parse("r3k2r/ppp1ppbp/2nqbnp1/3p4/3P4/2NQBNPB/PPP1PP1P/1R2K2R w Kkq - 6 9")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::f1(), MoveKind::Move),
-                (Position::g1(), MoveKind::ShortCastle),
-            ]);
+                fen::parse("rnbqkbnr/ppppp2p/8/5pp1/5P2/4P3/PPPP2PP/RNBQKBNR w KQkq g6 0 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert!(!is_in_check(&board, &Side::Black));
         }
 
-        // White no short castle because rook move
-        {
-            let board =
-                fen::parse("r3k2r/ppp1ppbp/2nqbnp1/3p4/3P4/2NQBNPB/PPP1PP1P/R3K1R1 w Qkq - 6 9")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-                (Position::f1(), MoveKind::Move),
-                (Position::c1(), MoveKind::LongCastle),
-            ]);
+        Ok(())
+    }
 
-            assert_eq!(moves, expected_moves);
+    #[test]
+    fn moves_ignoring_turn_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/8/8/8/3pP3/8/8/4K2R b K e3 0 1")?;
+
+        // Black is to move, so its moves are the usual ones, en passant included.
+        assert_eq!(
+            legal_moves_if_to_move(&board, &Side::Black),
+            get_all_legal_moves(&board, &Side::Black)
+        );
+        let black_pawn = pseudo_moves_ignoring_turn(&board, &Position::d4()).unwrap();
+        assert_eq!(
+            black_pawn.get(&Position::e3()),
+            Some(&MoveKind::EnPassant(Position::e4()))
+        );
+
+        // White's threats are what becomes legal once black passes.
+        let mut null_moved = board.clone();
+        null_moved.make_null_move();
+        assert_eq!(null_moved.side_to_move(), Side::White);
+        assert_eq!(null_moved.en_passant_target(), None);
+        let threats = legal_moves_if_to_move(&board, &Side::White);
+        assert_eq!(threats, get_all_legal_moves(&null_moved, &Side::White));
+        assert_eq!(
+            threats[&Position::e1()].get(&Position::g1()),
+            Some(&MoveKind::ShortCastle)
+        );
+
+        let white_pawn = pseudo_moves_ignoring_turn(&board, &Position::e4()).unwrap();
+        assert_eq!(white_pawn.keys().collect::<Vec<_>>(), vec![&Position::e5()]);
+        assert_eq!(
+            pseudo_moves_ignoring_turn(&board, &Position::e5()),
+            Err(MoveError::EmptySquare {
+                position: Position::e5()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_relative_test() -> Result<(), ParseError> {
+        // Black's e7-e5 is e2-e4 from Black's side of the board.
+        let relative = MoveRequest::new(Position::e2(), Position::e4());
+        let request = MoveRequest::from_relative(&relative, &Side::Black);
+        assert_eq!(request, MoveRequest::new(Position::e7(), Position::e5()));
+        assert_eq!(
+            MoveRequest::from_relative(&relative, &Side::White),
+            relative
+        );
+
+        let promotion =
+            MoveRequest::promotion(Position::b7(), Position::a8(), PromotionType::Knight);
+        let request = MoveRequest::from_relative(&promotion, &Side::Black);
+        assert_eq!(
+            request,
+            MoveRequest::promotion(Position::b2(), Position::a1(), PromotionType::Knight)
+        );
+        assert_eq!(
+            MoveRequest::from_relative(&request, &Side::Black),
+            promotion
+        );
+
+        let mut board = fen::parse("4k3/8/8/8/8/8/6p1/4K3 b - - 0 1")?;
+        let relative = MoveRequest::promotion(Position::g7(), Position::g8(), PromotionType::Queen);
+        assert!(move_piece(
+            &mut board,
+            MoveRequest::from_relative(&relative, &Side::Black)
+        )
+        .is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn preview_move_round_trip_test() -> Result<(), ParseError> {
+        let board = fen::parse("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")?;
+
+        let cases = [
+            (MoveRequest::new(Position::a1(), Position::a7()), "Ra7"),
+            (MoveRequest::new(Position::e1(), Position::g1()), "O-O"),
+            (MoveRequest::new(Position::e5(), Position::d6()), "exd6"),
+            (
+                MoveRequest::promotion(Position::b7(), Position::a8(), PromotionType::Queen),
+                "bxa8=Q+",
+            ),
+        ];
+        for (request, notation) in cases {
+            let move_info = preview_move(&board, &request).unwrap();
+            assert_eq!(move_info.to_notation(), notation);
+            assert_eq!(move_info.to_request(), request);
         }
 
-        // White no castle because king move
-        {
-            let board =
-                fen::parse("r3k2r/ppp1ppbp/2nqbnp1/3p4/3P4/2NQBNPB/PPP1PP1P/R2K3R w kq - 6 9")?;
-            let moves = get_king_moves(&board, &Position::d1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d2(), MoveKind::Move),
-                (Position::c1(), MoveKind::Move),
-                (Position::e1(), MoveKind::Move),
-            ]);
+        let move_info =
+            preview_move(&board, &MoveRequest::new(Position::e5(), Position::d6())).unwrap();
+        assert!(move_info.is_capture);
+        assert_eq!(move_info.move_kind, MoveKind::EnPassant(Position::d5()));
+        assert_eq!(move_info.move_state, Some(MoveState::CanMove));
 
-            assert_eq!(moves, expected_moves);
+        // Both rooks can reach d1 once the king is out of the way.
+        let board = fen::parse("4k3/8/8/8/8/8/4K3/R6R w - - 0 1")?;
+        let move_info =
+            preview_move(&board, &MoveRequest::new(Position::a1(), Position::d1())).unwrap();
+        assert_eq!(move_info.to_notation(), "Rad1");
+
+        assert_eq!(
+            preview_move(&board, &MoveRequest::new(Position::a1(), Position::b2())).unwrap_err(),
+            MoveError::InvalidMove
+        );
+        assert_boards_eq!(board, fen::parse("4k3/8/8/8/8/8/4K3/R6R w - - 0 1")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lan_round_trip_test() {
+        for fixture in crate::fixtures::FIXTURES {
+            let mut game = crate::game::Game::new(Board::default());
+            let mut lans = Vec::new();
+            for san in fixture.moves {
+                let board = game.board().clone();
+                let request = MoveRequest::from_san(&board, san).unwrap();
+                let move_info = game.attempt_move(request.clone()).unwrap();
+
+                let lan = move_info.to_lan();
+                assert_eq!(
+                    MoveRequest::from_lan(&board, &lan).unwrap(),
+                    request,
+                    "{lan}"
+                );
+                lans.push(lan);
+            }
+
+            if fixture.name == "scholars-mate" {
+                assert_eq!(
+                    lans.join(" "),
+                    "e2-e4 e7-e5 Qd1-h5 Nb8-c6 Bf1-c4 Ng8-f6 Qh5xf7#"
+                );
+            }
         }
+    }
 
-        // White no long castle because piece on b1
+    #[test]
+    fn lan_needs_no_disambiguation_test() -> Result<(), ParseError> {
+        // Both knights and both rooks can reach the same squares.
+        let board = fen::parse("4k3/8/8/8/8/8/8/RN2K1NR w - - 0 1")?;
+
+        let mut lans = Vec::new();
+        for (start, moves) in get_all_legal_moves(&board, &Side::White) {
+            for end in moves.keys() {
+                let mut simulation = board.clone_for_simulation();
+                let mut move_info =
+                    move_piece(&mut simulation, MoveRequest::new(start, *end)).unwrap();
+                let lan = move_info.to_lan();
+
+                move_info.file_disambiguation = true;
+                move_info.rank_disambiguation = true;
+                assert_eq!(move_info.to_lan(), lan);
+
+                assert_eq!(
+                    MoveRequest::from_lan(&board, &lan).unwrap(),
+                    MoveRequest::new(start, *end)
+                );
+                lans.push(lan);
+            }
+        }
+
+        let count = lans.len();
+        lans.sort();
+        lans.dedup();
+        assert_eq!(lans.len(), count);
+        assert!(lans.contains(&String::from("Nb1-d2")));
+        assert!(lans.contains(&String::from("Ng1-e2")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn kingless_board_test() -> Result<(), ParseError> {
+        let board = Board::empty();
+        assert!(!is_in_check(&board, &Side::White));
+        assert!(!is_in_check(&board, &Side::Black));
+        assert_eq!(get_move_state(&board), MoveState::Invalid);
+
+        // Only black has a king. White still has castling rights, but no king to castle with.
+        let board = Board::new(
+            vec![
+                (Position::e8(), Piece::new(PieceType::King, Side::Black)),
+                (Position::a1(), Piece::new(PieceType::Rook, Side::White)),
+                (Position::e1(), Piece::new(PieceType::Rook, Side::White)),
+                (Position::h1(), Piece::new(PieceType::Rook, Side::White)),
+            ],
+            Side::White,
+            CastleRights::new(true, true, false, false),
+            None,
+            0,
+            1,
+        );
+        assert!(is_in_check(&board, &Side::Black));
+        assert!(!is_in_check(&board, &Side::White));
+        assert_eq!(get_move_state(&board), MoveState::Invalid);
+        assert!(!MoveState::Invalid.is_game_over());
+
+        let legal_moves = get_all_legal_moves(&board, &Side::White);
+        assert!(legal_moves
+            .values()
+            .flatten()
+            .all(|(_, move_kind)| !matches!(
+                move_kind,
+                MoveKind::ShortCastle | MoveKind::LongCastle
+            )));
+        assert_eq!(legal_moves.values().map(HashMap::len).sum::<usize>(), 31);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_move_state_test() -> Result<(), ParseError> {
+        // White in checkmate
         {
             let board =
-                fen::parse("rn2kbnr/ppp1pppp/3qb3/3p4/3P4/3QB3/PPP1PPPP/RN2KBNR w KQkq - 4 4")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-            ]);
+                fen::parse("rnb1kbnr/pppp1ppp/4p3/8/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::Checkmate);
         }
 
-        // White no long castle because piece on c1
+        // White in check
         {
             let board =
-                fen::parse("rnb1kbnr/pp2pppp/2pq4/3p4/3P4/2NQ4/PPP1PPPP/R1B1KBNR w KQkq - 0 4")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::d1(), MoveKind::Move),
-                (Position::d2(), MoveKind::Move),
-            ]);
+                fen::parse("rnb1kbnr/pppp1ppp/4p3/8/7q/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::Check);
         }
 
-        // White no long castle because piece on d1
+        // White in stalemate
         {
-            let board =
-                fen::parse("rnbqkbnr/pp3ppp/2p1p3/3p4/3P4/N3B3/PPP1PPPP/R2QKBNR w KQkq - 0 4")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([(Position::d2(), MoveKind::Move)]);
+            let board = fen::parse("rnb1kbnr/ppp1ppp1/8/8/8/8/4q3/6K1 w kq - 0 1")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::Stalemate);
         }
 
-        // White no short castle because piece on f1
+        // White can claim the 50 move rule but the game continues
         {
-            let board = fen::parse("rnbqkbnr/pppppp1p/6p1/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::new();
+            let board =
+                fen::parse("rnb1kbnr/ppppqppp/4p3/8/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 100 50")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::CanMove);
+            assert!(can_claim_fifty_move_rule(&board));
         }
 
-        // White no short castle because piece on g1
+        // White in 75 move rule draw
         {
             let board =
-                fen::parse("rnbqkbnr/ppp2ppp/3pp3/8/8/3BP3/PPPP1PPP/RNBQK1NR w KQkq - 0 3")?;
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::from([
-                (Position::e2(), MoveKind::Move),
-                (Position::f1(), MoveKind::Move),
-            ]);
+                fen::parse("rnb1kbnr/ppppqppp/4p3/8/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 150 75")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::SeventyFiveMoveRule);
         }
 
-        // White no moves
+        // White not in check
         {
-            let board = Board::default();
-            let moves = get_king_moves(&board, &Position::e1(), &Side::White);
-            let expected_moves = HashMap::new();
+            let board =
+                fen::parse("rnb1kbnr/ppppqppp/4p3/8/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::CanMove);
         }
 
-        // Black short & long castles
+        // Black in checkmate
         {
             let board =
-                fen::parse("r3k2r/ppp1pp1p/2nqbnpb/3p4/3P4/2PQPPP1/PP5P/RNB1KBNR b KQkq - 0 8")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d8(), MoveKind::Move),
-                (Position::d7(), MoveKind::Move),
-                (Position::f8(), MoveKind::Move),
-                (Position::c8(), MoveKind::LongCastle),
-                (Position::g8(), MoveKind::ShortCastle),
-            ]);
+                fen::parse("rnbqkbnr/ppppp2p/5p2/6pQ/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::Checkmate);
         }
 
-        // Black no long castle because rook move
+        // Black in check
         {
             let board =
-                fen::parse("1r2k2r/ppp1pp1p/2nqbnpb/3p4/3P1P2/2PQP1P1/PP5P/RNB1KBNR b KQk - 0 9")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d8(), MoveKind::Move),
-                (Position::d7(), MoveKind::Move),
-                (Position::f8(), MoveKind::Move),
-                (Position::g8(), MoveKind::ShortCastle),
-            ]);
+                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/7Q/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
+
+            assert_eq!(get_move_state(&board), MoveState::Check);
+        }
 
-            assert_eq!(moves, expected_moves);
+        // Black in stalemate
+        {
+            let board = fen::parse("1R6/8/8/8/p2R4/k7/8/1K6 b - - 0 99")?;
+
+            assert_eq!(get_move_state(&board), MoveState::Stalemate);
         }
 
-        // Black no short castle because rook move
+        // Black can claim the 50 move rule but the game continues
         {
             let board =
-                fen::parse("r3k1r1/ppp1pp1p/2nqbnpb/3p4/3P2P1/2PQPP2/PP5P/RNB1KBNR b KQq - 0 9")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d8(), MoveKind::Move),
-                (Position::d7(), MoveKind::Move),
-                (Position::f8(), MoveKind::Move),
-                (Position::c8(), MoveKind::LongCastle),
-            ]);
+                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/8/5P2/4PQ2/PPPP2PP/RNB1KBNR b KQkq - 100 50")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::CanMove);
+            assert!(can_claim_fifty_move_rule(&board));
         }
 
-        // Black no castle because king move
+        // Black in 75 move rule draw
         {
             let board =
-                fen::parse("r2k3r/ppp1pp1p/2nqbnpb/3p4/3P2P1/2PQPP2/PP5P/RNB1KBNR b KQ - 0 9")?;
-            let moves = get_king_moves(&board, &Position::d8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d7(), MoveKind::Move),
-                (Position::c8(), MoveKind::Move),
-                (Position::e8(), MoveKind::Move),
-            ]);
+                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/8/5P2/4PQ2/PPPP2PP/RNB1KBNR b KQkq - 150 75")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::SeventyFiveMoveRule);
         }
 
-        // Black no long castle because piece on b8
+        // Black not in check
         {
             let board =
-                fen::parse("rn2kbnr/ppp1pppp/3qb3/3p4/3P4/2P5/PP1QPPPP/RNB1KBNR b KQkq - 0 4")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d8(), MoveKind::Move),
-                (Position::d7(), MoveKind::Move),
-            ]);
+                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/8/5P2/4PQ2/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
 
-            assert_eq!(moves, expected_moves);
+            assert_eq!(get_move_state(&board), MoveState::CanMove);
         }
 
-        // Black no long castle because piece on c8
+        Ok(())
+    }
+
+    #[test]
+    fn move_counter_rules_test() -> Result<(), ParseError> {
+        // 99 -> 100 half moves makes the 50 move rule claimable
         {
-            let board =
-                fen::parse("r1b1kbnr/ppp1pppp/2nq4/3p4/3P4/2P1P3/PP3PPP/RNBQKBNR b KQkq - 0 4")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::d8(), MoveKind::Move),
-                (Position::d7(), MoveKind::Move),
-            ]);
+            let mut board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 99 80")?;
+            assert!(!can_claim_fifty_move_rule(&board));
 
-            assert_eq!(moves, expected_moves);
+            move_piece(&mut board, MoveRequest::new(Position::a1(), Position::a2())).unwrap();
+
+            assert_eq!(board.get_half_moves(), 100);
+            assert!(can_claim_fifty_move_rule(&board));
+            assert_eq!(get_move_state(&board), MoveState::CanMove);
         }
 
-        // Black no long castle because piece on d8
+        // 149 -> 150 half moves without mate ends the game
         {
-            let board =
-                fen::parse("r2qkbnr/ppp1pppp/2n5/3p1b2/3PP3/8/PPP2PPP/RNBQKBNR b KQkq - 0 4")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([(Position::d7(), MoveKind::Move)]);
+            let mut board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 149 100")?;
+            assert_eq!(get_move_state(&board), MoveState::CanMove);
 
-            assert_eq!(moves, expected_moves);
+            move_piece(&mut board, MoveRequest::new(Position::a1(), Position::a2())).unwrap();
+
+            assert_eq!(board.get_half_moves(), 150);
+            assert_eq!(get_move_state(&board), MoveState::SeventyFiveMoveRule);
         }
 
-        // Black no short castle because piece on f8
+        // 149 -> 150 half moves with mate is still a checkmate
         {
-            let board =
-                fen::parse("rnbqkb1r/pppppppp/7n/8/8/2N2P2/PPPPP1PP/R1BQKBNR b KQkq - 0 2")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::new();
+            let mut board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 149 100")?;
 
-            assert_eq!(moves, expected_moves);
+            move_piece(&mut board, MoveRequest::new(Position::a1(), Position::a8())).unwrap();
+
+            assert_eq!(board.get_half_moves(), 150);
+            assert_eq!(get_move_state(&board), MoveState::Checkmate);
         }
 
-        // Black no short castle because piece on g8
+        Ok(())
+    }
+
+    #[test]
+    fn make_move_undo_test() -> Result<(), ParseError> {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 3 1",
+            "rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 3",
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+            "rn1qkbnr/ppP1ppp1/3p3p/5b2/8/8/P1PPPPPP/RNBQKBNR w KQkq - 0 5",
+            "4k3/8/8/8/8/8/p5K1/1N6 b - - 7 40",
+        ];
+
+        let promotion_types = [
+            PromotionType::Queen,
+            PromotionType::Rook,
+            PromotionType::Bishop,
+            PromotionType::Knight,
+        ];
+
+        for fen in fens {
+            let board = fen::parse(fen)?;
+
+            for (start, moves) in get_all_legal_moves(&board, &board.side_to_move()) {
+                for (end, move_kind) in moves {
+                    let requests = match move_kind {
+                        MoveKind::Promotion(_) => promotion_types
+                            .iter()
+                            .map(|promotion_type| {
+                                MoveRequest::promotion(start, end, *promotion_type)
+                            })
+                            .collect(),
+                        _ => vec![MoveRequest::new(start, end)],
+                    };
+
+                    for request in requests {
+                        let mut new_board = board.clone();
+                        let (_, move_undo) = make_move(&mut new_board, request).unwrap();
+                        assert_ne!(new_board, board);
+
+                        new_board.undo(move_undo);
+                        assert_boards_eq!(new_board, board);
+                        assert_eq!(fen::generate(&new_board), fen);
+                        assert_eq!(
+                            new_board.get_repetition_state(),
+                            board.get_repetition_state()
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn make_move_undo_details_test() -> Result<(), ParseError> {
+        // Promotion restores the pawn rather than the promoted piece
         {
             let board =
-                fen::parse("rnbqk1nr/pppp1ppp/3bp3/8/8/3PPP2/PPP3PP/RNBQKBNR b KQkq - 0 3")?;
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::from([
-                (Position::e7(), MoveKind::Move),
-                (Position::f8(), MoveKind::Move),
-            ]);
-
-            assert_eq!(moves, expected_moves);
+                fen::parse("rn1qkbnr/ppP1ppp1/3p3p/5b2/8/8/P1PPPPPP/RNBQKBNR w KQkq - 0 5")?;
+            let mut new_board = board.clone();
+            let request =
+                MoveRequest::promotion(Position::c7(), Position::b8(), PromotionType::Knight);
+            let (_, move_undo) = make_move(&mut new_board, request).unwrap();
+
+            assert_eq!(
+                move_undo.moved_piece,
+                Piece::new(PieceType::Pawn, Side::White)
+            );
+            assert_eq!(
+                move_undo.captured,
+                Some((Position::b8(), Piece::new(PieceType::Knight, Side::Black)))
+            );
+
+            new_board.undo(move_undo);
+            assert_boards_eq!(new_board, board);
         }
 
-        // Black no moves
+        // En passant restores the captured pawn on its true square
         {
-            let board = Board::default();
-            let moves = get_king_moves(&board, &Position::e8(), &Side::Black);
-            let expected_moves = HashMap::new();
+            let board = fen::parse("rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 3")?;
+            let mut new_board = board.clone();
+            let request = MoveRequest::new(Position::d5(), Position::c6());
+            let (_, move_undo) = make_move(&mut new_board, request).unwrap();
+
+            assert_eq!(new_board.piece_at(&Position::c5()), None);
+            assert_eq!(
+                move_undo.captured,
+                Some((Position::c5(), Piece::new(PieceType::Pawn, Side::Black)))
+            );
+
+            new_board.undo(move_undo);
+            assert_boards_eq!(new_board, board);
+            assert_eq!(new_board.en_passant_target(), Some(Position::c6()));
+        }
 
-            assert_eq!(moves, expected_moves);
+        // Castling restores both the king and the rook, along with the rights
+        {
+            let board =
+                fen::parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")?;
+            let mut new_board = board.clone();
+            let request = MoveRequest::new(Position::e1(), Position::c1());
+            let (_, move_undo) = make_move(&mut new_board, request).unwrap();
+
+            assert_eq!(move_undo.rook_move, Some((Position::a1(), Position::d1())));
+            assert!(!new_board.get_castle_rights().white.long);
+
+            new_board.undo(move_undo);
+            assert_boards_eq!(new_board, board);
+            assert_eq!(
+                *new_board.get_castle_rights(),
+                CastleRights::new(true, true, true, true)
+            );
         }
 
         Ok(())
     }
 
     #[test]
-    fn get_all_moves_test() -> Result<(), ParseError> {
-        let board =
-            fen::parse("r3k1n1/3b1pbr/ppn1p1p1/7p/3Q1P2/PPP3PN/R3P2P/1NB1KB1R w Kq - 1 15")?;
-
-        let all_white_moves = get_all_moves(&board, &Side::White);
+    fn moves_for_piece_type_test() -> Result<(), ParseError> {
+        let board = Board::default();
+        let e4 = Position::e4();
+
+        let knight_moves = moves_for_piece_type(&board, &PieceType::Knight, &Side::White, &e4);
+        let mut expected = HashMap::new();
+        for position in [
+            Position::c3(),
+            Position::g3(),
+            Position::c5(),
+            Position::g5(),
+            Position::d6(),
+            Position::f6(),
+        ] {
+            expected.insert(position, MoveKind::Move);
+        }
+        assert_eq!(knight_moves, expected);
+
+        let rook_moves = moves_for_piece_type(&board, &PieceType::Rook, &Side::White, &e4);
+        assert_eq!(rook_moves.len(), 11);
+        assert_eq!(rook_moves.get(&Position::e7()), Some(&MoveKind::Capture));
+        assert!(!rook_moves.contains_key(&Position::e2()));
+
+        let bishop_moves = moves_for_piece_type(&board, &PieceType::Bishop, &Side::White, &e4);
+        assert_eq!(bishop_moves.len(), 8);
+        assert_eq!(bishop_moves.get(&Position::b7()), Some(&MoveKind::Capture));
+        assert_eq!(bishop_moves.get(&Position::h7()), Some(&MoveKind::Capture));
+
+        let queen_moves = moves_for_piece_type(&board, &PieceType::Queen, &Side::White, &e4);
+        assert_eq!(queen_moves.len(), 19);
+
+        // A king away from its home square never castles, even with castle rights.
+        let king_moves = moves_for_piece_type(&board, &PieceType::King, &Side::White, &e4);
+        assert_eq!(king_moves.len(), 8);
+        assert!(king_moves.values().all(|kind| *kind == MoveKind::Move));
+
+        let white_pawn_moves = moves_for_piece_type(&board, &PieceType::Pawn, &Side::White, &e4);
+        assert_eq!(
+            white_pawn_moves,
+            HashMap::from([(Position::e5(), MoveKind::Move)])
+        );
+
+        let black_pawn_moves = moves_for_piece_type(&board, &PieceType::Pawn, &Side::Black, &e4);
+        assert_eq!(
+            black_pawn_moves,
+            HashMap::from([(Position::e3(), MoveKind::Move)])
+        );
+
+        // Occupancy of the starting square itself is ignored.
+        let promotion_moves =
+            moves_for_piece_type(&board, &PieceType::Pawn, &Side::White, &Position::b7());
+        assert_eq!(
+            promotion_moves,
+            HashMap::from([
+                (Position::a8(), MoveKind::Promotion(true)),
+                (Position::c8(), MoveKind::Promotion(true)),
+            ])
+        );
+
+        let double_moves =
+            moves_for_piece_type(&board, &PieceType::Pawn, &Side::Black, &Position::e7());
+        assert_eq!(
+            double_moves,
+            HashMap::from([
+                (Position::e6(), MoveKind::Move),
+                (Position::e5(), MoveKind::DoubleMove(Position::e6())),
+            ])
+        );
+
+        let board = fen::parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2")?;
+        let en_passant_moves =
+            moves_for_piece_type(&board, &PieceType::Pawn, &Side::White, &Position::c5());
+        assert_eq!(
+            en_passant_moves,
+            HashMap::from([
+                (Position::c6(), MoveKind::Move),
+                (Position::d6(), MoveKind::EnPassant(Position::d5())),
+            ])
+        );
 
-        let expected_white_moves = HashMap::from([
-            (
-                Position::a3(),
-                HashMap::from([(Position::a4(), MoveKind::Move)]),
-            ),
-            (
-                Position::b3(),
-                HashMap::from([(Position::b4(), MoveKind::Move)]),
-            ),
-            (
-                Position::c3(),
-                HashMap::from([(Position::c4(), MoveKind::Move)]),
-            ),
-            (
-                Position::e2(),
-                HashMap::from([
-                    (Position::e3(), MoveKind::Move),
-                    (Position::e4(), MoveKind::DoubleMove(Position::e3())),
-                ]),
-            ),
-            (
-                Position::f4(),
-                HashMap::from([(Position::f5(), MoveKind::Move)]),
-            ),
-            (
-                Position::g3(),
-                HashMap::from([(Position::g4(), MoveKind::Move)]),
-            ),
-            (Position::h2(), HashMap::from([])),
-            (
-                Position::a2(),
-                HashMap::from([
-                    (Position::a1(), MoveKind::Move),
-                    (Position::b2(), MoveKind::Move),
-                    (Position::c2(), MoveKind::Move),
-                    (Position::d2(), MoveKind::Move),
-                ]),
-            ),
-            (
-                Position::b1(),
-                HashMap::from([(Position::d2(), MoveKind::Move)]),
-            ),
-            (
-                Position::c1(),
-                HashMap::from([
-                    (Position::b2(), MoveKind::Move),
-                    (Position::d2(), MoveKind::Move),
-                    (Position::e3(), MoveKind::Move),
-                ]),
-            ),
-            (
-                Position::f1(),
-                HashMap::from([(Position::g2(), MoveKind::Move)]),
-            ),
-            (
-                Position::h1(),
-                HashMap::from([(Position::g1(), MoveKind::Move)]),
-            ),
-            (
-                Position::h3(),
-                HashMap::from([
-                    (Position::g5(), MoveKind::Move),
-                    (Position::g1(), MoveKind::Move),
-                    (Position::f2(), MoveKind::Move),
-                ]),
-            ),
-            (
-                Position::e1(),
-                HashMap::from([
-                    (Position::d1(), MoveKind::Move),
-                    (Position::d2(), MoveKind::Move),
-                    (Position::f2(), MoveKind::Move),
-                ]),
-            ),
-            (
-                Position::d4(),
-                HashMap::from([
-                    (Position::a4(), MoveKind::Move),
-                    (Position::b4(), MoveKind::Move),
-                    (Position::c4(), MoveKind::Move),
-                    (Position::e4(), MoveKind::Move),
-                    (Position::d1(), MoveKind::Move),
-                    (Position::d2(), MoveKind::Move),
-                    (Position::d3(), MoveKind::Move),
-                    (Position::d5(), MoveKind::Move),
-                    (Position::d6(), MoveKind::Move),
-                    (Position::d7(), MoveKind::Capture),
-                    (Position::b6(), MoveKind::Capture),
-                    (Position::c5(), MoveKind::Move),
-                    (Position::e3(), MoveKind::Move),
-                    (Position::f2(), MoveKind::Move),
-                    (Position::g1(), MoveKind::Move),
-                    (Position::e5(), MoveKind::Move),
-                    (Position::f6(), MoveKind::Move),
-                    (Position::g7(), MoveKind::Capture),
-                ]),
-            ),
-        ]);
+        Ok(())
+    }
 
-        assert_eq!(all_white_moves, expected_white_moves);
+    #[test]
+    fn checkmate_details_test() -> Result<(), ParseError> {
+        // Back rank mate
+        {
+            let board = fen::parse("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1")?;
+            let details = checkmate_details(&board).unwrap();
+
+            assert_eq!(details.king_position, Position::g8());
+            assert_eq!(
+                details.checking_pieces,
+                vec![(Position::a8(), Piece::new(PieceType::Rook, Side::White))]
+            );
+            assert!(details
+                .blocked_escapes
+                .contains(&(Position::f8(), EscapeBlocker::Attacked)));
+            assert!(details
+                .blocked_escapes
+                .contains(&(Position::g7(), EscapeBlocker::Occupied)));
+            assert_eq!(details.blocked_escapes.len(), 5);
+            assert_eq!(
+                details.interposition_squares,
+                vec![
+                    Position::b8(),
+                    Position::c8(),
+                    Position::d8(),
+                    Position::e8(),
+                    Position::f8()
+                ]
+            );
+            assert_eq!(
+                details.to_string(),
+                "checkmated by the rook on a8; f8 and h8 were covered"
+            );
+        }
 
-        let all_black_moves = get_all_moves(&board, &Side::Black);
+        // Smothered mate
+        {
+            let board = fen::parse("6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1")?;
+            let details = checkmate_details(&board).unwrap();
+
+            assert_eq!(
+                details.checking_pieces,
+                vec![(Position::f7(), Piece::new(PieceType::Knight, Side::White))]
+            );
+            assert!(details
+                .blocked_escapes
+                .iter()
+                .all(|(_, blocker)| *blocker == EscapeBlocker::Occupied));
+            assert!(details.interposition_squares.is_empty());
+            assert_eq!(details.to_string(), "checkmated by the knight on f7");
+        }
 
-        let expected_black_moves = HashMap::from([
-            (
-                Position::a6(),
-                HashMap::from([(Position::a5(), MoveKind::Move)]),
-            ),
-            (
-                Position::b6(),
-                HashMap::from([(Position::b5(), MoveKind::Move)]),
-            ),
-            (
-                Position::e6(),
-                HashMap::from([(Position::e5(), MoveKind::Move)]),
-            ),
-            (
-                Position::f7(),
-                HashMap::from([
-                    (Position::f6(), MoveKind::Move),
-                    (Position::f5(), MoveKind::DoubleMove(Position::f6())),
-                ]),
-            ),
-            (
-                Position::g6(),
-                HashMap::from([(Position::g5(), MoveKind::Move)]),
-            ),
-            (
-                Position::h5(),
-                HashMap::from([(Position::h4(), MoveKind::Move)]),
-            ),
-            (
-                Position::a8(),
-                HashMap::from([
-                    (Position::a7(), MoveKind::Move),
-                    (Position::b8(), MoveKind::Move),
-                    (Position::c8(), MoveKind::Move),
-                    (Position::d8(), MoveKind::Move),
-                ]),
-            ),
+        // Not checkmate
+        assert_eq!(checkmate_details(&Board::default()), None);
+        assert_eq!(
+            checkmate_details(&fen::parse("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")?),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn move_request_from_san_test() -> Result<(), ParseError> {
+        let board = Board::default();
+        assert_eq!(
+            MoveRequest::from_san(&board, "e4")?,
+            MoveRequest::new(Position::e2(), Position::e4())
+        );
+        assert_eq!(
+            MoveRequest::from_san(&board, "Nf3")?,
+            MoveRequest::new(Position::g1(), Position::f3())
+        );
+        assert!(MoveRequest::from_san(&board, "e5").is_err());
+        assert!(MoveRequest::from_san(&board, "Qh5").is_err());
+        assert!(MoveRequest::from_san(&board, "").is_err());
+        assert!(MoveRequest::from_san(&board, "O-O").is_err());
+
+        // Disambiguation by file, by rank and the check suffix
+        let board =
+            fen::parse("rnb1kbnr/ppp1ppp1/3p4/2N5/4q2p/2N3N1/PPPPP1PP/R1BQKB1R w KQkq - 0 8")?;
+        assert!(MoveRequest::from_san(&board, "Nxe4").is_err());
+        assert_eq!(
+            MoveRequest::from_san(&board, "Ngxe4")?,
+            MoveRequest::new(Position::g3(), Position::e4())
+        );
+        assert!(MoveRequest::from_san(&board, "Ncxe4").is_err());
+        assert!(MoveRequest::from_san(&board, "N3xe4").is_err());
+        assert_eq!(
+            MoveRequest::from_san(&board, "N5xe4")?,
+            MoveRequest::new(Position::c5(), Position::e4())
+        );
+        assert_eq!(
+            MoveRequest::from_san(&board, "Nc3xe4+")?,
+            MoveRequest::new(Position::c3(), Position::e4())
+        );
+
+        // Castling
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        assert_eq!(
+            MoveRequest::from_san(&board, "O-O")?,
+            MoveRequest::new(Position::e1(), Position::g1())
+        );
+        assert_eq!(
+            MoveRequest::from_san(&board, "0-0-0")?,
+            MoveRequest::new(Position::e1(), Position::c1())
+        );
+
+        // Promotions
+        let board =
+            fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+        assert_eq!(
+            MoveRequest::from_san(&board, "bxa8=N")?,
+            MoveRequest::promotion(Position::b7(), Position::a8(), PromotionType::Knight)
+        );
+        assert_eq!(
+            MoveRequest::from_san(&board, "b8Q")?,
+            MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Queen)
+        );
+        assert!(MoveRequest::from_san(&board, "b8").is_err());
+        assert!(MoveRequest::from_san(&board, "d5=Q").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn display_test() {
+        assert_eq!(MoveState::Check.to_string(), "check");
+        assert_eq!(MoveState::Checkmate.to_string(), "checkmate");
+        assert_eq!(MoveState::Stalemate.to_string(), "stalemate");
+        assert_eq!(
+            MoveState::ThreefoldRepetition.to_string(),
+            "draw by threefold repetition"
+        );
+        assert_eq!(
+            MoveState::FiftyMoveRule.to_string(),
+            "draw by the fifty-move rule"
+        );
+
+        assert_eq!(MoveKind::Capture.to_string(), "capture");
+        assert_eq!(
+            MoveKind::EnPassant(Position::d5()).to_string(),
+            "en passant"
+        );
+        assert_eq!(MoveKind::ShortCastle.to_string(), "kingside castle");
+        assert_eq!(MoveKind::LongCastle.to_string(), "queenside castle");
+        assert_eq!(
+            MoveKind::DoubleMove(Position::e3()).to_string(),
+            "double pawn move"
+        );
+    }
+
+    #[test]
+    fn perft_test() -> Result<(), ParseError> {
+        // Reference node counts from the Chess Programming Wiki perft results.
+        let positions = [
             (
-                Position::c6(),
-                HashMap::from([
-                    (Position::a7(), MoveKind::Move),
-                    (Position::b8(), MoveKind::Move),
-                    (Position::d8(), MoveKind::Move),
-                    (Position::e7(), MoveKind::Move),
-                    (Position::e5(), MoveKind::Move),
-                    (Position::d4(), MoveKind::Capture),
-                    (Position::b4(), MoveKind::Move),
-                    (Position::a5(), MoveKind::Move),
-                ]),
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                vec![20, 400, 8902],
             ),
             (
-                Position::d7(),
-                HashMap::from([(Position::c8(), MoveKind::Move)]),
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                vec![48, 2039],
             ),
             (
-                Position::g8(),
-                HashMap::from([
-                    (Position::e7(), MoveKind::Move),
-                    (Position::f6(), MoveKind::Move),
-                    (Position::h6(), MoveKind::Move),
-                ]),
+                "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                vec![14, 191, 2812],
             ),
             (
-                Position::g7(),
-                HashMap::from([
-                    (Position::f8(), MoveKind::Move),
-                    (Position::h8(), MoveKind::Move),
-                    (Position::h6(), MoveKind::Move),
-                    (Position::f6(), MoveKind::Move),
-                    (Position::e5(), MoveKind::Move),
-                    (Position::d4(), MoveKind::Capture),
-                ]),
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                vec![6, 264],
             ),
             (
-                Position::h7(),
-                HashMap::from([
-                    (Position::h8(), MoveKind::Move),
-                    (Position::h6(), MoveKind::Move),
-                ]),
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                vec![44, 1486],
             ),
             (
-                Position::e8(),
-                HashMap::from([
-                    (Position::f8(), MoveKind::Move),
-                    (Position::e7(), MoveKind::Move),
-                    (Position::d8(), MoveKind::Move),
-                    (Position::c8(), MoveKind::LongCastle),
-                ]),
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                vec![46, 2079],
             ),
-        ]);
-
-        assert_eq!(all_black_moves, expected_black_moves);
+        ];
+
+        for (board_fen, node_counts) in positions {
+            let board = fen::parse(board_fen)?;
+            for (depth, nodes) in node_counts.into_iter().enumerate() {
+                assert_eq!(
+                    perft(&board, depth as u32 + 1),
+                    nodes,
+                    "{board_fen} depth {}",
+                    depth + 1
+                );
+            }
+        }
 
         Ok(())
     }
 
     #[test]
-    fn is_in_check_test() -> Result<(), ParseError> {
-        // White in check
-        {
-            let board =
-                fen::parse("rnb1kbnr/pp1ppppp/8/q1p5/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 1 3")?;
+    fn is_zugzwang_risky_test() -> Result<(), ParseError> {
+        let board = fen::parse("8/8/4k3/4p3/4P3/4K3/8/8 w - - 0 1")?;
+        assert!(is_zugzwang_risky(&board, &Side::White));
+        assert!(is_zugzwang_risky(&board, &Side::Black));
 
-            assert!(is_in_check(&board, &Side::White));
-        }
+        // A lone minor piece is still risky, a rook is enough to spare a move.
+        let board = fen::parse("8/8/4k3/4p3/4P3/4K3/8/5BR1 w - - 0 1")?;
+        assert!(!is_zugzwang_risky(&board, &Side::White));
+        assert!(is_zugzwang_risky(&board, &Side::Black));
+        let board = fen::parse("8/8/4k3/4p3/4P3/4K3/8/5B2 w - - 0 1")?;
+        assert!(is_zugzwang_risky(&board, &Side::White));
 
-        // White not in check
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1ppppp/8/2p5/8/3P1P2/PPP1P1PP/RNBQKBNR b KQkq - 0 2")?;
+        assert!(!is_zugzwang_risky(&Board::default(), &Side::White));
+        assert!(!is_zugzwang_risky(&Board::default(), &Side::Black));
 
-            assert!(!is_in_check(&board, &Side::Black));
+        Ok(())
+    }
+
+    // A generator that has forgotten about castling, to stand in for a disagreeing reference.
+    fn divide_without_castling(board: &Board, depth: u32) -> HashMap<String, u64> {
+        let mut divide = HashMap::new();
+        for (start, piece_moves) in get_all_legal_moves(board, &board.side_to_move()) {
+            for (end, move_kind) in piece_moves {
+                if matches!(move_kind, MoveKind::ShortCastle | MoveKind::LongCastle) {
+                    continue;
+                }
+                for request in perft_requests(start, end, &move_kind) {
+                    let mut new_board = board.clone();
+                    let coordinate = request.to_coordinate();
+                    make_move(&mut new_board, request).unwrap();
+                    let nodes = if depth == 1 {
+                        1
+                    } else {
+                        divide_without_castling(&new_board, depth - 1)
+                            .values()
+                            .sum()
+                    };
+                    divide.insert(coordinate, nodes);
+                }
+            }
         }
 
-        // Black in check
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppppp2p/8/5ppQ/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
+        divide
+    }
 
-            assert!(is_in_check(&board, &Side::Black));
+    #[test]
+    fn find_divergence_test() -> Result<(), ParseError> {
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+
+        let agreeing = |fen: &str, depth: u32| perft_divide(&fen::parse(fen).unwrap(), depth);
+        assert_eq!(find_divergence(&board, 3, &agreeing), None);
+
+        let broken =
+            |fen: &str, depth: u32| divide_without_castling(&fen::parse(fen).unwrap(), depth);
+        let divergence = find_divergence(&board, 3, &broken).unwrap();
+
+        // The search ends one move from the leaves, where only the castles are missing.
+        assert_eq!(divergence.depth, 1);
+        assert_eq!(divergence.moves.len(), 2);
+        assert!(!divergence.mismatches.is_empty());
+        for (coordinate, ours, reference) in &divergence.mismatches {
+            assert!(["e1g1", "e1c1", "e8g8", "e8c8"].contains(&coordinate.as_str()));
+            assert_eq!((*ours, *reference), (Some(1), None));
         }
 
-        // Black not in check
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppppp2p/8/5pp1/5P2/4P3/PPPP2PP/RNBQKBNR w KQkq g6 0 3")?;
-
-            assert!(!is_in_check(&board, &Side::Black));
+        // Replaying the path reaches the reported position.
+        let mut replayed = board.clone();
+        for coordinate in &divergence.moves {
+            move_piece(&mut replayed, MoveRequest::from_coordinate(coordinate)?).unwrap();
         }
+        assert_eq!(fen::generate(&replayed), divergence.fen);
 
         Ok(())
     }
 
     #[test]
-    fn get_move_state_test() -> Result<(), ParseError> {
-        // White in checkmate
-        {
-            let board =
-                fen::parse("rnb1kbnr/pppp1ppp/4p3/8/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")?;
+    fn perft_stats_test() -> Result<(), ParseError> {
+        // Reference breakdowns from the Chess Programming Wiki perft results.
+        let stats = perft_stats(&Board::default(), 4);
+        assert_eq!(
+            stats,
+            PerftStats {
+                nodes: 197281,
+                captures: 1576,
+                en_passant: 0,
+                castles: 0,
+                promotions: 0,
+                checks: 469,
+                checkmates: 8,
+            }
+        );
+
+        let board = fen::parse("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1")?;
+        let stats = perft_stats(&board, 3);
+        assert_eq!(
+            stats,
+            PerftStats {
+                nodes: 2812,
+                captures: 209,
+                en_passant: 2,
+                castles: 0,
+                promotions: 0,
+                checks: 267,
+                checkmates: 0,
+            }
+        );
+        assert!(stats
+            .to_string()
+            .starts_with("Nodes:      2812\nCaptures:   209\n"));
 
-            assert_eq!(get_move_state(&board), MoveState::Checkmate);
-        }
+        let board =
+            fen::parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")?;
+        let stats = perft_stats(&board, 1);
+        assert_eq!(stats.nodes, 48);
+        assert_eq!(stats.captures, 8);
+        assert_eq!(stats.castles, 2);
 
-        // White in check
-        {
-            let board =
-                fen::parse("rnb1kbnr/pppp1ppp/4p3/8/7q/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 1 3")?;
+        Ok(())
+    }
 
-            assert_eq!(get_move_state(&board), MoveState::Check);
-        }
+    #[test]
+    fn sliding_moves_match_ray_walk_test() {
+        // Compare the ray table lookups against walking each ray square by square on thousands
+        // of pseudo-random boards.
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next_random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
 
-        // White in stalemate
-        {
-            let board = fen::parse("rnb1kbnr/ppp1ppp1/8/8/8/8/4q3/6K1 w kq - 0 1")?;
+        let directions = [Offset::ORTHOGONAL, Offset::DIAGONAL].concat();
+        for _ in 0..5000 {
+            let occupied = next_random() & next_random();
+            let black = next_random();
 
-            assert_eq!(get_move_state(&board), MoveState::Stalemate);
-        }
+            let mut board = Board::empty();
+            for position in SquareSet::from_bits(occupied) {
+                let side = if black & (1 << position.value()) != 0 {
+                    Side::Black
+                } else {
+                    Side::White
+                };
+                board.add_piece(&position, Piece::new(PieceType::Pawn, side));
+            }
 
-        // White in 50 move rule stalemate
-        {
-            let board =
-                fen::parse("rnb1kbnr/ppppqppp/4p3/8/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 100 50")?;
+            let from = Position::from_index((next_random() % 64) as usize).unwrap();
+            let side = if next_random().is_multiple_of(2) {
+                Side::White
+            } else {
+                Side::Black
+            };
 
-            assert_eq!(get_move_state(&board), MoveState::Stalemate);
-        }
+            let filter = |new_position: &Position| {
+                if !board.is_occupied(new_position) {
+                    WhileMoveResult::Continue
+                } else if board.is_occupied_by(new_position, &side.opponent()) {
+                    WhileMoveResult::Capture
+                } else {
+                    WhileMoveResult::Stop
+                }
+            };
 
-        // White not in check
-        {
-            let board =
-                fen::parse("rnb1kbnr/ppppqppp/4p3/8/8/3P1P2/PPP1P1PP/RNBQKBNR w KQkq - 1 3")?;
+            let mut expected = HashMap::new();
+            for offset in &directions {
+                add_while_valid(&from, offset, filter, &mut expected);
+            }
 
-            assert_eq!(get_move_state(&board), MoveState::CanMove);
+            assert_eq!(get_while_valid(&board, &from, &side, &directions), expected);
         }
+    }
 
-        // Black in checkmate
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppppp2p/5p2/6pQ/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
-
-            assert_eq!(get_move_state(&board), MoveState::Checkmate);
+    #[test]
+    fn terminal_detection_test() -> Result<(), ParseError> {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            // Checkmates
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "R6k/6pp/8/8/8/8/8/6K1 b - - 0 1",
+            "6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1",
+            // Stalemates
+            "1R6/8/8/8/p2R4/k7/8/1K6 b - - 0 99",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            // Check with an escape
+            "4r1k1/8/8/8/7b/8/1R6/4K3 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = fen::parse(fen)?;
+            let move_state = get_move_state(&board);
+
+            assert_eq!(
+                board.has_legal_move(),
+                !get_all_legal_moves(&board, &board.side_to_move()).is_empty(),
+                "{fen}"
+            );
+            assert_eq!(
+                board.is_checkmate(),
+                move_state == MoveState::Checkmate,
+                "{fen}"
+            );
+            assert_eq!(
+                board.is_stalemate(),
+                move_state == MoveState::Stalemate,
+                "{fen}"
+            );
         }
 
-        // Black in check
+        assert!(fen::parse("R6k/6pp/8/8/8/8/8/6K1 b - - 0 1")?.is_checkmate());
+        assert!(fen::parse("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")?.is_stalemate());
+
+        Ok(())
+    }
+
+    #[test]
+    fn legal_move_filter_test() -> Result<(), ParseError> {
+        let legal_targets = |board: &Board, start: Position| {
+            let mut targets: Vec<Position> = get_all_legal_moves(board, &board.side_to_move())
+                .remove(&start)
+                .map(|moves| moves.into_keys().collect())
+                .unwrap_or_default();
+            targets.sort_by_key(Position::value);
+            targets
+        };
+
+        // Taking en passant would clear both pawns off the rank and expose the king.
+        let board = fen::parse("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1")?;
+        assert_eq!(legal_targets(&board, Position::b5()), vec![Position::b6()]);
+
+        // The same capture is fine once the rook is gone.
+        let board = fen::parse("8/8/8/KPp5/8/8/8/4k3 w - c6 0 1")?;
+        assert_eq!(
+            legal_targets(&board, Position::b5()),
+            vec![Position::b6(), Position::c6()]
+        );
+
+        // A pinned bishop can only slide along the pin.
+        let board = fen::parse("4k3/8/8/8/q7/8/2B5/3K4 w - - 0 1")?;
+        assert_eq!(
+            legal_targets(&board, Position::c2()),
+            vec![Position::b3(), Position::a4()]
+        );
+
+        // In check, only captures of the checker and interpositions are allowed.
+        let board = fen::parse("4r1k1/8/8/8/8/8/1R6/4K3 w - - 0 1")?;
+        assert_eq!(legal_targets(&board, Position::b2()), vec![Position::e2()]);
+
+        // A double check leaves only king moves.
+        let board = fen::parse("4r1k1/8/8/8/7b/8/1R6/4K3 w - - 0 1")?;
+        assert_eq!(
+            get_all_legal_moves(&board, &Side::White)
+                .keys()
+                .collect::<Vec<_>>(),
+            vec![&Position::e1()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_promotion_move_test() -> Result<(), ParseError> {
+        let board = fen::parse("r3k3/1P6/8/8/8/8/6p1/R3K2N w - - 0 1")?;
+        assert!(is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::b7(), Position::b8())
+        ));
+        assert!(is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::b7(), Position::a8())
+        ));
+        // Not a legal move for the pawn.
+        assert!(!is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::b7(), Position::c8())
+        ));
+        // A rook reaching the back rank isn't promoting.
+        assert!(!is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::a1(), Position::a8())
+        ));
+        // Black's pawn can only promote on its own turn.
+        assert!(!is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::g2(), Position::h1())
+        ));
+
+        let board = fen::parse("r3k3/1P6/8/8/8/8/6p1/R3K2N b - - 0 1")?;
+        assert!(is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::g2(), Position::g1())
+        ));
+        assert!(is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::g2(), Position::h1())
+        ));
+        assert!(!is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::a8(), Position::a1())
+        ));
+        assert!(!is_promotion_move(
+            &board,
+            &MoveRequest::new(Position::b7(), Position::b8())
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pawn_side_helpers_test() {
+        assert_eq!(Side::White.promotion_rank(), rank::EIGHT);
+        assert_eq!(Side::Black.promotion_rank(), rank::ONE);
+        assert_eq!(Side::White.pawn_start_rank(), rank::TWO);
+        assert_eq!(Side::Black.pawn_start_rank(), rank::SEVEN);
+        assert_eq!(Side::White.pawn_direction(), Offset::NORTH);
+        assert_eq!(Side::Black.pawn_direction(), Offset::SOUTH);
+    }
+
+    #[test]
+    fn possible_en_passant_capture_test() -> Result<(), ParseError> {
+        // Black pawn on the left of the target.
+        assert!(possible_en_passant_capture(&fen::parse(
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"
+        )?));
+
+        // Black pawn on the right of the target.
+        assert!(possible_en_passant_capture(&fen::parse(
+            "4k3/8/8/8/4Pp2/8/8/4K3 b - e3 0 1"
+        )?));
+
+        // White pawns on either side.
+        assert!(possible_en_passant_capture(&fen::parse(
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"
+        )?));
+        assert!(possible_en_passant_capture(&fen::parse(
+            "4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 1"
+        )?));
+
+        // No pawn able to take.
+        assert!(!possible_en_passant_capture(&fen::parse(
+            "4k3/8/8/8/4P2p/8/8/4K3 b - e3 0 1"
+        )?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_move_test() -> Result<(), ParseError> {
+        // Check with a single interposition
         {
-            let board =
-                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/7Q/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
+            let board = fen::parse("R6k/8/7K/8/8/8/b7/8 b - - 0 1")?;
 
-            assert_eq!(get_move_state(&board), MoveState::Check);
+            assert_eq!(
+                only_move(&board),
+                Some(MoveRequest::new(Position::a2(), Position::g8()))
+            );
         }
 
-        // Black in stalemate
+        // Stalemate
         {
             let board = fen::parse("1R6/8/8/8/p2R4/k7/8/1K6 b - - 0 99")?;
 
-            assert_eq!(get_move_state(&board), MoveState::Stalemate);
+            assert_eq!(only_move(&board), None);
         }
 
-        // Black in 50 move stalemate
+        // Normal position
         {
-            let board =
-                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/8/5P2/4PQ2/PPPP2PP/RNB1KBNR b KQkq - 100 50")?;
+            let board = Board::default();
 
-            assert_eq!(get_move_state(&board), MoveState::Stalemate);
+            assert_eq!(only_move(&board), None);
         }
 
-        // Black not in check
+        // A lone promotion is four different moves
         {
-            let board =
-                fen::parse("rnbqkbnr/ppp1p1pp/3p1p2/8/5P2/4PQ2/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
+            let board = fen::parse("8/P7/8/8/8/8/r7/k1K5 w - - 0 1")?;
 
-            assert_eq!(get_move_state(&board), MoveState::CanMove);
+            assert_eq!(only_move(&board), None);
         }
 
         Ok(())
@@ -2032,7 +4376,7 @@ mod tests {
             let board =
                 fen::parse("rnbqkbnr/pp1pp1pp/2p2p2/7Q/5P2/4P3/PPPP2PP/RNB1KBNR b KQkq - 1 3")?;
 
-            let all_legal_moves = get_all_legal_moves(&board, board.get_current_turn());
+            let all_legal_moves = get_all_legal_moves(&board, &board.side_to_move());
 
             let expected_legal_moves = HashMap::from([(
                 Position::g7(),
@@ -2047,14 +4391,14 @@ mod tests {
             let board =
                 fen::parse("rn2kbnr/ppp2ppp/1q1pp3/8/2B1P1b1/NP6/PBPP1PPP/R3K1NR w KQkq - 0 7")?;
 
-            let all_legal_moves = get_all_legal_moves(&board, board.get_current_turn());
+            let all_legal_moves = get_all_legal_moves(&board, &board.side_to_move());
 
             // Note that long castling is not a legal move, even though white still
             // has long castle rights and the start & end positions are not targets.
             // It is not legal because the king passes through check on d1.
             let king_moves = all_legal_moves.get(&Position::e1()).unwrap();
             assert!(!king_moves.contains_key(&Position::c1()));
-            assert!(board.get_castle_rights().white_long_castle_rights);
+            assert!(board.get_castle_rights().white.long);
         }
 
         // White no short castle because passthrough check
@@ -2062,21 +4406,21 @@ mod tests {
             let board =
                 fen::parse("rn2kbnr/ppp1ppp1/3p3p/8/2q1P1b1/NP3P1N/PBPP2PP/R3K2R w KQkq - 0 9")?;
 
-            let all_legal_moves = get_all_legal_moves(&board, board.get_current_turn());
+            let all_legal_moves = get_all_legal_moves(&board, &board.side_to_move());
 
             // Note that long castling is not a legal move, even though white still
             // has long castle rights and the start & end positions are not targets.
             // It is not legal because the king passes through check on d1.
             let king_moves = all_legal_moves.get(&Position::e1()).unwrap();
             assert!(!king_moves.contains_key(&Position::g1()));
-            assert!(board.get_castle_rights().white_short_castle_rights);
+            assert!(board.get_castle_rights().white.short);
         }
 
         // Black no long castle because passthrough check
         {
             let board = fen::parse("r3kbn1/pp2pppr/n2Q3p/P1P5/8/2P4P/P3PP1P/RNB1KBNR b KQq - 0 8")?;
 
-            let all_legal_moves = get_all_legal_moves(&board, board.get_current_turn());
+            let all_legal_moves = get_all_legal_moves(&board, &board.side_to_move());
 
             // The king has no valid moves.
             // Note that long castling is not a legal move, even though black still
@@ -2084,7 +4428,7 @@ mod tests {
             // It is not legal because the king passes through check on d8.
             assert!(!all_legal_moves.contains_key(&Position::e8()));
 
-            assert!(board.get_castle_rights().black_long_castle_rights);
+            assert!(board.get_castle_rights().black.long);
         }
 
         // Black no short castle because passthrough check
@@ -2092,14 +4436,250 @@ mod tests {
             let board =
                 fen::parse("rnb1k2r/ppqp1ppp/2p4n/4p3/1Q6/b1PP2PP/PP2PP2/RNB1KBNR b KQkq - 0 6")?;
 
-            let all_legal_moves = get_all_legal_moves(&board, board.get_current_turn());
+            let all_legal_moves = get_all_legal_moves(&board, &board.side_to_move());
 
             // Note that long castling is not a legal move, even though black still
             // has long castle rights and the start & end positions are not targets.
             // It is not legal because the king passes through check on d8.
             let king_moves = all_legal_moves.get(&Position::e8()).unwrap();
             assert!(!king_moves.contains_key(&Position::g8()));
-            assert!(board.get_castle_rights().black_short_castle_rights);
+            assert!(board.get_castle_rights().black.short);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn castle_rights_without_rook_test() -> Result<(), ParseError> {
+        // FEN parsing keeps castle rights as written, and generating castling for the missing h1
+        // rook used to panic when the move was tried.
+        let board =
+            fen::parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K21 w KQkq - 0 1")?;
+        let king_moves = get_all_legal_moves(&board, &Side::White)
+            .remove(&Position::e1())
+            .unwrap();
+        assert_eq!(king_moves.get(&Position::c1()), Some(&MoveKind::LongCastle));
+        assert!(!king_moves.contains_key(&Position::g1()));
+
+        let board =
+            fen::parse("r3k2r/p1ppqpb1/nn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/13K2R w KQkq - 0 1")?;
+        let king_moves = get_all_legal_moves(&board, &Side::White)
+            .remove(&Position::e1())
+            .unwrap();
+        assert!(!king_moves.contains_key(&Position::c1()));
+        assert_eq!(
+            king_moves.get(&Position::g1()),
+            Some(&MoveKind::ShortCastle)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter_legal_test() -> Result<(), ParseError> {
+        let board = fen::parse("4r2k/1P6/8/8/8/8/4R3/4K3 w - - 0 1")?;
+        let candidates = [
+            MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Knight),
+            MoveRequest::new(Position::b7(), Position::b8()),
+            MoveRequest::new(Position::e2(), Position::e8()),
+            // The rook is pinned to the king.
+            MoveRequest::new(Position::e2(), Position::d2()),
+            MoveRequest::promotion(Position::e1(), Position::d1(), PromotionType::Queen),
+            MoveRequest::new(Position::e1(), Position::d1()),
+            MoveRequest::new(Position::h8(), Position::g8()),
+            MoveRequest::new(Position::a1(), Position::a2()),
+        ];
+
+        assert_eq!(
+            filter_legal(&board, &candidates),
+            vec![true, false, true, false, false, true, false, false]
+        );
+        assert!(filter_legal(&board, &[]).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pawn_endings_test() -> Result<(), ParseError> {
+        // Kings and locked or blocked pawns, with their states and perft counts to depth 3.
+        let positions = [
+            (
+                "8/8/8/1p1p1p1p/1P1P1P1P/8/8/k6K w - - 0 1",
+                MoveState::CanMove,
+                [3, 9, 54],
+            ),
+            (
+                "k7/P7/K7/8/8/8/8/8 b - - 0 1",
+                MoveState::Stalemate,
+                [0, 0, 0],
+            ),
+            (
+                "8/8/8/8/8/k7/p7/K7 w - - 0 1",
+                MoveState::Stalemate,
+                [0, 0, 0],
+            ),
+            (
+                "4k3/8/8/8/8/4p3/4P3/4K3 w - - 0 1",
+                MoveState::CanMove,
+                [2, 10, 30],
+            ),
+            (
+                "4k3/8/8/8/4p3/8/4P3/4K3 w - - 0 1",
+                MoveState::CanMove,
+                [5, 29, 165],
+            ),
+            (
+                "k7/P7/8/8/8/8/8/7K w - - 0 1",
+                MoveState::CanMove,
+                [3, 6, 48],
+            ),
+            (
+                "8/8/3k4/p1p1p1p1/P1P1P1P1/8/8/3K4 b - - 0 1",
+                MoveState::CanMove,
+                [5, 25, 170],
+            ),
+            (
+                "8/8/8/8/8/3k4/4p3/3K4 w - - 0 1",
+                MoveState::Check,
+                [2, 15, 37],
+            ),
+            (
+                "k7/1PP5/1K6/8/8/8/8/8 b - - 0 1",
+                MoveState::Checkmate,
+                [0, 0, 0],
+            ),
+            (
+                "8/8/8/8/1pP5/8/8/k6K b - c3 0 1",
+                MoveState::CanMove,
+                [5, 19, 112],
+            ),
+            (
+                "8/2p5/2P5/8/8/8/k7/2K5 w - - 0 1",
+                MoveState::CanMove,
+                [3, 12, 65],
+            ),
+            (
+                "7k/5p1P/5P1K/8/8/8/8/8 w - - 0 1",
+                MoveState::CanMove,
+                [2, 2, 8],
+            ),
+        ];
+
+        for (board_fen, move_state, node_counts) in positions {
+            let board = fen::parse(board_fen)?;
+            assert_eq!(get_move_state(&board), move_state, "{board_fen}");
+            for (depth, nodes) in (1..).zip(node_counts) {
+                assert_eq!(perft(&board, depth), nodes, "{board_fen} at depth {depth}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocked_pawn_moves_test() -> Result<(), ParseError> {
+        // Blocked on the first square, the double move is gone too.
+        let board = fen::parse("4k3/8/8/8/8/4p3/4P3/4K3 w - - 0 1")?;
+        assert!(get_pawn_moves(&board, &Position::e2(), &Side::White).is_empty());
+
+        // Blocked on the second square, only the single move is left.
+        let board = fen::parse("4k3/8/8/8/4p3/8/4P3/4K3 w - - 0 1")?;
+        assert_eq!(
+            get_pawn_moves(&board, &Position::e2(), &Side::White),
+            HashMap::from([(Position::e3(), MoveKind::Move)])
+        );
+        let board = fen::parse("4k3/3p4/8/3P4/8/8/8/4K3 b - - 0 1")?;
+        assert_eq!(
+            get_pawn_moves(&board, &Position::d7(), &Side::Black),
+            HashMap::from([(Position::d6(), MoveKind::Move)])
+        );
+
+        // A pawn can't promote onto an occupied square, but can still capture onto one.
+        let board = fen::parse("kn6/P7/8/8/8/8/8/7K w - - 0 1")?;
+        assert_eq!(
+            get_pawn_moves(&board, &Position::a7(), &Side::White),
+            HashMap::from([(Position::b8(), MoveKind::Promotion(true))])
+        );
+        let board = fen::parse("7k/8/8/8/8/8/p7/K7 b - - 0 1")?;
+        assert!(get_pawn_moves(&board, &Position::a2(), &Side::Black).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn random_game_invariants_test() -> Result<(), ParseError> {
+        // Plays pseudo-random legal games and checks after every move that undo restores the
+        // board, that the SAN reads back as the same move and that no king can be captured.
+        let starts = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next_random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize
+        };
+
+        for game in 0..20 {
+            let mut board = fen::parse(starts[game % starts.len()])?;
+
+            for _ in 0..50 {
+                let side = board.side_to_move();
+                let all_legal_moves = get_all_legal_moves(&board, &side);
+
+                let mut requests: Vec<MoveRequest> = all_legal_moves
+                    .iter()
+                    .flat_map(|(start, piece_moves)| {
+                        piece_moves
+                            .iter()
+                            .flat_map(|(end, move_kind)| perft_requests(*start, *end, move_kind))
+                    })
+                    .collect();
+                if requests.is_empty() {
+                    break;
+                }
+                requests.sort_by_key(|request| {
+                    (
+                        request.start.value(),
+                        request.end.value(),
+                        request.promotion.map(|promotion| promotion.to_algebraic()),
+                    )
+                });
+
+                for request in &requests {
+                    assert!(board
+                        .piece_at(&request.end)
+                        .is_none_or(|piece| piece.piece_type != PieceType::King));
+                }
+
+                let request = requests[next_random() % requests.len()].clone();
+                let before = board.clone();
+                let before_fen = fen::generate(&board);
+
+                let (file_disambiguation, rank_disambiguation) =
+                    get_disambiguation(&board, &all_legal_moves, &request);
+                let (mut move_info, move_undo) = make_move(&mut board, request.clone()).unwrap();
+                move_info.file_disambiguation = file_disambiguation;
+                move_info.rank_disambiguation = rank_disambiguation;
+
+                let san = move_info.to_notation();
+                assert_eq!(
+                    MoveRequest::from_san(&before, &san).ok(),
+                    Some(request),
+                    "{before_fen} {san}"
+                );
+                assert_boards_eq!(fen::parse(&fen::generate(&board))?, board);
+
+                let mut undone = board.clone();
+                undone.undo(move_undo);
+                assert_eq!(undone, before);
+                assert_eq!(fen::generate(&undone), before_fen);
+            }
         }
 
         Ok(())
@@ -0,0 +1,145 @@
+use crate::{
+    board::{file, position::Position, rank, Board},
+    piece::Side,
+};
+
+// Whether `side` holds the opposition: the kings stand on the same file, rank or diagonal with an
+// odd number of squares between them, and it is the other side's turn to move. One square apart is
+// direct opposition, three or five is distant opposition.
+pub fn has_opposition(board: &Board, side: &Side) -> bool {
+    let (Some(king), Some(enemy_king)) = (
+        board.king_position(side),
+        board.king_position(&side.opponent()),
+    ) else {
+        return false;
+    };
+
+    if board.side_to_move() == *side {
+        return false;
+    }
+
+    let file_distance = king.file().abs_diff(enemy_king.file());
+    let rank_distance = king.rank().abs_diff(enemy_king.rank());
+    let distance = match (file_distance, rank_distance) {
+        (0, distance) | (distance, 0) => distance,
+        (file_distance, rank_distance) if file_distance == rank_distance => file_distance,
+        _ => return false,
+    };
+
+    distance > 0 && distance % 2 == 0
+}
+
+// The squares which, once reached by the attacking king, win the ending for `side`'s pawn
+// regardless of who is to move. Up to the fourth rank they lie two ranks ahead of the pawn; from
+// the fifth rank on, one and two ranks ahead. Rook pawns only have the two squares next to the
+// promotion square on the neighbouring file.
+pub fn key_squares(pawn: &Position, side: &Side) -> Vec<Position> {
+    let relative_rank = |square_rank: usize| match side {
+        Side::White => square_rank,
+        Side::Black => rank::EIGHT - square_rank,
+    };
+
+    if pawn.file() == file::A || pawn.file() == file::H {
+        let key_file = if pawn.file() == file::A {
+            file::B
+        } else {
+            file::G
+        };
+        return [rank::SEVEN, rank::EIGHT]
+            .iter()
+            .filter_map(|key_rank| Position::try_new(key_file, relative_rank(*key_rank)))
+            .collect();
+    }
+
+    let pawn_rank = relative_rank(pawn.rank());
+    let ranks_ahead: &[usize] = if pawn_rank <= rank::FOUR {
+        &[2]
+    } else {
+        &[1, 2]
+    };
+
+    let mut squares = Vec::new();
+    for ahead in ranks_ahead {
+        let key_rank = pawn_rank + ahead;
+        if key_rank > rank::EIGHT {
+            continue;
+        }
+        for key_file in pawn.file() - 1..=pawn.file() + 1 {
+            if let Some(square) = Position::try_new(key_file, relative_rank(key_rank)) {
+                squares.push(square);
+            }
+        }
+    }
+
+    squares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen, ParseError};
+
+    #[test]
+    fn opposition_test() -> Result<(), ParseError> {
+        // Direct opposition, with Black to move.
+        let board = fen::parse("4k3/8/4K3/8/8/8/8/8 b - - 0 1")?;
+        assert!(has_opposition(&board, &Side::White));
+        assert!(!has_opposition(&board, &Side::Black));
+
+        // The same kings with White to move hand the opposition to Black.
+        let board = fen::parse("4k3/8/4K3/8/8/8/8/8 w - - 0 1")?;
+        assert!(has_opposition(&board, &Side::Black));
+        assert!(!has_opposition(&board, &Side::White));
+
+        // Distant opposition along a file and a rank.
+        let board = fen::parse("4k3/8/8/8/4K3/8/8/8 b - - 0 1")?;
+        assert!(has_opposition(&board, &Side::White));
+        let board = fen::parse("8/8/8/k5K1/8/8/8/8 w - - 0 1")?;
+        assert!(has_opposition(&board, &Side::Black));
+
+        // Diagonal opposition.
+        let board = fen::parse("4k3/8/2K5/8/8/8/8/8 b - - 0 1")?;
+        assert!(has_opposition(&board, &Side::White));
+
+        // An even number of squares between the kings isn't opposition.
+        let board = fen::parse("4k3/8/8/4K3/8/8/8/8 b - - 0 1")?;
+        assert!(!has_opposition(&board, &Side::White));
+        assert!(!has_opposition(&board, &Side::Black));
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_squares_test() {
+        assert_eq!(
+            key_squares(&Position::d4(), &Side::White),
+            vec![Position::c6(), Position::d6(), Position::e6()]
+        );
+        assert_eq!(
+            key_squares(&Position::d6(), &Side::White),
+            vec![
+                Position::c7(),
+                Position::d7(),
+                Position::e7(),
+                Position::c8(),
+                Position::d8(),
+                Position::e8()
+            ]
+        );
+
+        // Black's pawns mirror White's.
+        assert_eq!(
+            key_squares(&Position::d5(), &Side::Black),
+            vec![Position::c3(), Position::d3(), Position::e3()]
+        );
+
+        assert_eq!(
+            key_squares(&Position::a3(), &Side::White),
+            vec![Position::b7(), Position::b8()]
+        );
+        assert_eq!(
+            key_squares(&Position::h6(), &Side::Black),
+            vec![Position::g2(), Position::g1()]
+        );
+    }
+}
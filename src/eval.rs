@@ -0,0 +1,353 @@
+use crate::{
+    board::{position::Position, Board, FileState},
+    piece::{PieceType, Side},
+};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum RaceOutcome {
+    // The pawn promotes before the defending king can catch it.
+    Promotes { side: Side, pawn: Position },
+    // The defending king gets back in time.
+    Caught { side: Side, pawn: Position },
+}
+
+// Centipawn bonuses for rook placement.
+const ROOK_ON_OPEN_FILE: i32 = 25;
+const ROOK_ON_HALF_OPEN_FILE: i32 = 12;
+const ROOK_ON_SEVENTH_RANK: i32 = 20;
+const DOUBLED_ROOKS: i32 = 15;
+const CONNECTED_ROOKS: i32 = 10;
+
+struct Passer {
+    side: Side,
+    pawn: Position,
+    moves_to_promote: usize,
+    unstoppable: bool,
+}
+
+// Decides pawn races in king and pawn endings, using the rule of the square and whether the
+// attacking king already covers the promotion square. Returns None if there are other pieces on
+// the board or no passed pawns.
+pub fn passed_pawn_race(board: &Board) -> Option<RaceOutcome> {
+    let pieces: Vec<Position> = board
+        .get_white_positions()
+        .iter()
+        .chain(board.get_black_positions().iter())
+        .collect();
+
+    let only_kings_and_pawns = pieces.iter().all(|position| {
+        matches!(
+            board.piece_at(position).map(|piece| &piece.piece_type),
+            Some(PieceType::King | PieceType::Pawn)
+        )
+    });
+    if !only_kings_and_pawns {
+        return None;
+    }
+
+    let mut passers: Vec<Passer> = [Side::White, Side::Black]
+        .iter()
+        .flat_map(|side| passed_pawns(board, side))
+        .collect();
+
+    // The quickest unstoppable pawn decides the race; the side to move is a tempo ahead.
+    let side_to_move = board.side_to_move();
+    passers.sort_by_key(|passer| {
+        let tempo = usize::from(passer.side != side_to_move);
+        (
+            !passer.unstoppable,
+            passer.moves_to_promote + tempo,
+            passer.pawn.value(),
+        )
+    });
+
+    passers.into_iter().next().map(|passer| {
+        if passer.unstoppable {
+            RaceOutcome::Promotes {
+                side: passer.side,
+                pawn: passer.pawn,
+            }
+        } else {
+            RaceOutcome::Caught {
+                side: passer.side,
+                pawn: passer.pawn,
+            }
+        }
+    })
+}
+
+// Rewards `side`'s rooks for open and half-open files, the seventh rank (the opponent's pawn
+// rank), sharing a file and defending each other.
+pub fn rook_placement(board: &Board, side: &Side) -> i32 {
+    let rooks: Vec<Position> = side_positions(board, side)
+        .into_iter()
+        .filter(|position| {
+            board.piece_at(position).map(|piece| &piece.piece_type) == Some(&PieceType::Rook)
+        })
+        .collect();
+
+    let mut score = 0;
+    for rook in &rooks {
+        score += match board.file_state(rook.file()) {
+            FileState::Open => ROOK_ON_OPEN_FILE,
+            FileState::HalfOpenFor(file_side) if file_side == *side => ROOK_ON_HALF_OPEN_FILE,
+            _ => 0,
+        };
+
+        if rook.rank() == side.opponent().pawn_start_rank() {
+            score += ROOK_ON_SEVENTH_RANK;
+        }
+    }
+
+    let doubled = rooks.iter().enumerate().any(|(index, rook)| {
+        rooks[index + 1..]
+            .iter()
+            .any(|other| other.file() == rook.file())
+    });
+    if doubled {
+        score += DOUBLED_ROOKS;
+    }
+
+    if board.rooks_connected(side) {
+        score += CONNECTED_ROOKS;
+    }
+
+    score
+}
+
+fn passed_pawns(board: &Board, side: &Side) -> Vec<Passer> {
+    let opponent = side.opponent();
+    let (Some(king), Some(enemy_king)) =
+        (board.king_position(side), board.king_position(&opponent))
+    else {
+        return Vec::new();
+    };
+
+    let mut passers = Vec::new();
+    for pawn in pawn_positions(board, side) {
+        let is_passed = pawn_positions(board, &opponent).iter().all(|enemy_pawn| {
+            enemy_pawn.file().abs_diff(pawn.file()) > 1 || !is_ahead(enemy_pawn, &pawn, side)
+        });
+        if !is_passed {
+            continue;
+        }
+
+        let promotion_square = Position::promotion_square(&pawn, side);
+        // A pawn on its starting rank saves a move with the double step.
+        let mut moves_to_promote = pawn.rank().abs_diff(promotion_square.rank());
+        if pawn.rank() == side.pawn_start_rank() {
+            moves_to_promote -= 1;
+        }
+
+        // Rule of the square: the defender catches the pawn if its king can reach the promotion
+        // square no later than the pawn, counting the extra move it gets when it is to move.
+        let defender_moves = enemy_king.chebyshev_distance(&promotion_square);
+        let defender_tempo = usize::from(board.side_to_move() == opponent);
+        let outside_square = defender_moves > moves_to_promote + defender_tempo;
+
+        // A king next to the promotion square escorts the pawn in, unless the defender is already
+        // standing in its way.
+        let path_clear = path_to_promotion(&pawn, side)
+            .iter()
+            .all(|square| board.piece_at(square).is_none());
+        let shepherded = king.chebyshev_distance(&promotion_square) <= 1
+            && enemy_king.chebyshev_distance(&promotion_square) > 1;
+
+        passers.push(Passer {
+            side: *side,
+            pawn,
+            moves_to_promote,
+            unstoppable: path_clear && (outside_square || shepherded),
+        });
+    }
+
+    passers
+}
+
+fn is_ahead(position: &Position, pawn: &Position, side: &Side) -> bool {
+    match side {
+        Side::White => position.rank() > pawn.rank(),
+        Side::Black => position.rank() < pawn.rank(),
+    }
+}
+
+fn path_to_promotion(pawn: &Position, side: &Side) -> Vec<Position> {
+    let forward = side.pawn_direction();
+
+    let mut path = Vec::new();
+    let mut current = pawn + forward;
+    while let Some(position) = current {
+        current = position + forward;
+        path.push(position);
+    }
+
+    path
+}
+
+fn pawn_positions(board: &Board, side: &Side) -> Vec<Position> {
+    side_positions(board, side)
+        .into_iter()
+        .filter(|position| {
+            board.piece_at(position).map(|piece| &piece.piece_type) == Some(&PieceType::Pawn)
+        })
+        .collect()
+}
+
+fn side_positions(board: &Board, side: &Side) -> Vec<Position> {
+    match side {
+        Side::White => board.get_white_positions().iter().collect(),
+        Side::Black => board.get_black_positions().iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen, ParseError};
+
+    #[test]
+    fn unstoppable_passer_test() -> Result<(), ParseError> {
+        // The black king is far outside the square of the a-pawn.
+        let board = fen::parse("8/7k/8/P7/8/8/8/6K1 b - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Promotes {
+                side: Side::White,
+                pawn: Position::a5()
+            })
+        );
+
+        // Just outside the square with White to move.
+        let board = fen::parse("8/8/4k3/P7/8/8/8/6K1 w - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Promotes {
+                side: Side::White,
+                pawn: Position::a5()
+            })
+        );
+
+        // A pawn that FEN left on its promotion rank has nothing left to race.
+        let board = fen::parse("P3k3/8/8/8/8/8/8/4K3 w - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Promotes {
+                side: Side::White,
+                pawn: Position::a8()
+            })
+        );
+
+        // The white king escorts the pawn past the defender.
+        let board = fen::parse("8/1K6/3k4/P7/8/8/8/8 b - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Promotes {
+                side: Side::White,
+                pawn: Position::a5()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn caught_passer_test() -> Result<(), ParseError> {
+        let board = fen::parse("8/8/3k4/P7/8/8/8/6K1 b - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Caught {
+                side: Side::White,
+                pawn: Position::a5()
+            })
+        );
+
+        // On the edge of the square the king still gets there in time with White to move:
+        // 1.a6 Kc7 2.a7 Kb7 3.a8=Q+ Kxa8.
+        let board = fen::parse("8/8/3k4/P7/8/8/8/6K1 w - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Caught {
+                side: Side::White,
+                pawn: Position::a5()
+            })
+        );
+
+        // A pawn on its starting rank can double step out of the square.
+        let board = fen::parse("8/8/8/8/6k1/8/P7/6K1 w - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Promotes {
+                side: Side::White,
+                pawn: Position::a2()
+            })
+        );
+        // A king on the edge of the square still catches it:
+        // 1.a4 Ke5 2.a5 Kd6 3.a6 Kc7 4.a7 Kb7 5.a8=Q+ Kxa8.
+        let board = fen::parse("8/8/8/8/5k2/8/P7/6K1 w - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Caught {
+                side: Side::White,
+                pawn: Position::a2()
+            })
+        );
+        let board = fen::parse("8/8/8/8/3k4/8/P7/6K1 w - - 0 1")?;
+        assert_eq!(
+            passed_pawn_race(&board),
+            Some(RaceOutcome::Caught {
+                side: Side::White,
+                pawn: Position::a2()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_a_race_test() -> Result<(), ParseError> {
+        // Blocked pawns aren't passed.
+        let board = fen::parse("8/4k3/8/p7/P7/8/8/4K3 w - - 0 1")?;
+        assert_eq!(passed_pawn_race(&board), None);
+
+        // Other pieces make it more than a pawn race.
+        let board = fen::parse("8/7k/8/P7/8/8/8/5RK1 w - - 0 1")?;
+        assert_eq!(passed_pawn_race(&board), None);
+
+        assert_eq!(passed_pawn_race(&Board::default()), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rook_placement_test() -> Result<(), ParseError> {
+        // Rooks at home behind their pawns get nothing, not even for being connected.
+        assert_eq!(rook_placement(&Board::default(), &Side::White), 0);
+
+        let board = fen::parse("4k3/p7/8/8/8/8/1PP5/R1R1K3 w - - 0 1")?;
+        assert_eq!(
+            rook_placement(&board, &Side::White),
+            ROOK_ON_HALF_OPEN_FILE + CONNECTED_ROOKS
+        );
+
+        let board = fen::parse("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")?;
+        assert_eq!(rook_placement(&board, &Side::White), ROOK_ON_OPEN_FILE);
+
+        // Black's a-file is half-open for White only.
+        let board = fen::parse("r3k3/p7/8/8/8/8/8/4K3 b - - 0 1")?;
+        assert_eq!(rook_placement(&board, &Side::Black), 0);
+
+        let board = fen::parse("4k3/R7/8/8/8/8/8/R3K3 w - - 0 1")?;
+        assert_eq!(
+            rook_placement(&board, &Side::White),
+            2 * ROOK_ON_OPEN_FILE + ROOK_ON_SEVENTH_RANK + DOUBLED_ROOKS + CONNECTED_ROOKS
+        );
+
+        let board = fen::parse("4k3/8/8/8/8/8/1r1p4/3K4 b - - 0 1")?;
+        assert_eq!(
+            rook_placement(&board, &Side::Black),
+            ROOK_ON_OPEN_FILE + ROOK_ON_SEVENTH_RANK
+        );
+
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 mod generate;
+mod many;
 mod parse;
 
 pub use generate::generate;
-pub use parse::parse;
+pub use many::{parse_many, write_many};
+pub use parse::{
+    fullmove_number, parse, parse_with, peek, peek_with, side_to_move, FenFields, ParseOptions,
+};
@@ -5,9 +5,9 @@ use crate::{
 
 pub fn generate(board: &Board) -> String {
     let piece_placement = generate_piece_placement(board);
-    let active_color = generate_active_color(board.get_current_turn());
+    let active_color = generate_active_color(&board.side_to_move());
     let castling_availability = generate_castling_availability(board.get_castle_rights());
-    let en_passant_target = generate_en_passant_target(board.get_en_passant_target());
+    let en_passant_target = generate_en_passant_target(&board.en_passant_target());
     let half_moves = generate_half_moves(board.get_half_moves());
     let full_moves = generate_full_moves(board.get_full_moves());
 
@@ -20,8 +20,8 @@ pub fn generate_piece_placement(board: &Board) -> String {
         let mut rank_string = String::new();
         let mut current_empty_count = 0;
         for current_file in file::A..=file::H {
-            let position = Position::from_file_and_rank(current_file, current_rank);
-            match board.get_piece(&position) {
+            let position = Position::new_unchecked(current_file, current_rank);
+            match board.piece_at(&position) {
                 Some(piece) => {
                     if current_empty_count > 0 {
                         rank_string.push_str(&current_empty_count.to_string());
@@ -55,29 +55,7 @@ pub fn generate_active_color(side: &Side) -> String {
 }
 
 pub fn generate_castling_availability(castle_rights: &CastleRights) -> String {
-    let mut castling_availability = String::new();
-
-    if castle_rights.white_short_castle_rights {
-        castling_availability.push('K');
-    }
-
-    if castle_rights.white_long_castle_rights {
-        castling_availability.push('Q');
-    }
-
-    if castle_rights.black_short_castle_rights {
-        castling_availability.push('k');
-    }
-
-    if castle_rights.black_long_castle_rights {
-        castling_availability.push('q');
-    }
-
-    if castling_availability.is_empty() {
-        castling_availability.push('-');
-    }
-
-    castling_availability
+    castle_rights.to_string()
 }
 
 pub fn generate_en_passant_target(target: &Option<Position>) -> String {
@@ -0,0 +1,87 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{board::Board, fen, ParseError};
+
+// Reads one FEN per line, skipping blank lines and lines starting with '#'. Each error carries
+// the 1-based line it came from, and reading carries on past it.
+pub fn parse_many(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<Board, (usize, ParseError)>> {
+    reader.lines().enumerate().filter_map(|(index, line)| {
+        let line_number = index + 1;
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                let error = ParseError::new(&format!("Unable to read the line: {error}"));
+                return Some(Err((line_number, error)));
+            }
+        };
+
+        let fen = line.trim();
+        if fen.is_empty() || fen.starts_with('#') {
+            return None;
+        }
+
+        Some(fen::parse(fen).map_err(|error| (line_number, error)))
+    })
+}
+
+pub fn write_many<'a>(
+    boards: impl IntoIterator<Item = &'a Board>,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    for board in boards {
+        writeln!(writer, "{}", fen::generate(board))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS: &str = "\
+# Positions from the perft suite
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1
+
+  8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1
+";
+
+    #[test]
+    fn parse_many_test() {
+        let boards: Vec<Board> = parse_many(CORPUS.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(boards.len(), 3);
+        assert_eq!(boards[0], Board::startpos());
+
+        let mut written = Vec::new();
+        write_many(&boards, &mut written).unwrap();
+        let expected: Vec<&str> = CORPUS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            format!("{}\n", expected.join("\n"))
+        );
+    }
+
+    #[test]
+    fn parse_many_error_lines_test() {
+        let corpus = format!("{CORPUS}8/8/8/8/8/8/8 w - - 0 1\n\n# done\nnot a fen\n");
+        let results: Vec<_> = parse_many(corpus.as_bytes()).collect();
+        assert_eq!(results.len(), 5);
+        assert!(results[..3].iter().all(Result::is_ok));
+
+        let error_lines: Vec<usize> = results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .map(|(line, _)| *line)
+            .collect();
+        assert_eq!(error_lines, vec![6, 9]);
+    }
+}
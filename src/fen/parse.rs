@@ -4,7 +4,59 @@ use crate::{
     ParseError,
 };
 
+// The fields of a FEN string, with everything but the piece placement parsed. Reading these
+// doesn't build a Board, for callers that only need the side to move or the move counters.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct FenFields<'a> {
+    pub piece_placement: &'a str,
+    pub current_turn: Side,
+    pub castle_rights: CastleRights,
+    pub en_passant_target: Option<Position>,
+    pub half_moves: u32,
+    pub full_moves: u32,
+}
+
+// Lenient parsing, the default, fixes up counters other tools commonly get wrong, such as a full
+// move number of 0. Strict parsing rejects them instead, along with counters that contradict
+// each other.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub strict: bool,
+}
+
 pub fn parse(fen: &str) -> Result<Board, ParseError> {
+    parse_with(fen, &ParseOptions::default())
+}
+
+pub fn parse_with(fen: &str, options: &ParseOptions) -> Result<Board, ParseError> {
+    let fields = peek_with(fen, options)?;
+    let pieces = parse_piece_placement(fields.piece_placement)?;
+
+    let board = Board::try_new(
+        pieces,
+        fields.current_turn,
+        fields.castle_rights,
+        fields.en_passant_target,
+        fields.half_moves,
+        fields.full_moves,
+    )
+    .map_err(|error| ParseError::new(error.to_string().as_str()))?;
+
+    // The half move clock counts plies, so it can't be more than the plies played so far.
+    if options.strict && board.get_half_moves() > board.ply() {
+        return Err(ParseError::new(
+            "The half move clock is larger than the number of plies played.",
+        ));
+    }
+
+    Ok(board)
+}
+
+pub fn peek(fen: &str) -> Result<FenFields<'_>, ParseError> {
+    peek_with(fen, &ParseOptions::default())
+}
+
+pub fn peek_with<'a>(fen: &'a str, options: &ParseOptions) -> Result<FenFields<'a>, ParseError> {
     let mut fen_iter = fen.split(' ');
 
     let piece_placement = fen_iter
@@ -26,23 +78,33 @@ pub fn parse(fen: &str) -> Result<Board, ParseError> {
         .next()
         .ok_or(ParseError::new("Missing full move data."))?;
 
-    let pieces = parse_piece_placement(piece_placement)?;
     let current_turn = parse_active_color(active_color)?;
-    let castle_rights = parse_castling_availability(castling_availability)?;
-    let en_passant_target = parse_en_passant_target(en_passant_target_square)?;
     let half_moves = parse_half_moves(half_moves)?;
-    let full_moves = parse_full_moves(full_moves)?;
+    let mut full_moves = parse_full_moves(full_moves)?;
 
-    let board = Board::new(
-        pieces,
+    if full_moves == 0 {
+        if options.strict {
+            return Err(ParseError::new("The full move number starts at 1."));
+        }
+        full_moves = 1;
+    }
+
+    Ok(FenFields {
+        piece_placement,
         current_turn,
-        castle_rights,
-        en_passant_target,
+        castle_rights: parse_castling_availability(castling_availability)?,
+        en_passant_target: parse_en_passant_target(en_passant_target_square)?,
         half_moves,
         full_moves,
-    );
+    })
+}
 
-    Ok(board)
+pub fn side_to_move(fen: &str) -> Result<Side, ParseError> {
+    peek(fen).map(|fields| fields.current_turn)
+}
+
+pub fn fullmove_number(fen: &str) -> Result<u32, ParseError> {
+    peek(fen).map(|fields| fields.full_moves)
 }
 
 pub fn parse_piece_placement(piece_notation: &str) -> Result<Vec<(Position, Piece)>, ParseError> {
@@ -50,6 +112,10 @@ pub fn parse_piece_placement(piece_notation: &str) -> Result<Vec<(Position, Piec
 
     let mut current_rank = rank::LENGTH;
     for rank_positions in piece_notation.split('/') {
+        // Every rank has been filled in, so anything left over is an extra rank.
+        if current_rank == 0 {
+            return Err(ParseError::new("Too many ranks found."));
+        }
         current_rank -= 1;
 
         let mut current_file: usize = file::A;
@@ -58,7 +124,14 @@ pub fn parse_piece_placement(piece_notation: &str) -> Result<Vec<(Position, Piec
                 let empty_positions = item.to_digit(10).unwrap() as usize;
                 current_file += empty_positions;
             } else {
-                let position = Position::from_file_and_rank(current_file, current_rank);
+                let Some(position) = Position::try_new(current_file, current_rank) else {
+                    let error = format!(
+                        "Rank {}'s notation exceeded the board length.",
+                        rank::to_char(current_rank)
+                    );
+                    return Err(ParseError::new(error.as_str()));
+                };
+
                 if let Some(piece) = Piece::from(item) {
                     pieces.push((position, piece));
                     current_file += 1;
@@ -86,10 +159,6 @@ pub fn parse_piece_placement(piece_notation: &str) -> Result<Vec<(Position, Piec
             );
             return Err(ParseError::new(error.as_str()));
         }
-
-        if current_rank == 0 {
-            break;
-        }
     }
 
     // We were given an insufficient number of ranks
@@ -112,33 +181,12 @@ pub fn parse_active_color(active_color: &str) -> Result<Side, ParseError> {
 }
 
 pub fn parse_castling_availability(castling_availibity: &str) -> Result<CastleRights, ParseError> {
-    let mut white_short_castle_rights = false;
-    let mut white_long_castle_rights = false;
-    let mut black_short_castle_rights = false;
-    let mut black_long_castle_rights = false;
-
-    if castling_availibity.contains("K") {
-        white_short_castle_rights = true;
-    }
-
-    if castling_availibity.contains("Q") {
-        white_long_castle_rights = true;
-    }
-
-    if castling_availibity.contains("k") {
-        black_short_castle_rights = true;
-    }
-
-    if castling_availibity.contains("q") {
-        black_long_castle_rights = true;
-    }
-
-    let castling_rights = CastleRights {
-        white_short_castle_rights,
-        white_long_castle_rights,
-        black_short_castle_rights,
-        black_long_castle_rights,
-    };
+    let castling_rights = CastleRights::new(
+        castling_availibity.contains('K'),
+        castling_availibity.contains('Q'),
+        castling_availibity.contains('k'),
+        castling_availibity.contains('q'),
+    );
 
     Ok(castling_rights)
 }
@@ -244,17 +292,17 @@ mod tests {
         ];
 
         for (position, piece) in position_tests {
-            assert_eq!(board.get_piece(&position), piece.as_ref());
+            assert_eq!(board.piece_at(&position), piece.as_ref());
         }
 
-        assert_eq!(*board.get_current_turn(), Side::Black);
+        assert_eq!(board.side_to_move(), Side::Black);
 
         assert_eq!(
             *board.get_castle_rights(),
             CastleRights::new(true, false, false, true)
         );
 
-        assert_eq!(*board.get_en_passant_target(), Some(Position::d3()));
+        assert_eq!(board.en_passant_target(), Some(Position::d3()));
 
         assert_eq!(board.get_half_moves(), 0);
 
@@ -288,6 +336,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_mutated_fens_test() {
+        // Randomly edited FENs must either parse or fail with an error, never panic, and whatever
+        // parses must survive a round trip through generate.
+        let seeds = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - e3 0 1",
+        ];
+        let alphabet: Vec<char> = "pnbrqkPNBRQK0123456789/ -wKQabcdefgh".chars().collect();
+
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next_random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize
+        };
+
+        for iteration in 0..20000 {
+            let mut fen: Vec<char> = seeds[iteration % seeds.len()].chars().collect();
+            for _ in 0..1 + next_random() % 4 {
+                let index = next_random() % (fen.len() + 1);
+                let item = alphabet[next_random() % alphabet.len()];
+                match next_random() % 3 {
+                    0 => fen.insert(index, item),
+                    1 if index < fen.len() => {
+                        fen.remove(index);
+                    }
+                    _ if index < fen.len() => fen[index] = item,
+                    _ => (),
+                }
+            }
+            let fen: String = fen.into_iter().collect();
+
+            if let Ok(board) = parse(&fen) {
+                let generated = crate::fen::generate(&board);
+                assert_eq!(parse(&generated).ok(), Some(board), "{fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn peek_test() -> Result<(), ParseError> {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 3 17",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 50 70",
+        ];
+
+        for fen in fens {
+            let fields = peek(fen)?;
+            let board = parse(fen)?;
+
+            assert_eq!(fields.piece_placement, fen.split(' ').next().unwrap());
+            assert_eq!(fields.current_turn, board.side_to_move());
+            assert_eq!(&fields.castle_rights, board.get_castle_rights());
+            assert_eq!(fields.en_passant_target, board.en_passant_target());
+            assert_eq!(fields.half_moves, board.get_half_moves());
+            assert_eq!(fields.full_moves, board.get_full_moves());
+
+            assert_eq!(side_to_move(fen)?, fields.current_turn);
+            assert_eq!(fullmove_number(fen)?, fields.full_moves);
+        }
+
+        // The placement isn't checked, only the fields that are parsed.
+        assert!(peek("not-a-board b - - 0 12").is_ok());
+        assert_eq!(fullmove_number("not-a-board b - - 0 12")?, 12);
+        assert!(peek("8/8/8/8/8/8/8/8 x - - 0 1").is_err());
+        assert!(side_to_move("8/8/8/8/8/8/8/8 w -").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn parse_piece_notation_valid() -> Result<(), ParseError> {
         let pieces =
@@ -365,7 +488,7 @@ mod tests {
         ];
 
         for (position, piece) in position_tests {
-            assert_eq!(board.get_piece(&position), piece.as_ref());
+            assert_eq!(board.piece_at(&position), piece.as_ref());
         }
 
         Ok(())
@@ -384,9 +507,20 @@ mod tests {
             parse_piece_placement("rnbq5/1p1p1pp1/7r/pBp1p2p/P2PP3/R4N2/1PP2PPP/1NBQK2R").is_err()
         );
 
+        // Piece placed after the rank is already full
+        assert!(
+            parse_piece_placement("8p/1p1p1pp1/7r/pBp1p2p/P2PP3/R4N2/1PP2PPP/1NBQK2R").is_err()
+        );
+
         // Insufficient number of ranks
         assert!(parse_piece_placement("rnbqkbn1/1p1p1pp1/7r/pBp1p2p/P2PP3/R4N2/1PP2PPP").is_err());
 
+        // Too many ranks, which used to be silently ignored
+        assert!(parse_piece_placement("8/8/8/8/8/8/8/8/8").is_err());
+        assert!(parse_piece_placement("8/8/8/8/8/8/8/8/").is_err());
+        assert!(parse_piece_placement("8/8/8/8/8/8/8/8/rnbqkbnr").is_err());
+        assert!(parse_piece_placement("////////////////").is_err());
+
         // Invalid piece notation
         assert!(
             parse_piece_placement("Xnbqkbn1/1p1p1pp1/7r/pBp1p2p/P2PP3/R4N2/1PP2PPP/1NBQK2R")
@@ -517,4 +651,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn full_moves_normalization_test() -> Result<(), ParseError> {
+        let fen = "4k3/8/8/8/8/8/8/4K3 w - - 0 0";
+        assert_eq!(parse(fen)?.get_full_moves(), 1);
+        assert_eq!(fullmove_number(fen)?, 1);
+        assert_eq!(
+            crate::fen::generate(&parse(fen)?),
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1"
+        );
+
+        let strict = ParseOptions { strict: true };
+        assert_eq!(
+            parse_with(fen, &strict).unwrap_err().to_string(),
+            "The full move number starts at 1."
+        );
+        assert!(parse_with("4k3/8/8/8/8/8/8/4K3 w - - 0 1", &strict).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn strict_counters_test() {
+        let strict = ParseOptions { strict: true };
+
+        // Black to move on move 3 means five plies have been played.
+        assert!(parse_with("4k3/8/8/8/8/8/8/4K3 b - - 5 3", &strict).is_ok());
+        assert_eq!(
+            parse_with("4k3/8/8/8/8/8/8/4K3 b - - 6 3", &strict)
+                .unwrap_err()
+                .to_string(),
+            "The half move clock is larger than the number of plies played."
+        );
+        assert!(parse("4k3/8/8/8/8/8/8/4K3 b - - 6 3").is_ok());
+
+        // Move numbers too large to count the plies of are still accepted.
+        let board = parse_with("4k3/8/8/8/8/8/8/4K3 w - - 0 4294967295", &strict).unwrap();
+        assert_eq!(board.ply(), u32::MAX);
+    }
 }
@@ -0,0 +1,160 @@
+// Short scripted games with the expected position after every ply, for tests in this crate and,
+// through the "test-util" feature, in crates built on top of it.
+
+use crate::{
+    board::MoveState,
+    game::{Game, GameResult},
+};
+
+pub struct Fixture {
+    pub name: &'static str,
+    pub moves: &'static [&'static str], // in SAN
+    pub fens: &'static [&'static str],  // the starting position, then one per move
+    pub claims_draw: bool,              // whether the last player claims the draw on offer
+    pub move_state: MoveState,
+    pub result: GameResult,
+}
+
+pub static SCHOLARS_MATE: Fixture = Fixture {
+    name: "scholars-mate",
+    moves: &["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"],
+    fens: &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+        "rnbqkbnr/pppp1ppp/8/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 2",
+        "r1bqkbnr/pppp1ppp/2n5/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 2 3",
+        "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3",
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4",
+    ],
+    claims_draw: false,
+    move_state: MoveState::Checkmate,
+    result: GameResult::WhiteWins,
+};
+
+// Both knights go out and back twice, repeating the starting position a third time.
+pub static REPETITION: Fixture = Fixture {
+    name: "repetition",
+    moves: &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"],
+    fens: &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1",
+        "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2",
+        "rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 3 2",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3",
+        "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 5 3",
+        "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 6 4",
+        "rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 7 4",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 8 5",
+    ],
+    claims_draw: true,
+    move_state: MoveState::ThreefoldRepetition,
+    result: GameResult::Draw,
+};
+
+// A pawn marches from e4 to a8, promoting with a capture.
+pub static PROMOTION: Fixture = Fixture {
+    name: "promotion",
+    moves: &[
+        "e4", "d5", "exd5", "c6", "dxc6", "Qb6", "cxb7", "Bd7", "bxa8=Q",
+    ],
+    fens: &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+        "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+        "rnbqkbnr/pp2pppp/2p5/3P4/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3",
+        "rnbqkbnr/pp2pppp/2P5/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3",
+        "rnb1kbnr/pp2pppp/1qP5/8/8/8/PPPP1PPP/RNBQKBNR w KQkq - 1 4",
+        "rnb1kbnr/pP2pppp/1q6/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 4",
+        "rn2kbnr/pP1bpppp/1q6/8/8/8/PPPP1PPP/RNBQKBNR w KQkq - 1 5",
+        "Qn2kbnr/p2bpppp/1q6/8/8/8/PPPP1PPP/RNBQKBNR b KQk - 0 5",
+    ],
+    claims_draw: false,
+    move_state: MoveState::CanMove,
+    result: GameResult::Ongoing,
+};
+
+// White castles short and black castles long.
+pub static CASTLING: Fixture = Fixture {
+    name: "castling",
+    moves: &[
+        "e4", "e5", "Nf3", "Nc6", "Bc4", "d6", "O-O", "Be6", "d3", "Qd7", "Nc3", "O-O-O",
+    ],
+    fens: &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+        "r1bqkbnr/ppp2ppp/2np4/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4",
+        "r1bqkbnr/ppp2ppp/2np4/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 4",
+        "r2qkbnr/ppp2ppp/2npb3/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 2 5",
+        "r2qkbnr/ppp2ppp/2npb3/4p3/2B1P3/3P1N2/PPP2PPP/RNBQ1RK1 b kq - 0 5",
+        "r3kbnr/pppq1ppp/2npb3/4p3/2B1P3/3P1N2/PPP2PPP/RNBQ1RK1 w kq - 1 6",
+        "r3kbnr/pppq1ppp/2npb3/4p3/2B1P3/2NP1N2/PPP2PPP/R1BQ1RK1 b kq - 2 6",
+        "2kr1bnr/pppq1ppp/2npb3/4p3/2B1P3/2NP1N2/PPP2PPP/R1BQ1RK1 w - - 3 7",
+    ],
+    claims_draw: false,
+    move_state: MoveState::CanMove,
+    result: GameResult::Ongoing,
+};
+
+pub static FIXTURES: [&Fixture; 4] = [&SCHOLARS_MATE, &REPETITION, &PROMOTION, &CASTLING];
+
+pub fn get(name: &str) -> Option<&'static Fixture> {
+    FIXTURES
+        .iter()
+        .find(|fixture| fixture.name == name)
+        .copied()
+}
+
+// Replays the named fixture from the standard position. Panics if there is no such fixture.
+pub fn play(name: &str) -> Game {
+    let fixture = get(name).unwrap_or_else(|| panic!("There is no fixture named {name}."));
+
+    let mut game = Game::from_san_moves(fixture.moves)
+        .unwrap_or_else(|error| panic!("The {name} fixture doesn't replay: {error}"));
+    if fixture.claims_draw {
+        assert!(
+            game.claim_draw(),
+            "The {name} fixture has no draw to claim."
+        );
+    }
+
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_replay_test() {
+        for fixture in FIXTURES {
+            let game = play(fixture.name);
+            assert_eq!(fixture.fens.len(), fixture.moves.len() + 1);
+
+            for (ply, fen) in fixture.fens.iter().enumerate() {
+                assert_eq!(game.fen_at(ply), Some(*fen), "{} ply {ply}", fixture.name);
+            }
+            assert_eq!(game.fen_at(fixture.fens.len()), None);
+
+            assert_eq!(
+                game.get_move_state(),
+                fixture.move_state,
+                "{}",
+                fixture.name
+            );
+            assert_eq!(game.get_result(), fixture.result, "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    fn get_test() {
+        assert_eq!(get("castling").map(|fixture| fixture.moves.len()), Some(12));
+        assert!(get("fools-mate").is_none());
+    }
+}
@@ -1,36 +1,451 @@
-use std::collections::HashMap;
+mod handle;
+
+pub use handle::{spawn, GameHandle, HandleError};
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
+};
 
 use crate::{
-    board::{self, Board, MoveError, MoveInfo, MoveRequest, MoveState, RepetitionState},
+    annotation::Annotations,
+    board::{
+        self, position::Position, Board, BoardError, MoveError, MoveInfo, MoveKind, MoveRequest,
+        MoveState, RepetitionState,
+    },
     fen,
+    notation::{self, Notation},
+    piece::{Piece, PromotionType, Side},
+    ParseError,
 };
 
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Ongoing,
+}
+
+impl GameResult {
+    pub fn to_pgn_str(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        }
+    }
+
+    pub fn from_pgn_str(result: &str) -> Option<GameResult> {
+        match result.trim() {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Ongoing),
+            _ => None,
+        }
+    }
+
+    pub fn win_for(side: &Side) -> GameResult {
+        match side {
+            Side::White => GameResult::WhiteWins,
+            Side::Black => GameResult::BlackWins,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TerminationReason {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    Timeout,
+    Agreement,
+    Repetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    Abandoned,
+}
+
+const TERMINATION_REASONS: [TerminationReason; 9] = [
+    TerminationReason::Checkmate,
+    TerminationReason::Stalemate,
+    TerminationReason::Resignation,
+    TerminationReason::Timeout,
+    TerminationReason::Agreement,
+    TerminationReason::Repetition,
+    TerminationReason::FiftyMoveRule,
+    TerminationReason::InsufficientMaterial,
+    TerminationReason::Abandoned,
+];
+
+impl TerminationReason {
+    // Values for the PGN Termination tag, which only distinguishes how the game stopped.
+    pub fn to_pgn_str(&self) -> &'static str {
+        match self {
+            TerminationReason::Timeout => "time forfeit",
+            TerminationReason::Abandoned => "abandoned",
+            _ => "normal",
+        }
+    }
+}
+
+// Whether a move takes effect as soon as it is attempted or waits for confirm_move(), like the
+// move confirmation setting on online servers.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum MoveConfirmation {
+    #[default]
+    None,
+    Confirm,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum TakebackPolicy {
+    #[default]
+    Allowed,
+    RequiresAgreement, // the opponent has to accept a request_takeback()
+    Forbidden,
+}
+
+// Which draw rules end or can end the game. Analysis boards and studies sometimes need them off,
+// for example to shuffle around a fortress. There is no automatic insufficient material draw yet,
+// so that flag has nothing to switch off for now. Touch move is the over the board rule that a
+// touched piece has to be moved if it can be, see Game::touch.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct GameRules {
+    pub enforce_fifty_move: bool, // the claimable 50 move rule and the automatic 75 move rule
+    pub enforce_repetition: bool, // threefold claims and the automatic fivefold repetition
+    pub enforce_insufficient_material: bool,
+    pub touch_move: bool,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            enforce_fifty_move: true,
+            enforce_repetition: true,
+            enforce_insufficient_material: true,
+            touch_move: false,
+        }
+    }
+}
+
+// Everything that can't be recovered from the FEN alone is captured when the ply is played,
+// so navigating back to a ply reports exactly what the state was at that point.
+#[derive(Clone, Debug)]
+struct HistoryEntry {
+    fen: String,
+    repetition_state: RepetitionState,
+    repetition_count: u32,
+    board_state: MoveState, // from the board alone, before any draw rules are applied
+    in_check: bool,
+    fifty_move_claimable: bool,
+    checks_given: (u32, u32), // by white and by black, up to and including this ply
+    is_book: bool,
+    is_unchecked: bool,  // played with force_move, so it may break the rules
+    window_start: usize, // first ply that can still repeat this position
+    request: Option<MoveRequest>, // the move that led here
+    annotations: Annotations,
+}
+
+impl HistoryEntry {
+    // The FEN doesn't know how the position was reached, so the last move is put back from the
+    // recorded request.
+    fn board(&self) -> Board {
+        let mut board = fen::parse(&self.fen).unwrap();
+        board.set_last_move(
+            self.request
+                .as_ref()
+                .map(|request| (request.start, request.end)),
+        );
+
+        board
+    }
+}
+
+// Version 2 added the rules, book, promotion and annotations lines. Version 1 files are still read, with the
+// default rules.
+const SAVE_FORMAT_VERSION: u32 = 2;
+
+type LegalMoves = HashMap<Position, HashMap<Position, MoveKind>>;
+
+// The legal moves and the board they were generated for. A Mutex rather than a RefCell so that
+// Game stays Sync.
+#[derive(Default)]
+struct LegalMovesCache(Mutex<Option<(Board, Arc<LegalMoves>)>>);
+
+impl Clone for LegalMovesCache {
+    fn clone(&self) -> LegalMovesCache {
+        let cached = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        LegalMovesCache(Mutex::new(cached.clone()))
+    }
+}
+
 #[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Format(String),
+    IllegalMove { ply: usize, error: String },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "Could not read the saved game: {error}"),
+            LoadError::Format(error) => write!(f, "{error}"),
+            LoadError::IllegalMove { ply, error } => write!(f, "Ply {ply}: {error}"),
+        }
+    }
+}
+
+// A small summary of a game at its current ply, for logging and checkpoints.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GameSnapshot {
+    pub fen: String,
+    pub ply: usize,
+    pub result: GameResult,
+    pub side_to_move: Side,
+}
+
+#[derive(Clone)]
 pub struct Game {
     board: Board,
     index: usize,
-    history: Vec<String>,
+    history: Vec<HistoryEntry>,
     repetitions: HashMap<RepetitionState, u32>,
+    base_repetitions: HashMap<RepetitionState, u32>, // carried over from before a fork
+    draw_claim: Option<(usize, MoveState)>,
+    result: Option<(GameResult, TerminationReason)>,
+    draw_offer: Option<(Side, usize)>, // offering side and the ply at which the offer expires
+    last_draw_offers: HashMap<Side, usize>,
+    draw_offer_limit: Option<usize>,
+    default_promotion: Option<PromotionType>,
+    move_confirmation: MoveConfirmation,
+    staged_move: Option<MoveRequest>,
+    takeback_policy: TakebackPolicy,
+    takeback_request: Option<(Side, usize)>, // requesting side and the ply it was requested at
+    allow_divergence: bool, // whether moving from an earlier ply replaces the moves after it
+    rules: GameRules,
+    touched: Option<(usize, Position)>, // the ply it was touched at, kept until a move is played
+    legal_moves: LegalMovesCache,
+}
+
+// Games are shared between threads behind an Arc<RwLock<Game>>.
+fn _assert_sync()
+where
+    Game: Sync,
+{
 }
 
 impl Game {
     pub fn new(board: Board) -> Game {
-        let board_fen = fen::generate(&board);
-        let repetition_state = board.get_repetition_state();
-        Game {
+        let mut game = Game {
             board,
             index: 0,
-            history: vec![board_fen],
-            repetitions: HashMap::from([(repetition_state, 1)]),
+            history: Vec::new(),
+            repetitions: HashMap::new(),
+            base_repetitions: HashMap::new(),
+            draw_claim: None,
+            result: None,
+            draw_offer: None,
+            last_draw_offers: HashMap::new(),
+            draw_offer_limit: None,
+            default_promotion: None,
+            move_confirmation: MoveConfirmation::None,
+            staged_move: None,
+            takeback_policy: TakebackPolicy::Allowed,
+            takeback_request: None,
+            allow_divergence: false,
+            rules: GameRules::default(),
+            touched: None,
+            legal_moves: LegalMovesCache::default(),
+        };
+        game.record_position(None);
+
+        game
+    }
+
+    // Like new(), but for positions that didn't come from a trusted source, such as an editor.
+    pub fn with_board(board: Board) -> Result<Game, BoardError> {
+        if !board.has_king(&Side::White) {
+            return Err(BoardError::new("White has no king."));
+        }
+        if !board.has_king(&Side::Black) {
+            return Err(BoardError::new("Black has no king."));
+        }
+
+        Ok(Game::new(board))
+    }
+
+    pub fn from_san_moves(moves: &[&str]) -> Result<Game, ParseError> {
+        let mut game = Game::new(Board::default());
+
+        for (index, san) in moves.iter().enumerate() {
+            let invalid_move = |error: &dyn std::fmt::Display| {
+                ParseError::new(&format!("Move {index} ({san}): {error}"))
+            };
+
+            let request = MoveRequest::from_san(game.board(), san).map_err(|e| invalid_move(&e))?;
+            game.attempt_move(request).map_err(|e| invalid_move(&e))?;
+        }
+
+        Ok(game)
+    }
+
+    // Plays the opening from the standard position and marks its moves as book moves.
+    pub fn with_opening(opening: &[&str]) -> Result<Game, ParseError> {
+        let mut game = Game::from_san_moves(opening)?;
+        for entry in game.history.iter_mut().skip(1) {
+            entry.is_book = true;
+        }
+
+        Ok(game)
+    }
+
+    // Replaces the circles and arrows drawn on the position at `ply`.
+    pub fn annotate_position(&mut self, ply: usize, annotations: Annotations) -> bool {
+        match self.history.get_mut(ply) {
+            Some(entry) => {
+                entry.annotations = annotations;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn annotations_at(&self, ply: usize) -> Option<&Annotations> {
+        self.history.get(ply).map(|entry| &entry.annotations)
+    }
+
+    // Checks given by `side` up to the position being viewed, for displays like "checks: 3-1".
+    pub fn checks_given(&self, side: Side) -> u32 {
+        let (white, black) = self.history[self.index].checks_given;
+        match side {
+            Side::White => white,
+            Side::Black => black,
+        }
+    }
+
+    pub fn is_book_ply(&self, ply: usize) -> bool {
+        self.history.get(ply).is_some_and(|entry| entry.is_book)
+    }
+
+    // Whether the move leading to `ply` was played with force_move rather than checked against
+    // the rules. Not to be confused with MoveInfo::is_forced, the only legal move.
+    pub fn is_unchecked_ply(&self, ply: usize) -> bool {
+        self.history
+            .get(ply)
+            .is_some_and(|entry| entry.is_unchecked)
+    }
+
+    // Starts a new game from the position being viewed. Repetitions along the path to that
+    // position still count towards the repetition draws.
+    pub fn fork(&self) -> Game {
+        let window_start = self.history[self.index].window_start;
+        let mut repetitions = HashMap::new();
+        for entry in &self.history[window_start..self.index] {
+            repetitions
+                .entry(entry.repetition_state.clone())
+                .and_modify(|v| *v += 1)
+                .or_insert(1);
+        }
+
+        let mut game = Game {
+            board: self.board.clone(),
+            index: 0,
+            history: Vec::new(),
+            repetitions: repetitions.clone(),
+            base_repetitions: repetitions,
+            draw_claim: None,
+            result: None,
+            draw_offer: None,
+            last_draw_offers: HashMap::new(),
+            draw_offer_limit: self.draw_offer_limit,
+            default_promotion: self.default_promotion,
+            move_confirmation: self.move_confirmation,
+            staged_move: None,
+            takeback_policy: self.takeback_policy,
+            takeback_request: None,
+            allow_divergence: self.allow_divergence,
+            rules: self.rules,
+            touched: None,
+            legal_moves: LegalMovesCache::default(),
+        };
+        game.record_position(None);
+
+        game
+    }
+
+    pub fn fork_full(&self) -> Game {
+        self.clone()
+    }
+
+    fn record_position(&mut self, request: Option<MoveRequest>) {
+        let repetition_state = self.board.get_repetition_state();
+
+        // Nothing from before a pawn move, a capture or lost castling rights can come back, so
+        // only the plies since the last of those are kept for comparison.
+        let ply = self.history.len();
+        let is_irreversible = self.board.get_half_moves() == 0
+            || self.history.last().is_some_and(|entry| {
+                entry.repetition_state.get_castle_rights() != self.board.get_castle_rights()
+            });
+        let window_start = match self.history.last() {
+            Some(entry) if !is_irreversible => entry.window_start,
+            _ => ply,
+        };
+        if is_irreversible {
+            self.repetitions.clear();
+        }
+
+        let repetition_count = *self
+            .repetitions
+            .entry(repetition_state.clone())
+            .and_modify(|v| *v += 1)
+            .or_insert(1);
+
+        // The side that just moved gave check, unless this is the starting position.
+        let board_state = board::get_move_state(&self.board);
+        let mut checks_given = self
+            .history
+            .last()
+            .map_or((0, 0), |entry| entry.checks_given);
+        if request.is_some() && matches!(board_state, MoveState::Check | MoveState::Checkmate) {
+            match self.board.side_to_move() {
+                Side::Black => checks_given.0 += 1,
+                Side::White => checks_given.1 += 1,
+            }
         }
+
+        let in_check = board::is_in_check(&self.board, &self.board.side_to_move());
+        let fifty_move_claimable = board::can_claim_fifty_move_rule(&self.board);
+
+        self.history.push(HistoryEntry {
+            fen: fen::generate(&self.board),
+            repetition_state,
+            repetition_count,
+            board_state,
+            in_check,
+            fifty_move_claimable,
+            checks_given,
+            is_book: false,
+            is_unchecked: false,
+            window_start,
+            request,
+            annotations: Annotations::new(),
+        });
     }
 
     pub fn next_move(&mut self) -> bool {
         if self.index + 1 < self.history.len() {
             self.index += 1;
 
-            let next_board = &self.history[self.index];
-            self.board = fen::parse(next_board).unwrap();
+            self.board = self.history[self.index].board();
 
             true
         } else {
@@ -42,8 +457,7 @@ impl Game {
         if self.index > 0 {
             self.index -= 1;
 
-            let previous_board = &self.history[self.index];
-            self.board = fen::parse(previous_board).unwrap();
+            self.board = self.history[self.index].board();
 
             true
         } else {
@@ -51,167 +465,1062 @@ impl Game {
         }
     }
 
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    #[deprecated(note = "use Game::board")]
     pub fn get_board(&self) -> &Board {
         &self.board
     }
 
+    pub fn board_at(&self, ply: usize) -> Option<Board> {
+        if ply == self.index {
+            return Some(self.board.clone());
+        }
+
+        self.history.get(ply).map(HistoryEntry::board)
+    }
+
+    pub fn fen_at(&self, ply: usize) -> Option<&str> {
+        self.history.get(ply).map(|entry| entry.fen.as_str())
+    }
+
+    // The move that led to `ply`, None for the starting position.
+    pub fn move_at(&self, ply: usize) -> Option<&MoveRequest> {
+        self.history.get(ply)?.request.as_ref()
+    }
+
+    // The SAN move list in numbered pairs, e.g. "1. e4 e5\n2. Nf3 Nc6". Each move is padded to
+    // `width` so the columns line up, and the move that led to the viewed ply is marked with '>'.
+    pub fn format_move_list(&self, width: usize) -> String {
+        let start = self.history[0].board();
+        let mut move_number = start.get_full_moves();
+        let number_width = (move_number as usize + self.history.len() / 2)
+            .to_string()
+            .len();
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        if start.side_to_move() == Side::Black && self.history.len() > 1 {
+            line = format!("{move_number:>number_width$}.  {:<width$}", "...");
+        }
+
+        for ply in 1..self.history.len() {
+            let previous = self.history[ply - 1].board();
+            let request = self.history[ply].request.as_ref().unwrap();
+            // An unchecked move may not be legal, so there is no SAN to give for it.
+            let san = if self.history[ply].is_unchecked {
+                request.to_coordinate()
+            } else {
+                notation::render_move(&previous, request, Notation::San).unwrap()
+            };
+            let marker = if ply == self.index { '>' } else { ' ' };
+
+            match previous.side_to_move() {
+                Side::White => {
+                    line = format!("{move_number:>number_width$}. {marker}{san:<width$}")
+                }
+                Side::Black => {
+                    line.push_str(&format!(" {marker}{san:<width$}"));
+                    lines.push(line.trim_end().to_string());
+                    line = String::new();
+                    move_number += 1;
+                }
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line.trim_end().to_string());
+        }
+
+        lines.join("\n")
+    }
+
+    // Plies where the same position occurred, ignoring the move counters.
+    pub fn find_position(&self, board: &Board) -> Vec<usize> {
+        let repetition_state = board.get_repetition_state();
+        self.history
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.repetition_state == repetition_state)
+            .map(|(ply, _)| ply)
+            .collect()
+    }
+
+    pub fn default_promotion(&self) -> Option<PromotionType> {
+        self.default_promotion
+    }
+
+    #[deprecated(note = "use Game::default_promotion")]
+    pub fn get_default_promotion(&self) -> &Option<PromotionType> {
+        &self.default_promotion
+    }
+
+    pub fn set_default_promotion(&mut self, default_promotion: Option<PromotionType>) {
+        self.default_promotion = default_promotion;
+    }
+
+    // With move confirmation on, the move is only checked and staged, and the returned info
+    // describes what confirm_move() will play. Attempting another move replaces the staged one.
     pub fn attempt_move(&mut self, request: MoveRequest) -> Result<MoveInfo, MoveError> {
-        let move_state = self.get_move_state();
-        if move_state == MoveState::Checkmate || move_state == MoveState::Stalemate {
-            return Err(MoveError::new("Game is over."));
+        match self.move_confirmation {
+            MoveConfirmation::None => self.play_move(request),
+            MoveConfirmation::Confirm => {
+                let move_info = self.preview_move(request.clone())?;
+
+                self.staged_move = Some(request);
+
+                Ok(move_info)
+            }
         }
+    }
 
-        let all_legal_moves =
-            board::get_all_legal_moves(&self.board, self.board.get_current_turn());
+    // What attempt_move would return for the move, without playing or staging it.
+    pub fn preview_move(&self, request: MoveRequest) -> Result<MoveInfo, MoveError> {
+        let mut preview = self.clone();
+        preview.move_confirmation = MoveConfirmation::None;
+        preview.play_move(request)
+    }
 
-        let valid_move = all_legal_moves
-            .get(&request.start)
-            .map_or(false, |piece_moves| piece_moves.get(&request.end).is_some());
-        if !valid_move {
-            return Err(MoveError::new("Invalid move."));
+    pub fn staged_move(&self) -> Option<&MoveRequest> {
+        self.staged_move.as_ref()
+    }
+
+    pub fn confirm_move(&mut self) -> Result<MoveInfo, MoveError> {
+        let request = self.staged_move.take().ok_or(MoveError::InvalidMove)?;
+        self.play_move(request)
+    }
+
+    pub fn cancel_staged_move(&mut self) -> bool {
+        self.staged_move.take().is_some()
+    }
+
+    pub fn get_move_confirmation(&self) -> MoveConfirmation {
+        self.move_confirmation
+    }
+
+    pub fn set_move_confirmation(&mut self, move_confirmation: MoveConfirmation) {
+        self.move_confirmation = move_confirmation;
+        self.staged_move = None;
+    }
+
+    fn play_move(&mut self, mut request: MoveRequest) -> Result<MoveInfo, MoveError> {
+        self.check_divergence()?;
+        if self.result.is_some() || self.get_move_state().is_game_over() {
+            return Err(MoveError::GameOver);
         }
 
-        // Calculate if we need to do any move disambiguation before we change the state of the board.
-        let mut rank_disambiguation = false;
-        let mut file_disambiguation = false;
-        let moving_piece = self.board.get_piece(&request.start).unwrap();
-        for (piece_position, moves) in all_legal_moves {
-            if piece_position != request.start {
-                let piece = self.board.get_piece(&piece_position).unwrap();
-                if piece.piece_type == moving_piece.piece_type && moves.contains_key(&request.end) {
-                    if piece_position.file() == request.start.file() {
-                        rank_disambiguation = true;
-                    }
+        match self.board.piece_at(&request.start) {
+            None => {
+                return Err(MoveError::EmptySquare {
+                    position: request.start,
+                })
+            }
+            Some(piece) if piece.side != self.board.side_to_move() => {
+                return Err(MoveError::NotYourPiece {
+                    position: request.start,
+                    piece: *piece,
+                })
+            }
+            Some(_) => (),
+        }
 
-                    if piece_position.rank() == request.start.rank() {
-                        file_disambiguation = true;
-                    }
-                }
+        if let Some(touched) = self.touched_piece() {
+            if touched != request.start && !self.legal_destinations(&touched).is_empty() {
+                return Err(MoveError::TouchedPiece { position: touched });
             }
         }
 
+        let all_legal_moves = self.all_legal_moves();
+
+        let move_kind = all_legal_moves
+            .get(&request.start)
+            .and_then(|piece_moves| piece_moves.get(&request.end));
+        match move_kind {
+            Some(MoveKind::Promotion(_)) if request.promotion.is_none() => {
+                request.promotion = self.default_promotion;
+            }
+            Some(_) => (),
+            None => return Err(MoveError::InvalidMove),
+        }
+
+        // Promotions are never forced since each promotion type is a separate move.
+        let legal_move_count: usize = all_legal_moves.values().map(HashMap::len).sum();
+        let is_forced = legal_move_count == 1 && request.promotion.is_none();
+
+        // Calculate if we need to do any move disambiguation before we change the state of the board.
+        let (file_disambiguation, rank_disambiguation) =
+            board::get_disambiguation(&self.board, &all_legal_moves, &request);
+
+        let played = request.clone();
         let mut move_info = board::move_piece(&mut self.board, request)?;
-        move_info.move_state = Some(self.get_move_state());
         move_info.rank_disambiguation = rank_disambiguation;
         move_info.file_disambiguation = file_disambiguation;
+        move_info.is_forced = is_forced;
 
         // Add the new board state to the top of the stack
-        let new_fen = fen::generate(&self.board);
+        self.truncate_future();
+        self.record_position(Some(played));
+        self.index += 1;
+        self.touched = None;
 
-        // If a move is attempted while pointing to an older board state, delete the
-        // future states because the user has changed history.
-        let current_length = self.index + 1;
-        if current_length < self.history.len() {
-            self.history.resize(current_length, String::new());
+        move_info.move_state = Some(self.get_move_state());
+
+        Ok(move_info)
+    }
+
+    // Plays a move without checking it against the rules, see Board::force_move. It is recorded
+    // in the history like any other move, but flagged so it is shown in coordinate notation.
+    pub fn force_move(&mut self, request: MoveRequest) -> Result<Option<Piece>, MoveError> {
+        self.check_divergence()?;
+        if self.board.piece_at(&request.start).is_none() {
+            return Err(MoveError::EmptySquare {
+                position: request.start,
+            });
         }
 
-        self.history.push(new_fen);
+        let captured = self
+            .board
+            .force_move(&request.start, &request.end, request.promotion);
+
+        self.staged_move = None;
+        self.truncate_future();
+        self.record_position(Some(request));
+        self.history.last_mut().unwrap().is_unchecked = true;
         self.index += 1;
+        self.touched = None;
 
-        let repetition_state = self.board.get_repetition_state();
-        self.repetitions
-            .entry(repetition_state)
-            .and_modify(|v| *v += 1)
-            .or_insert(1);
+        Ok(captured)
+    }
 
-        Ok(move_info)
+    pub fn get_allow_divergence(&self) -> bool {
+        self.allow_divergence
     }
 
-    pub fn get_move_state(&self) -> MoveState {
-        let mut stalemate_by_repetition = false;
-        for repetition_count in self.repetitions.values() {
-            if *repetition_count >= 3 {
-                stalemate_by_repetition = true;
-                break;
-            }
-        }
+    // Off by default, so a move attempted while looking back through the game is refused
+    // instead of quietly throwing away the moves after the viewed ply.
+    pub fn set_allow_divergence(&mut self, allow_divergence: bool) {
+        self.allow_divergence = allow_divergence;
+    }
 
-        if stalemate_by_repetition {
-            MoveState::Stalemate
-        } else {
-            board::get_move_state(&self.board)
+    fn is_at_latest_position(&self) -> bool {
+        self.index + 1 == self.history.len()
+    }
+
+    fn check_divergence(&self) -> Result<(), MoveError> {
+        if !self.is_at_latest_position() && !self.allow_divergence {
+            return Err(MoveError::NotLatestPosition);
         }
+
+        Ok(())
     }
 
-    pub fn get_white_score(&self) -> i32 {
-        let mut score = 0;
-        for position in self.board.get_white_positions() {
-            if let Some(piece) = self.board.get_piece(position) {
-                score += piece.piece_type.value();
-            }
+    // If a move is played while pointing to an older board state, delete the future states
+    // because the user has changed history.
+    fn truncate_future(&mut self) {
+        let current_length = self.index + 1;
+        if current_length < self.history.len() {
+            self.history.truncate(current_length);
+            self.draw_claim = None;
+            self.draw_offer = None;
+            self.recount_repetitions();
         }
+    }
 
-        score
+    pub fn get_move_state(&self) -> MoveState {
+        match &self.draw_claim {
+            Some((index, claimed_state)) if *index == self.index => claimed_state.clone(),
+            _ => self.move_state_at(self.index),
+        }
     }
 
-    pub fn get_black_score(&self) -> i32 {
-        let mut score = 0;
-        for position in self.board.get_black_positions() {
-            if let Some(piece) = self.board.get_piece(position) {
-                score += piece.piece_type.value();
+    // The draw rules are applied when asked rather than when the ply is recorded, so changing
+    // them mid-game affects every ply.
+    fn move_state_at(&self, index: usize) -> MoveState {
+        let entry = &self.history[index];
+        let board_state = match &entry.board_state {
+            MoveState::SeventyFiveMoveRule if !self.rules.enforce_fifty_move => {
+                if entry.in_check {
+                    MoveState::Check
+                } else {
+                    MoveState::CanMove
+                }
             }
+            board_state => board_state.clone(),
+        };
+
+        // A checkmate or stalemate takes priority over any draw rule reached on the same move.
+        if self.rules.enforce_repetition
+            && !board_state.is_game_over()
+            && entry.repetition_count >= 5
+        {
+            MoveState::FivefoldRepetition
+        } else {
+            board_state
         }
+    }
 
-        score
+    pub fn get_rules(&self) -> GameRules {
+        self.rules
     }
-}
 
-#[cfg(test)]
-mod test {
-    use board::position::Position;
+    pub fn set_rules(&mut self, rules: GameRules) {
+        self.rules = rules;
+    }
 
-    use crate::{piece::PromotionType, ParseError};
+    fn can_claim_repetition(&self) -> bool {
+        self.rules.enforce_repetition && self.get_repetition_count() >= 3
+    }
 
-    use super::*;
+    // The ply of the last pawn move, capture or castling rights change, before which no position
+    // can repeat.
+    pub fn last_irreversible_ply(&self) -> usize {
+        self.history[self.index].window_start
+    }
 
-    #[test]
-    fn test_normal_pawn_move_notation() -> Result<(), ParseError> {
-        // Move forward
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1p1ppp/8/2p1p3/3P4/P7/1PP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
-            let mut game = Game::new(board);
+    pub fn get_repetition_count(&self) -> u32 {
+        self.history[self.index].repetition_count
+    }
 
-            let request = MoveRequest::new(Position::d4(), Position::d5());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "d5".to_string());
-        }
+    // Only the latest position can be claimed, a claim further back would end the game at a ply
+    // the game has already moved on from.
+    pub fn can_claim_draw(&self) -> bool {
+        let entry = &self.history[self.index];
+        let fifty_move_claimable = self.rules.enforce_fifty_move && entry.fifty_move_claimable;
 
-        // Capture left
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1p1ppp/8/2p1p3/3P4/P7/1PP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
-            let mut game = Game::new(board);
+        self.is_at_latest_position()
+            && (self.can_claim_repetition() || fifty_move_claimable)
+            && !self.get_move_state().is_game_over()
+    }
 
-            let request = MoveRequest::new(Position::d4(), Position::c5());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "dxc5".to_string());
+    pub fn claim_draw(&mut self) -> bool {
+        if !self.can_claim_draw() {
+            return false;
         }
 
-        // Capture right
-        {
-            let board =
-                fen::parse("rnbqkbnr/pp1p1ppp/8/2p1p3/3P4/P7/1PP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
-            let mut game = Game::new(board);
+        let claimed_state = if self.can_claim_repetition() {
+            MoveState::ThreefoldRepetition
+        } else {
+            MoveState::FiftyMoveRule
+        };
 
-            let request = MoveRequest::new(Position::d4(), Position::e5());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "dxe5".to_string());
-        }
+        self.draw_claim = Some((self.index, claimed_state));
 
-        Ok(())
+        true
     }
 
-    #[test]
-    fn test_pawn_promotion() -> Result<(), ParseError> {
-        // Promotion to Queen
-        {
-            let board =
-                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
-            let mut game = Game::new(board);
+    pub fn legal_captures(&self) -> Vec<MoveRequest> {
+        self.legal_move_requests()
+            .into_iter()
+            .filter(|(_, move_kind)| {
+                matches!(
+                    move_kind,
+                    MoveKind::Capture | MoveKind::EnPassant(_) | MoveKind::Promotion(true)
+                )
+            })
+            .map(|(request, _)| request)
+            .collect()
+    }
+
+    pub fn legal_checks(&self) -> Vec<MoveRequest> {
+        let opponent = self.board.side_to_move().opponent();
+
+        self.legal_move_requests()
+            .into_iter()
+            .map(|(request, _)| request)
+            .filter(|request| {
+                let mut new_board = self.board.clone();
+                board::move_piece(&mut new_board, request.clone()).is_ok()
+                    && board::is_in_check(&new_board, &opponent)
+            })
+            .collect()
+    }
+
+    // Each promotion choice is returned as its own request, ordered by start and end square.
+    fn legal_move_requests(&self) -> Vec<(MoveRequest, MoveKind)> {
+        let mut requests = Vec::new();
+        for (start, piece_moves) in self.all_legal_moves().iter() {
+            for (end, move_kind) in piece_moves {
+                let (start, end, move_kind) = (*start, *end, move_kind.clone());
+                if let MoveKind::Promotion(_) = move_kind {
+                    for promotion_type in [
+                        PromotionType::Queen,
+                        PromotionType::Rook,
+                        PromotionType::Bishop,
+                        PromotionType::Knight,
+                    ] {
+                        let request = MoveRequest::promotion(start, end, promotion_type);
+                        requests.push((request, move_kind.clone()));
+                    }
+                } else {
+                    requests.push((MoveRequest::new(start, end), move_kind));
+                }
+            }
+        }
+
+        requests.sort_by_key(|(request, _)| (request.start.value(), request.end.value()));
+
+        requests
+    }
+
+    // Selects the piece on `position` and returns the squares it can legally move to. With the
+    // touch move rule on, the piece then has to be the one moved this turn unless it has no legal
+    // move, and touching a different piece is refused. Without the rule nothing is recorded, and
+    // neither is a touch while looking back at an earlier ply. Going back and forth doesn't let
+    // go of a touch, only playing a move does.
+    pub fn touch(&mut self, position: &Position) -> Result<Vec<Position>, MoveError> {
+        match self.board.piece_at(position) {
+            None => {
+                return Err(MoveError::EmptySquare {
+                    position: *position,
+                })
+            }
+            Some(piece) if piece.side != self.board.side_to_move() => {
+                return Err(MoveError::NotYourPiece {
+                    position: *position,
+                    piece: *piece,
+                })
+            }
+            Some(_) => (),
+        }
+
+        if let Some(touched) = self.touched_piece() {
+            if touched != *position && !self.legal_destinations(&touched).is_empty() {
+                return Err(MoveError::TouchedPiece { position: touched });
+            }
+        }
+
+        if self.rules.touch_move && self.is_at_latest_position() {
+            self.touched = Some((self.index, *position));
+        }
+
+        Ok(self.legal_destinations(position))
+    }
+
+    // Lets go of the touched piece, which the rule only allows if it has no legal move.
+    pub fn release_touch(&mut self) -> bool {
+        match self.touched_piece() {
+            Some(touched) if !self.legal_destinations(&touched).is_empty() => false,
+            Some(_) => {
+                self.touched = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    // The touch only applies at the ply it was made at.
+    fn touched_piece(&self) -> Option<Position> {
+        match self.touched {
+            Some((ply, position)) if self.rules.touch_move && ply == self.index => Some(position),
+            _ => None,
+        }
+    }
+
+    // The squares the piece on `position` can legally move to, in square order.
+    pub fn legal_destinations(&self, position: &Position) -> Vec<Position> {
+        let mut destinations: Vec<Position> = self
+            .all_legal_moves()
+            .get(position)
+            .map(|piece_moves| piece_moves.keys().copied().collect())
+            .unwrap_or_default();
+        destinations.sort_by_key(Position::value);
+
+        destinations
+    }
+
+    // Every legal move in the current position, with each promotion choice as its own request.
+    pub fn legal_moves(&self) -> Vec<MoveRequest> {
+        self.legal_move_requests()
+            .into_iter()
+            .map(|(request, _)| request)
+            .collect()
+    }
+
+    // A promotion is only legal with a promotion piece, and any other move only without one.
+    pub fn is_legal_move(&self, request: &MoveRequest) -> bool {
+        let all_legal_moves = self.all_legal_moves();
+        let move_kind = all_legal_moves
+            .get(&request.start)
+            .and_then(|piece_moves| piece_moves.get(&request.end));
+
+        match move_kind {
+            Some(MoveKind::Promotion(_)) => request.promotion.is_some(),
+            Some(_) => request.promotion.is_none(),
+            None => false,
+        }
+    }
+
+    // UIs ask about one piece after another in the same position, so the legal moves are
+    // generated once and kept until the board changes. The cache is checked against the board
+    // itself, so it doesn't matter how the board came to change.
+    fn all_legal_moves(&self) -> Arc<LegalMoves> {
+        let mut cache = self
+            .legal_moves
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match cache.as_ref() {
+            Some((board, legal_moves)) if *board == self.board => Arc::clone(legal_moves),
+            _ => {
+                let legal_moves = Arc::new(board::get_all_legal_moves(
+                    &self.board,
+                    &self.board.side_to_move(),
+                ));
+                *cache = Some((self.board.clone(), Arc::clone(&legal_moves)));
+
+                legal_moves
+            }
+        }
+    }
+
+    pub fn get_takeback_policy(&self) -> TakebackPolicy {
+        self.takeback_policy
+    }
+
+    pub fn set_takeback_policy(&mut self, takeback_policy: TakebackPolicy) {
+        self.takeback_policy = takeback_policy;
+        self.takeback_request = None;
+    }
+
+    // Takes back `side`'s last move, along with the opponent's reply if there was one. Only
+    // possible without asking when the policy allows takebacks outright.
+    pub fn take_back(&mut self, side: Side) -> bool {
+        self.takeback_policy == TakebackPolicy::Allowed && self.remove_last_move_of(side)
+    }
+
+    pub fn request_takeback(&mut self, side: Side) -> bool {
+        if self.takeback_policy != TakebackPolicy::RequiresAgreement
+            || self.pending_takeback_request().is_some()
+            || self.plies_to_take_back(side).is_none()
+        {
+            return false;
+        }
+
+        self.takeback_request = Some((side, self.history.len() - 1));
+
+        true
+    }
+
+    // The request lapses as soon as another move is played.
+    pub fn pending_takeback_request(&self) -> Option<Side> {
+        match &self.takeback_request {
+            Some((side, requested_at)) if *requested_at == self.history.len() - 1 => Some(*side),
+            _ => None,
+        }
+    }
+
+    pub fn accept_takeback(&mut self) -> bool {
+        match self.pending_takeback_request() {
+            Some(side) => self.remove_last_move_of(side),
+            None => false,
+        }
+    }
+
+    pub fn decline_takeback(&mut self) -> bool {
+        if self.pending_takeback_request().is_none() {
+            return false;
+        }
+
+        self.takeback_request = None;
+
+        true
+    }
+
+    fn plies_to_take_back(&self, side: Side) -> Option<usize> {
+        if self.get_result() != GameResult::Ongoing {
+            return None;
+        }
+
+        let last_ply = self.history.len() - 1;
+        let side_to_move = fen::side_to_move(&self.history[last_ply].fen).ok()?;
+        let plies = if side_to_move == side { 2 } else { 1 };
+
+        (plies <= last_ply).then_some(plies)
+    }
+
+    fn remove_last_move_of(&mut self, side: Side) -> bool {
+        let Some(plies) = self.plies_to_take_back(side) else {
+            return false;
+        };
+
+        self.history.truncate(self.history.len() - plies);
+        self.index = self.history.len() - 1;
+        self.touched = None;
+        self.board = self.history[self.index].board();
+
+        self.draw_claim = None;
+        self.draw_offer = None;
+        self.takeback_request = None;
+        self.staged_move = None;
+        self.recount_repetitions();
+
+        true
+    }
+
+    // The counts carried over by fork() still apply until an irreversible move moves the window
+    // past the first ply.
+    fn recount_repetitions(&mut self) {
+        let window_start = self.history[self.history.len() - 1].window_start;
+        self.repetitions = if window_start == 0 {
+            self.base_repetitions.clone()
+        } else {
+            HashMap::new()
+        };
+        for entry in &self.history[window_start..] {
+            self.repetitions
+                .entry(entry.repetition_state.clone())
+                .and_modify(|v| *v += 1)
+                .or_insert(1);
+        }
+    }
+
+    pub fn get_draw_offer_limit(&self) -> Option<usize> {
+        self.draw_offer_limit
+    }
+
+    // The minimum number of plies between two draw offers from the same side.
+    pub fn set_draw_offer_limit(&mut self, draw_offer_limit: Option<usize>) {
+        self.draw_offer_limit = draw_offer_limit;
+    }
+
+    pub fn offer_draw(&mut self, side: Side) -> bool {
+        if self.get_result() != GameResult::Ongoing || self.pending_draw_offer().is_some() {
+            return false;
+        }
+
+        let last_ply = self.history.len() - 1;
+        if let (Some(limit), Some(previous_offer)) =
+            (self.draw_offer_limit, self.last_draw_offers.get(&side))
+        {
+            if last_ply < previous_offer + limit {
+                return false;
+            }
+        }
+
+        // The offer stands until the opponent has made their next move.
+        let plies_ahead = last_ply - self.index;
+        let side_to_move = if plies_ahead.is_multiple_of(2) {
+            self.board.side_to_move()
+        } else {
+            self.board.side_to_move().opponent()
+        };
+        let expires_at = if side_to_move == side {
+            last_ply + 2
+        } else {
+            last_ply + 1
+        };
+
+        self.last_draw_offers.insert(side, last_ply);
+        self.draw_offer = Some((side, expires_at));
+
+        true
+    }
+
+    pub fn pending_draw_offer(&self) -> Option<Side> {
+        match &self.draw_offer {
+            Some((side, expires_at)) if self.history.len() - 1 < *expires_at => Some(*side),
+            _ => None,
+        }
+    }
+
+    pub fn accept_draw(&mut self) -> bool {
+        if self.pending_draw_offer().is_none()
+            || !self.set_result(GameResult::Draw, TerminationReason::Agreement)
+        {
+            return false;
+        }
+
+        self.draw_offer = None;
+
+        true
+    }
+
+    // Records a result decided outside the rules of play, such as a resignation or a flag fall.
+    pub fn set_result(&mut self, result: GameResult, reason: TerminationReason) -> bool {
+        if result == GameResult::Ongoing || self.get_result() != GameResult::Ongoing {
+            return false;
+        }
+
+        self.result = Some((result, reason));
+
+        true
+    }
+
+    pub fn get_result(&self) -> GameResult {
+        if let Some((result, _)) = &self.result {
+            return result.clone();
+        }
+
+        match self.get_final_move_state() {
+            MoveState::Checkmate => {
+                let last_board = fen::parse(&self.history[self.history.len() - 1].fen).unwrap();
+                GameResult::win_for(&last_board.side_to_move().opponent())
+            }
+            MoveState::CanMove | MoveState::Check | MoveState::Invalid => GameResult::Ongoing,
+            _ => GameResult::Draw,
+        }
+    }
+
+    pub fn get_termination_reason(&self) -> Option<TerminationReason> {
+        if let Some((_, reason)) = &self.result {
+            return Some(reason.clone());
+        }
+
+        match self.get_final_move_state() {
+            MoveState::CanMove | MoveState::Check | MoveState::Invalid => None,
+            MoveState::Checkmate => Some(TerminationReason::Checkmate),
+            MoveState::Stalemate => Some(TerminationReason::Stalemate),
+            MoveState::FiftyMoveRule | MoveState::SeventyFiveMoveRule => {
+                Some(TerminationReason::FiftyMoveRule)
+            }
+            MoveState::ThreefoldRepetition | MoveState::FivefoldRepetition => {
+                Some(TerminationReason::Repetition)
+            }
+        }
+    }
+
+    // The result belongs to the whole game, so it is read from the last ply rather than the
+    // one currently being viewed.
+    fn get_final_move_state(&self) -> MoveState {
+        let last_index = self.history.len() - 1;
+        match &self.draw_claim {
+            Some((index, claimed_state)) if *index == last_index => claimed_state.clone(),
+            _ => self.move_state_at(last_index),
+        }
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            fen: self.history[self.index].fen.clone(),
+            ply: self.index,
+            result: self.get_result(),
+            side_to_move: self.board.side_to_move(),
+        }
+    }
+
+    // Writes the game as "key value" lines after a version header. Unlike PGN this keeps the ply
+    // being viewed, the rules, the book moves, the annotations and any result or draw claim that
+    // was recorded.
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        let moves: Vec<String> = self
+            .history
+            .iter()
+            .filter_map(|entry| {
+                let request = entry.request.as_ref()?;
+                let suffix = if entry.is_unchecked { "!" } else { "" };
+                Some(format!("{}{suffix}", request.to_coordinate()))
+            })
+            .collect();
+
+        writeln!(writer, "chess-game {SAVE_FORMAT_VERSION}")?;
+        writeln!(writer, "fen {}", self.history[0].fen)?;
+        writeln!(writer, "moves {}", moves.join(" "))?;
+        writeln!(writer, "index {}", self.index)?;
+        let rules: Vec<&str> = [
+            (self.rules.enforce_fifty_move, "fifty-move"),
+            (self.rules.enforce_repetition, "repetition"),
+            (
+                self.rules.enforce_insufficient_material,
+                "insufficient-material",
+            ),
+            (self.rules.touch_move, "touch-move"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+        writeln!(writer, "rules {}", rules.join(" "))?;
+        let book_plies: Vec<String> = (1..self.history.len())
+            .filter(|ply| self.history[*ply].is_book)
+            .map(|ply| ply.to_string())
+            .collect();
+        if !book_plies.is_empty() {
+            writeln!(writer, "book {}", book_plies.join(" "))?;
+        }
+        if let Some(promotion) = &self.default_promotion {
+            let promotion = promotion.to_algebraic().to_ascii_lowercase();
+            writeln!(writer, "promotion {promotion}")?;
+        }
+        for (ply, entry) in self.history.iter().enumerate() {
+            if !entry.annotations.is_empty() {
+                writeln!(
+                    writer,
+                    "annotations {ply} {}",
+                    entry.annotations.to_comment()
+                )?;
+            }
+        }
+        if let Some((ply, _)) = &self.draw_claim {
+            writeln!(writer, "claim {ply}")?;
+        }
+        if let Some((result, reason)) = &self.result {
+            writeln!(writer, "result {} {reason:?}", result.to_pgn_str())?;
+        }
+
+        Ok(())
+    }
+
+    // Reads a game written by save, replaying every move so a corrupted file is rejected.
+    pub fn load(mut reader: impl Read) -> Result<Game, LoadError> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .map_err(LoadError::Io)?;
+
+        let format_error = |error: &str| LoadError::Format(String::from(error));
+
+        let mut lines = contents.lines();
+        let version = lines
+            .next()
+            .and_then(|header| header.strip_prefix("chess-game "))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or(format_error("Missing the saved game header."))?;
+        if version == 0 || version > SAVE_FORMAT_VERSION {
+            return Err(format_error(&format!(
+                "Unsupported saved game version {version}."
+            )));
+        }
+
+        // Every key appears once, except annotations which get a line per annotated ply.
+        let mut fields: HashMap<&str, &str> = HashMap::new();
+        let mut annotation_lines = Vec::new();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            match line.split_once(' ').unwrap_or((line, "")) {
+                ("annotations", value) => annotation_lines.push(value),
+                (key, value) => {
+                    fields.insert(key, value);
+                }
+            }
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or(format_error(&format!("Missing the {key} field.")))
+        };
+        let ply_field = |key: &str| {
+            field(key)?
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format_error(&format!("Invalid {key} field.")))
+        };
+
+        let board = fen::parse(field("fen")?).map_err(|error| format_error(&error.to_string()))?;
+        let mut game = Game::new(board);
+
+        // The rules decide which moves are still playable, so they are set before replaying.
+        if let Some(names) = fields.get("rules") {
+            let mut rules = GameRules {
+                enforce_fifty_move: false,
+                enforce_repetition: false,
+                enforce_insufficient_material: false,
+                touch_move: false,
+            };
+            for name in names.split_whitespace() {
+                match name {
+                    "fifty-move" => rules.enforce_fifty_move = true,
+                    "repetition" => rules.enforce_repetition = true,
+                    "insufficient-material" => rules.enforce_insufficient_material = true,
+                    "touch-move" => rules.touch_move = true,
+                    _ => return Err(format_error(&format!("Unknown rule {name}."))),
+                }
+            }
+            game.set_rules(rules);
+        }
+
+        if let Some(promotion) = fields.get("promotion") {
+            let mut chars = promotion.trim().chars();
+            let promotion = match (chars.next(), chars.next()) {
+                (Some(notation), None) => PromotionType::from_coordinate(notation),
+                _ => None,
+            };
+            game.set_default_promotion(Some(
+                promotion.ok_or(format_error("Invalid promotion field."))?,
+            ));
+        }
+
+        for (index, notation) in field("moves")?.split_whitespace().enumerate() {
+            let illegal_move = |error: &dyn std::fmt::Display| LoadError::IllegalMove {
+                ply: index + 1,
+                error: error.to_string(),
+            };
+
+            // Unchecked moves are marked with a trailing '!' and replayed with force_move.
+            let (notation, is_unchecked) = match notation.strip_suffix('!') {
+                Some(notation) => (notation, true),
+                None => (notation, false),
+            };
+
+            let request = MoveRequest::from_coordinate(notation).map_err(|e| illegal_move(&e))?;
+            if is_unchecked {
+                game.force_move(request).map_err(|e| illegal_move(&e))?;
+            } else {
+                game.attempt_move(request).map_err(|e| illegal_move(&e))?;
+            }
+        }
+
+        if let Some(book_plies) = fields.get("book") {
+            for ply in book_plies.split_whitespace() {
+                let entry = ply
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|ply| *ply > 0)
+                    .and_then(|ply| game.history.get_mut(ply))
+                    .ok_or(format_error(&format!("Invalid book ply {ply}.")))?;
+                entry.is_book = true;
+            }
+        }
+
+        for line in annotation_lines {
+            let invalid_annotations = || format_error(&format!("Invalid annotations {line}."));
+            let (ply, comment) = line.split_once(' ').ok_or_else(invalid_annotations)?;
+            let ply = ply.parse::<usize>().map_err(|_| invalid_annotations())?;
+            let annotations =
+                Annotations::from_comment(comment).map_err(|_| invalid_annotations())?;
+            if !game.annotate_position(ply, annotations) {
+                return Err(invalid_annotations());
+            }
+        }
+
+        if fields.contains_key("claim") {
+            let ply = ply_field("claim")?;
+            if !game.go_to_ply(ply) || !game.claim_draw() {
+                return Err(format_error(&format!(
+                    "No draw can be claimed at ply {ply}."
+                )));
+            }
+        }
+
+        if let Some(result) = fields.get("result") {
+            let (result, reason) = result
+                .split_once(' ')
+                .ok_or(format_error("Invalid result field."))?;
+            let result =
+                GameResult::from_pgn_str(result).ok_or(format_error("Invalid result field."))?;
+            let reason = TERMINATION_REASONS
+                .into_iter()
+                .find(|known| format!("{known:?}") == reason.trim())
+                .ok_or(format_error("Invalid termination reason."))?;
+            if !game.set_result(result, reason) {
+                return Err(format_error("The result conflicts with the moves."));
+            }
+        }
+
+        let index = ply_field("index")?;
+        if !game.go_to_ply(index) {
+            return Err(format_error(&format!(
+                "Index {index} is past the last move."
+            )));
+        }
+
+        Ok(game)
+    }
+
+    fn go_to_ply(&mut self, ply: usize) -> bool {
+        let Some(entry) = self.history.get(ply) else {
+            return false;
+        };
+
+        self.board = entry.board();
+        self.index = ply;
+
+        true
+    }
+
+    pub fn get_white_score(&self) -> i32 {
+        let mut score = 0;
+        for position in self.board.get_white_positions() {
+            if let Some(piece) = self.board.piece_at(&position) {
+                score += piece.piece_type.value();
+            }
+        }
+
+        score
+    }
+
+    pub fn get_black_score(&self) -> i32 {
+        let mut score = 0;
+        for position in self.board.get_black_positions() {
+            if let Some(piece) = self.board.piece_at(&position) {
+                score += piece.piece_type.value();
+            }
+        }
+
+        score
+    }
+}
+
+// The full history makes the derived output unreadable, so only the current position is shown.
+impl std::fmt::Debug for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Game")
+            .field("fen", &self.history[self.index].fen)
+            .field("ply", &self.index)
+            .field("state", &self.get_move_state())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use board::position::Position;
+
+    use crate::{
+        annotation::Color,
+        assert_boards_eq,
+        piece::{Piece, PieceType},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_normal_pawn_move_notation() -> Result<(), ParseError> {
+        // Move forward
+        {
+            let board =
+                fen::parse("rnbqkbnr/pp1p1ppp/8/2p1p3/3P4/P7/1PP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d4(), Position::d5());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "d5".to_string());
+        }
+
+        // Capture left
+        {
+            let board =
+                fen::parse("rnbqkbnr/pp1p1ppp/8/2p1p3/3P4/P7/1PP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d4(), Position::c5());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "dxc5".to_string());
+        }
+
+        // Capture right
+        {
+            let board =
+                fen::parse("rnbqkbnr/pp1p1ppp/8/2p1p3/3P4/P7/1PP1PPPP/RNBQKBNR w KQkq e6 0 3")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d4(), Position::e5());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "dxe5".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pawn_promotion() -> Result<(), ParseError> {
+        // Promotion to Queen
+        {
+            let board =
+                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let mut game = Game::new(board);
 
             let request =
                 MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Queen);
@@ -233,278 +1542,1736 @@ mod test {
             assert_eq!(notation, "b8=N".to_string());
         }
 
-        // Promotion to Rook
+        // Promotion to Rook
+        {
+            let board =
+                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let mut game = Game::new(board);
+
+            let request =
+                MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Rook);
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "b8=R".to_string());
+        }
+
+        // Promotion to Bishop
+        {
+            let board =
+                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let mut game = Game::new(board);
+
+            let request =
+                MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Bishop);
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "b8=B".to_string());
+        }
+
+        // Promotion by capture left
+        {
+            let board =
+                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let mut game = Game::new(board);
+
+            let request =
+                MoveRequest::promotion(Position::b7(), Position::a8(), PromotionType::Queen);
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "bxa8=Q".to_string());
+        }
+
+        // Promotion by capture right into check
+        {
+            let board =
+                fen::parse("r1b1kbnr/pP1pqp2/2n4p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 1 8")?;
+            let mut game = Game::new(board);
+
+            let request =
+                MoveRequest::promotion(Position::b7(), Position::c8(), PromotionType::Queen);
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "bxc8=Q+".to_string());
+        }
+
+        // Promotion data on a move that isn't a promotion is rejected rather than ignored
+        {
+            let board = Board::default();
+            let mut game = Game::new(board.clone());
+
+            let request =
+                MoveRequest::promotion(Position::e2(), Position::e4(), PromotionType::Queen);
+            assert_eq!(
+                game.attempt_move(request).unwrap_err(),
+                MoveError::UnexpectedPromotion
+            );
+            assert_boards_eq!(game.board(), &board);
+            assert_eq!(game.fen_at(1), None);
+        }
+
+        // Without a default promotion the piece has to be given
+        {
+            let board = fen::parse("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::b7(), Position::b8());
+            assert_eq!(
+                game.attempt_move(request).unwrap_err(),
+                MoveError::MissingPromotion
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_knight_move_notation() -> Result<(), ParseError> {
+        // Normal knight move
+        {
+            let board = Board::default();
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::b1(), Position::c3());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Nc3".to_string());
+        }
+
+        // Knight file disambiguation
+        {
+            let board =
+                fen::parse("rnb1kbnr/1pp2ppp/3p4/8/p3q3/2N3N1/PPPPPPPP/R1BQKB1R w KQkq - 0 8")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Ncxe4".to_string());
+        }
+
+        // Knight rank disambiguation
+        {
+            let board =
+                fen::parse("rnb1kbnr/ppp1ppp1/3p4/2N5/4q2p/2N5/PPPPPPPP/R1BQKB1R w KQkq - 0 8")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "N3xe4".to_string());
+        }
+
+        // Knight rank & file disambiguation
+        {
+            let board =
+                fen::parse("rnb1kbnr/ppp1ppp1/3p4/2N5/4q2p/2N3N1/PPPPP1PP/R1BQKB1R w KQkq - 0 8")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Nc3xe4".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rook_move_notation() -> Result<(), ParseError> {
+        // Normal rook move
+        {
+            let board = fen::parse("rnbqkbnr/1ppppppp/8/p7/P7/8/1PPPPPPP/RNBQKBNR w KQkq a6 0 2")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::a1(), Position::a3());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Ra3".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bishop_move_notation() -> Result<(), ParseError> {
+        // Normal bishop move
+        {
+            let board =
+                fen::parse("rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 2")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::c1(), Position::g5());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Bg5".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_queen_move_notation() -> Result<(), ParseError> {
+        // Normal queen move
+        {
+            let board = fen::parse("rnbqkbnr/pppp1ppp/8/8/3p4/7P/PPP1PPP1/RNBQKBNR w KQkq - 0 3")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d1(), Position::d4());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Qxd4".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_king_move_notation() -> Result<(), ParseError> {
+        // Normal king move
+        {
+            let board =
+                fen::parse("rnbqkbnr/p2p4/1pp2pp1/7p/3p4/N2QBNPP/PPP1PPB1/R3K2R w KQkq - 0 9")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::e1(), Position::d1());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Kd1".to_string());
+        }
+
+        // Short Castle
+        {
+            let board =
+                fen::parse("rnbqkbnr/p2p4/1pp2pp1/7p/3p4/N2QBNPP/PPP1PPB1/R3K2R w KQkq - 0 9")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::e1(), Position::g1());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "O-O".to_string());
+        }
+
+        // Long Castle
+        {
+            let board =
+                fen::parse("rnbqkbnr/p2p4/1pp2pp1/7p/3p4/N2QBNPP/PPP1PPB1/R3K2R w KQkq - 0 9")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::e1(), Position::c1());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "O-O-O".to_string());
+        }
+
+        // Long Castle Checkmate
+        {
+            let board = fen::parse("3k4/8/8/2Q1Q3/8/8/8/R3K3 w Q - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::e1(), Position::c1());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "O-O-O#".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_notation() -> Result<(), ParseError> {
+        // Check
+        {
+            let board =
+                fen::parse("rnbqkbnr/ppppp1pp/8/5p2/4P3/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 2")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d1(), Position::h5());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Qh5+".to_string());
+        }
+
+        // Checkmate
+        {
+            let board =
+                fen::parse("rnbqkbnr/ppppp2p/5p2/6p1/4P3/P7/1PPP1PPP/RNBQKBNR w KQkq g6 0 3")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d1(), Position::h5());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Qh5#".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disambiguation() -> Result<(), ParseError> {
+        // File disambiguation
+        {
+            let board = fen::parse("3r3r/8/8/R7/4Q2Q/8/8/R6Q b - - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::d8(), Position::f8());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Rdf8".to_string());
+        }
+
+        // Rank disambiguation
+        {
+            let board = fen::parse("3r3r/8/8/R7/4Q2Q/8/8/R6Q w - - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::a1(), Position::a3());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "R1a3".to_string());
+        }
+
+        // Rank and file disambiguation
+        {
+            let board = fen::parse("3r3r/8/8/R7/4Q2Q/8/8/R6Q w - - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::h4(), Position::e1());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Qh4e1".to_string());
+        }
+
+        // The other piece shares neither file nor rank, so the file is enough
+        {
+            let board = fen::parse("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::b1(), Position::d2());
+            let result = game.attempt_move(request).unwrap();
+            let notation = result.to_notation();
+            assert_eq!(notation, "Nbd2".to_string());
+        }
+
+        // Three pieces converging on one square
+        {
+            let board = fen::parse("4k3/8/8/2N5/8/2N5/5N2/4K3 w - - 0 1")?;
+
+            let mut game = Game::new(board.clone());
+            let request = MoveRequest::new(Position::f2(), Position::e4());
+            let notation = game.attempt_move(request).unwrap().to_notation();
+            assert_eq!(notation, "Nfe4".to_string());
+
+            let mut game = Game::new(board.clone());
+            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let notation = game.attempt_move(request).unwrap().to_notation();
+            assert_eq!(notation, "N3e4".to_string());
+
+            let mut game = Game::new(board);
+            let request = MoveRequest::new(Position::c5(), Position::e4());
+            let notation = game.attempt_move(request).unwrap().to_notation();
+            assert_eq!(notation, "N5e4".to_string());
+        }
+
+        // Pawn captures already name their file
+        {
+            let board = fen::parse("4k3/8/8/3p4/2P1P3/8/8/4K3 w - - 0 1")?;
+            let mut game = Game::new(board);
+
+            let request = MoveRequest::new(Position::e4(), Position::d5());
+            let notation = game.attempt_move(request).unwrap().to_notation();
+            assert_eq!(notation, "exd5".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repetition_draws() {
+        let mut game = Game::new(Board::default());
+
+        let shuffle = [
+            (Position::g1(), Position::f3()),
+            (Position::g8(), Position::f6()),
+            (Position::f3(), Position::g1()),
+            (Position::f6(), Position::g8()),
+        ];
+
+        // Each shuffle returns to the starting position one more time.
+        for repetition in 2..=4 {
+            for (start, end) in &shuffle {
+                game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+            }
+
+            assert_eq!(game.get_repetition_count(), repetition);
+            assert_eq!(game.get_move_state(), MoveState::CanMove);
+            assert_eq!(game.can_claim_draw(), repetition >= 3);
+        }
+
+        for (start, end) in &shuffle {
+            game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+        }
+
+        assert_eq!(game.get_repetition_count(), 5);
+        assert_eq!(game.get_move_state(), MoveState::FivefoldRepetition);
+        assert!(!game.can_claim_draw());
+        assert!(game
+            .attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_game_rules() -> Result<(), ParseError> {
+        let shuffle = [
+            (Position::g1(), Position::f3()),
+            (Position::g8(), Position::f6()),
+            (Position::f3(), Position::g1()),
+            (Position::f6(), Position::g8()),
+        ];
+
+        let mut game = Game::new(Board::default());
+        game.set_rules(GameRules {
+            enforce_repetition: false,
+            ..GameRules::default()
+        });
+        for _ in 0..4 {
+            for (start, end) in &shuffle {
+                game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+            }
+        }
+        assert_eq!(game.get_repetition_count(), 5);
+        assert_eq!(game.get_move_state(), MoveState::CanMove);
+        assert!(!game.can_claim_draw());
+        assert!(game
+            .attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+            .is_ok());
+
+        // The rules are saved, so the game can be reloaded past the fivefold repetition.
+        let mut saved = Vec::new();
+        game.save(&mut saved).unwrap();
+        let loaded = Game::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.get_rules(), game.get_rules());
+        assert_eq!(loaded.snapshot(), game.snapshot());
+        assert_eq!(loaded.get_move_state(), MoveState::CanMove);
+
+        // Turning the rule back on mid-game applies it to the plies already played.
+        game.previous_move();
+        game.set_rules(GameRules::default());
+        assert_eq!(game.get_move_state(), MoveState::FivefoldRepetition);
+
+        let board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 149 100")?;
+        let mut game = Game::new(board);
+        game.set_rules(GameRules {
+            enforce_fifty_move: false,
+            ..GameRules::default()
+        });
+        game.attempt_move(MoveRequest::new(Position::a1(), Position::a7()))
+            .unwrap();
+        assert_eq!(game.get_move_state(), MoveState::CanMove);
+        assert!(!game.can_claim_draw());
+
+        game.set_rules(GameRules::default());
+        assert_eq!(game.get_move_state(), MoveState::SeventyFiveMoveRule);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_touch_move() {
+        // Without the rule, touching only shows where the piece can go.
+        let mut game = Game::new(Board::default());
+        assert_eq!(
+            game.touch(&Position::g1()),
+            Ok(vec![Position::f3(), Position::h3()])
+        );
+        assert!(game
+            .attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+            .is_ok());
+
+        let mut game = Game::new(Board::default());
+        game.set_rules(GameRules {
+            touch_move: true,
+            ..GameRules::default()
+        });
+        assert_eq!(
+            game.touch(&Position::e7()).unwrap_err(),
+            MoveError::NotYourPiece {
+                position: Position::e7(),
+                piece: Piece::new(PieceType::Pawn, Side::Black)
+            }
+        );
+
+        game.touch(&Position::g1()).unwrap();
+        let touched_error = MoveError::TouchedPiece {
+            position: Position::g1(),
+        };
+        assert_eq!(
+            game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+                .unwrap_err(),
+            touched_error
+        );
+        assert_eq!(game.touch(&Position::e2()).unwrap_err(), touched_error);
+        assert!(!game.release_touch());
+        assert!(game
+            .attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+            .is_ok());
+
+        // A touched piece that can't move doesn't hold the player to anything.
+        assert_eq!(game.touch(&Position::a8()), Ok(Vec::new()));
+        assert!(game.release_touch());
+        assert_eq!(game.touch(&Position::h8()), Ok(Vec::new()));
+        assert!(game
+            .attempt_move(MoveRequest::new(Position::e7(), Position::e5()))
+            .is_ok());
+
+        // Looking back through the game doesn't let go of the touch.
+        game.touch(&Position::b1()).unwrap();
+        assert!(game.previous_move());
+        assert!(game.release_touch());
+        assert!(game.next_move());
+        assert_eq!(
+            game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+                .unwrap_err(),
+            MoveError::TouchedPiece {
+                position: Position::b1()
+            }
+        );
+        assert!(game
+            .attempt_move(MoveRequest::new(Position::b1(), Position::c3()))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_legal_move_cache() {
+        let mut game = Game::new(Board::default());
+        let generations = || crate::stats::snapshot().legal_move_generations;
+
+        crate::stats::reset();
+        assert_eq!(
+            game.legal_destinations(&Position::g1()),
+            vec![Position::f3(), Position::h3()]
+        );
+        assert_eq!(
+            game.legal_destinations(&Position::e2()),
+            vec![Position::e3(), Position::e4()]
+        );
+        assert!(game.is_legal_move(&MoveRequest::new(Position::e2(), Position::e4())));
+        assert!(!game.is_legal_move(&MoveRequest::new(Position::e2(), Position::e5())));
+        assert_eq!(game.legal_moves().len(), 20);
+        game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+            .unwrap();
+        if crate::stats::ENABLED {
+            assert_eq!(generations(), 1);
+        }
+
+        // A new position needs new moves, and going back doesn't reuse the newer ones.
+        assert_eq!(
+            game.legal_destinations(&Position::e7()),
+            vec![Position::e5(), Position::e6()]
+        );
+        assert!(game.previous_move());
+        assert!(game.legal_destinations(&Position::e7()).is_empty());
+        assert_eq!(
+            game.legal_destinations(&Position::e2()),
+            vec![Position::e3(), Position::e4()]
+        );
+        if crate::stats::ENABLED {
+            assert_eq!(generations(), 3);
+        }
+    }
+
+    #[test]
+    fn test_history_state_snapshots() {
+        let mut game = Game::new(Board::default());
+
+        let shuffle = [
+            (Position::g1(), Position::f3()),
+            (Position::g8(), Position::f6()),
+            (Position::f3(), Position::g1()),
+            (Position::f6(), Position::g8()),
+        ];
+
+        for _ in 0..2 {
+            for (start, end) in &shuffle {
+                game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+            }
+        }
+
+        assert_eq!(game.get_repetition_count(), 3);
+        assert!(game.can_claim_draw());
+
+        // Four plies back is the same position, but it had only been seen twice at that point.
+        for _ in 0..4 {
+            assert!(game.previous_move());
+        }
+
+        assert_eq!(
+            game.board().get_repetition_state(),
+            Board::default().get_repetition_state()
+        );
+        assert_eq!(game.get_repetition_count(), 2);
+        assert_eq!(game.get_move_state(), MoveState::CanMove);
+        assert!(!game.can_claim_draw());
+        assert!(!game.claim_draw());
+
+        for _ in 0..4 {
+            assert!(game.next_move());
+        }
+
+        assert_eq!(game.get_repetition_count(), 3);
+        assert!(game.can_claim_draw());
+
+        // Changing history drops the repetitions from the discarded plies.
+        for _ in 0..4 {
+            assert!(game.previous_move());
+        }
+
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::b1(), Position::c3()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::g8(), Position::f6()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::c3(), Position::b1()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::f6(), Position::g8()))
+            .unwrap();
+
+        assert_eq!(game.get_repetition_count(), 3);
+        assert!(game.can_claim_draw());
+        assert!(!game.next_move());
+    }
+
+    #[test]
+    fn test_claim_draw() -> Result<(), ParseError> {
+        // Threefold repetition claim
+        {
+            let mut game = Game::new(Board::default());
+            assert!(!game.claim_draw());
+
+            for _ in 0..2 {
+                game.attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+                    .unwrap();
+                game.attempt_move(MoveRequest::new(Position::g8(), Position::f6()))
+                    .unwrap();
+                game.attempt_move(MoveRequest::new(Position::f3(), Position::g1()))
+                    .unwrap();
+                game.attempt_move(MoveRequest::new(Position::f6(), Position::g8()))
+                    .unwrap();
+            }
+
+            assert!(game.claim_draw());
+            assert_eq!(game.get_move_state(), MoveState::ThreefoldRepetition);
+            assert!(game
+                .attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+                .is_err());
+        }
+
+        // Fifty move claim at exactly 100 half moves
         {
-            let board =
-                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 99 80")?;
             let mut game = Game::new(board);
+            assert!(!game.can_claim_draw());
 
-            let request =
-                MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Rook);
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "b8=R".to_string());
+            game.attempt_move(MoveRequest::new(Position::a1(), Position::a2()))
+                .unwrap();
+            assert_eq!(game.get_move_state(), MoveState::CanMove);
+
+            assert!(game.claim_draw());
+            assert_eq!(game.get_move_state(), MoveState::FiftyMoveRule);
         }
 
-        // Promotion to Bishop
+        // The move reaching 150 half moves still wins by checkmate
         {
-            let board =
-                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 149 100")?;
             let mut game = Game::new(board);
 
-            let request =
-                MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Bishop);
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "b8=B".to_string());
+            let result = game
+                .attempt_move(MoveRequest::new(Position::a1(), Position::a8()))
+                .unwrap();
+            assert_eq!(result.move_state, Some(MoveState::Checkmate));
+            assert_eq!(game.get_move_state(), MoveState::Checkmate);
         }
 
-        // Promotion by capture left
+        Ok(())
+    }
+
+    #[test]
+    fn test_forced_move() -> Result<(), ParseError> {
+        // The only legal move is marked as forced
         {
-            let board =
-                fen::parse("r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8")?;
+            let board = fen::parse("R6k/8/7K/8/8/8/b7/8 b - - 0 1")?;
             let mut game = Game::new(board);
 
-            let request =
-                MoveRequest::promotion(Position::b7(), Position::a8(), PromotionType::Queen);
+            let request = MoveRequest::new(Position::a2(), Position::g8());
             let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "bxa8=Q".to_string());
+            assert!(result.is_forced);
         }
 
-        // Promotion by capture right into check
+        // A normal move is not
         {
-            let board =
-                fen::parse("r1b1kbnr/pP1pqp2/2n4p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 1 8")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(Board::default());
 
-            let request =
-                MoveRequest::promotion(Position::b7(), Position::c8(), PromotionType::Queen);
+            let request = MoveRequest::new(Position::e2(), Position::e4());
             let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "bxc8=Q+".to_string());
+            assert!(!result.is_forced);
         }
 
         Ok(())
     }
 
     #[test]
-    fn test_knight_move_notation() -> Result<(), ParseError> {
-        // Normal knight move
+    fn test_default_promotion() -> Result<(), ParseError> {
+        let fen = "r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8";
+
+        // Strict by default
         {
-            let board = Board::default();
-            let mut game = Game::new(board);
+            let mut game = Game::new(fen::parse(fen)?);
+            assert_eq!(game.default_promotion(), None);
 
-            let request = MoveRequest::new(Position::b1(), Position::c3());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Nc3".to_string());
+            let request = MoveRequest::new(Position::b7(), Position::b8());
+            assert!(game.attempt_move(request).is_err());
         }
 
-        // Knight file disambiguation
+        // Auto queen
         {
-            let board =
-                fen::parse("rnb1kbnr/1pp2ppp/3p4/8/p3q3/2N3N1/PPPPPPPP/R1BQKB1R w KQkq - 0 8")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(fen::parse(fen)?);
+            game.set_default_promotion(Some(PromotionType::Queen));
 
-            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let request = MoveRequest::new(Position::b7(), Position::b8());
             let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Ncxe4".to_string());
+            assert_eq!(result.to_notation(), "b8=Q");
         }
 
-        // Knight rank disambiguation
+        // Auto knight, including a capture promotion
         {
-            let board =
-                fen::parse("rnb1kbnr/ppp1ppp1/3p4/2N5/4q2p/2N5/PPPPPPPP/R1BQKB1R w KQkq - 0 8")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(fen::parse(fen)?);
+            game.set_default_promotion(Some(PromotionType::Knight));
 
-            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let request = MoveRequest::new(Position::b7(), Position::a8());
             let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "N3xe4".to_string());
+            assert_eq!(result.to_notation(), "bxa8=N");
         }
 
-        // Knight rank & file disambiguation
+        // An explicit promotion wins over the default
         {
-            let board =
-                fen::parse("rnb1kbnr/ppp1ppp1/3p4/2N5/4q2p/2N3N1/PPPPP1PP/R1BQKB1R w KQkq - 0 8")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(fen::parse(fen)?);
+            game.set_default_promotion(Some(PromotionType::Knight));
 
-            let request = MoveRequest::new(Position::c3(), Position::e4());
+            let request =
+                MoveRequest::promotion(Position::b7(), Position::b8(), PromotionType::Rook);
             let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Nc3xe4".to_string());
+            assert_eq!(result.to_notation(), "b8=R");
         }
 
         Ok(())
     }
 
     #[test]
-    fn test_rook_move_notation() -> Result<(), ParseError> {
-        // Normal rook move
-        {
-            let board = fen::parse("rnbqkbnr/1ppppppp/8/p7/P7/8/1PPPPPPP/RNBQKBNR w KQkq a6 0 2")?;
-            let mut game = Game::new(board);
-
-            let request = MoveRequest::new(Position::a1(), Position::a3());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Ra3".to_string());
+    fn test_game_result_pgn_strings() {
+        let results = [
+            (GameResult::WhiteWins, "1-0"),
+            (GameResult::BlackWins, "0-1"),
+            (GameResult::Draw, "1/2-1/2"),
+            (GameResult::Ongoing, "*"),
+        ];
+
+        for (result, pgn_str) in results {
+            assert_eq!(result.to_pgn_str(), pgn_str);
+            assert_eq!(GameResult::from_pgn_str(pgn_str), Some(result));
         }
 
-        Ok(())
+        assert_eq!(GameResult::from_pgn_str("1-1"), None);
+        assert_eq!(GameResult::from_pgn_str("½-½"), None);
+
+        let reasons = [
+            (TerminationReason::Checkmate, "normal"),
+            (TerminationReason::Stalemate, "normal"),
+            (TerminationReason::Resignation, "normal"),
+            (TerminationReason::Timeout, "time forfeit"),
+            (TerminationReason::Agreement, "normal"),
+            (TerminationReason::Repetition, "normal"),
+            (TerminationReason::FiftyMoveRule, "normal"),
+            (TerminationReason::InsufficientMaterial, "normal"),
+            (TerminationReason::Abandoned, "abandoned"),
+        ];
+
+        for (reason, pgn_str) in reasons {
+            assert_eq!(reason.to_pgn_str(), pgn_str);
+        }
     }
 
     #[test]
-    fn test_bishop_move_notation() -> Result<(), ParseError> {
-        // Normal bishop move
+    fn test_game_result() -> Result<(), ParseError> {
+        // Checkmate is credited to the side that delivered it
         {
-            let board =
-                fen::parse("rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 2")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(fen::parse(
+                "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2",
+            )?);
+            assert_eq!(game.get_result(), GameResult::Ongoing);
+            assert_eq!(game.get_termination_reason(), None);
+
+            game.attempt_move(MoveRequest::new(Position::d8(), Position::h4()))
+                .unwrap();
+            assert_eq!(game.get_result(), GameResult::BlackWins);
+            assert_eq!(
+                game.get_termination_reason(),
+                Some(TerminationReason::Checkmate)
+            );
+
+            // Viewing an earlier ply doesn't change the result of the game.
+            game.previous_move();
+            assert_eq!(game.get_result(), GameResult::BlackWins);
+            assert!(!game.set_result(GameResult::WhiteWins, TerminationReason::Resignation));
+        }
 
-            let request = MoveRequest::new(Position::c1(), Position::g5());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Bg5".to_string());
+        // Resignation ends the game
+        {
+            let mut game = Game::new(Board::default());
+            assert!(!game.set_result(GameResult::Ongoing, TerminationReason::Abandoned));
+            assert!(game.set_result(GameResult::BlackWins, TerminationReason::Resignation));
+            assert_eq!(game.get_result(), GameResult::BlackWins);
+            assert_eq!(
+                game.get_termination_reason(),
+                Some(TerminationReason::Resignation)
+            );
+            assert!(!game.set_result(GameResult::Draw, TerminationReason::Agreement));
+            assert!(game
+                .attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+                .is_err());
+        }
+
+        // Claimed draws
+        {
+            let board = fen::parse("7k/8/6K1/8/8/8/8/R7 w - - 100 80")?;
+            let mut game = Game::new(board);
+            assert!(game.claim_draw());
+            assert_eq!(game.get_result(), GameResult::Draw);
+            assert_eq!(
+                game.get_termination_reason(),
+                Some(TerminationReason::FiftyMoveRule)
+            );
         }
 
         Ok(())
     }
 
     #[test]
-    fn test_queen_move_notation() -> Result<(), ParseError> {
-        // Normal queen move
-        {
-            let board = fen::parse("rnbqkbnr/pppp1ppp/8/8/3p4/7P/PPP1PPP1/RNBQKBNR w KQkq - 0 3")?;
-            let mut game = Game::new(board);
+    fn test_fork() {
+        let mut game = Game::new(Board::default());
+
+        let shuffle = [
+            (Position::g1(), Position::f3()),
+            (Position::g8(), Position::f6()),
+            (Position::f3(), Position::g1()),
+            (Position::f6(), Position::g8()),
+        ];
+
+        for (start, end) in &shuffle {
+            game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+        }
+        game.attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+            .unwrap();
+
+        // Fork one ply back, at the second occurrence of the starting position.
+        game.previous_move();
+        let mut fork = game.fork();
+        assert_boards_eq!(fork.board(), game.board());
+        assert_eq!(fork.get_repetition_count(), 2);
+        assert!(!fork.previous_move());
+        assert!(!fork.can_claim_draw());
+
+        for (start, end) in &shuffle {
+            fork.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+        }
 
-            let request = MoveRequest::new(Position::d1(), Position::d4());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Qxd4".to_string());
+        assert_eq!(fork.get_repetition_count(), 3);
+        assert!(fork.can_claim_draw());
+
+        // Taking moves back keeps the repetitions from before the fork.
+        assert!(fork.take_back(Side::White));
+        assert_eq!(fork.get_repetition_count(), 2);
+        for (start, end) in &shuffle[2..] {
+            fork.attempt_move(MoveRequest::new(*start, *end)).unwrap();
         }
+        assert_eq!(fork.get_repetition_count(), 3);
+        assert!(fork.can_claim_draw());
+
+        // The original game is untouched by the fork.
+        assert_eq!(game.get_repetition_count(), 2);
+        assert!(game.next_move());
+
+        let mut full_fork = game.fork_full();
+        assert!(full_fork.previous_move());
+        assert_eq!(full_fork.get_repetition_count(), 2);
+        full_fork.set_allow_divergence(true);
+        full_fork
+            .attempt_move(MoveRequest::new(Position::b1(), Position::c3()))
+            .unwrap();
+        assert_eq!(
+            game.board().piece_at(&Position::f3()),
+            Some(&Piece::new(PieceType::Knight, Side::White))
+        );
+        assert!(game.board().piece_at(&Position::c3()).is_none());
+    }
+
+    #[test]
+    fn test_from_san_moves() {
+        let game = Game::from_san_moves(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5"]).unwrap();
+        assert_eq!(
+            fen::generate(game.board()),
+            "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"
+        );
+
+        let mut game = game;
+        assert!(game.previous_move());
+
+        let error = Game::from_san_moves(&["e4", "e5", "Nf3", "Nc6", "Ke3"]).unwrap_err();
+        assert_eq!(error.to_string(), "Move 4 (Ke3): Illegal move.");
+    }
+
+    #[test]
+    fn test_legal_captures_and_checks() -> Result<(), ParseError> {
+        // Every knight move uncovers the rook on the d-file.
+        let game = Game::new(fen::parse("3k4/8/2p1p3/8/3N4/8/8/3R2K1 w - - 0 1")?);
+
+        let captures = game.legal_captures();
+        assert_eq!(
+            captures,
+            vec![
+                MoveRequest::new(Position::d4(), Position::c6()),
+                MoveRequest::new(Position::d4(), Position::e6()),
+            ]
+        );
+
+        let checks = game.legal_checks();
+        assert_eq!(checks.len(), 8);
+        assert!(checks.iter().all(|request| request.start == Position::d4()));
+        assert!(checks.contains(&MoveRequest::new(Position::d4(), Position::b5())));
+        assert!(!captures.contains(&MoveRequest::new(Position::d4(), Position::b5())));
+
+        // Promotions by capture count once per promotion type.
+        let game = Game::new(fen::parse(
+            "r1bqkbnr/pP3p2/2np3p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 0 8",
+        )?);
+        let captures = game.legal_captures();
+        assert_eq!(
+            captures
+                .iter()
+                .filter(|request| request.start == Position::b7())
+                .count(),
+            8
+        );
+        assert!(captures.contains(&MoveRequest::promotion(
+            Position::b7(),
+            Position::c8(),
+            PromotionType::Knight
+        )));
+
+        assert!(Game::new(Board::default()).legal_captures().is_empty());
+        assert!(Game::new(Board::default()).legal_checks().is_empty());
 
         Ok(())
     }
 
     #[test]
-    fn test_king_move_notation() -> Result<(), ParseError> {
-        // Normal king move
+    fn test_draw_offers() {
+        // An offer expires once the opponent has moved
         {
-            let board =
-                fen::parse("rnbqkbnr/p2p4/1pp2pp1/7p/3p4/N2QBNPP/PPP1PPB1/R3K2R w KQkq - 0 9")?;
-            let mut game = Game::new(board);
-
-            let request = MoveRequest::new(Position::e1(), Position::d1());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Kd1".to_string());
+            let mut game = Game::new(Board::default());
+            assert!(!game.accept_draw());
+
+            assert!(game.offer_draw(Side::White));
+            assert_eq!(game.pending_draw_offer(), Some(Side::White));
+            assert!(!game.offer_draw(Side::Black));
+
+            game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+                .unwrap();
+            assert_eq!(game.pending_draw_offer(), Some(Side::White));
+
+            game.attempt_move(MoveRequest::new(Position::e7(), Position::e5()))
+                .unwrap();
+            assert_eq!(game.pending_draw_offer(), None);
+            assert!(!game.accept_draw());
+            assert_eq!(game.get_result(), GameResult::Ongoing);
         }
 
-        // Short Castle
+        // An offer made after moving expires with the opponent's reply
         {
-            let board =
-                fen::parse("rnbqkbnr/p2p4/1pp2pp1/7p/3p4/N2QBNPP/PPP1PPB1/R3K2R w KQkq - 0 9")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(Board::default());
+            game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+                .unwrap();
+
+            assert!(game.offer_draw(Side::White));
+            game.attempt_move(MoveRequest::new(Position::e7(), Position::e5()))
+                .unwrap();
+            assert_eq!(game.pending_draw_offer(), None);
+        }
 
-            let request = MoveRequest::new(Position::e1(), Position::g1());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "O-O".to_string());
+        // Accepting ends the game
+        {
+            let mut game = Game::new(Board::default());
+            game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+                .unwrap();
+
+            assert!(game.offer_draw(Side::White));
+            assert!(game.accept_draw());
+            assert_eq!(game.get_result(), GameResult::Draw);
+            assert_eq!(
+                game.get_termination_reason(),
+                Some(TerminationReason::Agreement)
+            );
+            assert_eq!(game.pending_draw_offer(), None);
+            assert!(!game.offer_draw(Side::Black));
+            assert!(game
+                .attempt_move(MoveRequest::new(Position::e7(), Position::e5()))
+                .is_err());
         }
 
-        // Long Castle
+        // Repeated offers inside the limit are rejected
         {
-            let board =
-                fen::parse("rnbqkbnr/p2p4/1pp2pp1/7p/3p4/N2QBNPP/PPP1PPB1/R3K2R w KQkq - 0 9")?;
-            let mut game = Game::new(board);
+            let mut game = Game::new(Board::default());
+            game.set_draw_offer_limit(Some(4));
+
+            let moves = [
+                (Position::g1(), Position::f3()),
+                (Position::g8(), Position::f6()),
+                (Position::f3(), Position::g1()),
+                (Position::f6(), Position::g8()),
+            ];
+
+            assert!(game.offer_draw(Side::White));
+            for (start, end) in &moves[..2] {
+                game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+            }
+            assert_eq!(game.pending_draw_offer(), None);
+            assert!(!game.offer_draw(Side::White));
+            assert!(game.offer_draw(Side::Black));
 
-            let request = MoveRequest::new(Position::e1(), Position::c1());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "O-O-O".to_string());
+            for (start, end) in &moves[2..] {
+                game.attempt_move(MoveRequest::new(*start, *end)).unwrap();
+            }
+            assert!(game.offer_draw(Side::White));
         }
+    }
 
-        // Long Castle Checkmate
-        {
-            let board = fen::parse("3k4/8/8/2Q1Q3/8/8/8/R3K3 w Q - 0 1")?;
-            let mut game = Game::new(board);
+    #[test]
+    fn test_with_opening() {
+        let opening = ["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5"];
+        let mut game = Game::with_opening(&opening).unwrap();
 
-            let request = MoveRequest::new(Position::e1(), Position::c1());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "O-O-O#".to_string());
+        assert!(!game.is_book_ply(0));
+        for ply in 1..=6 {
+            assert!(game.is_book_ply(ply));
         }
+        assert!(!game.is_book_ply(7));
+
+        game.attempt_move(MoveRequest::new(Position::c2(), Position::c3()))
+            .unwrap();
+        assert_eq!(
+            fen::generate(game.board()),
+            "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/2P2N2/PP1P1PPP/RNBQK2R b KQkq - 0 4"
+        );
+        assert!(!game.is_book_ply(7));
+        assert!(game.is_book_ply(6));
+
+        // Leaving the book early replaces the rest of it.
+        for _ in 0..3 {
+            game.previous_move();
+        }
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::f1(), Position::b5()))
+            .unwrap();
+        assert!(game.is_book_ply(4));
+        assert!(!game.is_book_ply(5));
+
+        assert!(Game::with_opening(&["e4", "e4"]).is_err());
+    }
+
+    #[test]
+    fn test_move_descriptions() -> Result<(), ParseError> {
+        let mut game = Game::new(Board::default());
+        let result = game
+            .attempt_move(MoveRequest::new(Position::b1(), Position::c3()))
+            .unwrap();
+        assert_eq!(result.describe(), "Knight from b1 to c3");
+
+        let mut game = Game::new(fen::parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2")?);
+        let result = game
+            .attempt_move(MoveRequest::new(Position::e5(), Position::d6()))
+            .unwrap();
+        assert_eq!(result.describe(), "Pawn captures on d6 en passant");
+
+        let mut game = Game::new(fen::parse(
+            "r1b1kbnr/pP1pqp2/2n4p/2p1p1p1/3P4/1P6/2P1PPPP/RNBQKBNR w KQkq - 1 8",
+        )?);
+        let result = game
+            .attempt_move(MoveRequest::promotion(
+                Position::b7(),
+                Position::c8(),
+                PromotionType::Queen,
+            ))
+            .unwrap();
+        assert_eq!(
+            result.describe(),
+            "Pawn captures on c8, promoting to a queen, giving check"
+        );
+
+        let mut game = Game::new(fen::parse("3k4/8/8/2Q1Q3/8/8/8/R3K3 w Q - 0 1")?);
+        let result = game
+            .attempt_move(MoveRequest::new(Position::e1(), Position::c1()))
+            .unwrap();
+        assert_eq!(
+            result.describe(),
+            "King castles queenside, giving checkmate"
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_check_notation() -> Result<(), ParseError> {
-        // Check
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppppp1pp/8/5p2/4P3/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 2")?;
-            let mut game = Game::new(board);
+    fn test_history_accessors() {
+        let mut game = Game::from_san_moves(&["Nf3", "Nf6", "Ng1", "Ng8", "e4"]).unwrap();
+        game.previous_move();
+        game.previous_move();
+
+        let index_before = game.index;
+        assert_eq!(
+            game.board_at(0).unwrap().get_repetition_state(),
+            Board::default().get_repetition_state()
+        );
+        assert_eq!(
+            game.fen_at(1),
+            Some("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1")
+        );
+        assert_eq!(
+            fen::generate(&game.board_at(5).unwrap()),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"
+        );
+        assert_eq!(game.board_at(3).as_ref(), Some(game.board()));
+        assert_eq!(game.board_at(6), None);
+        assert_eq!(game.fen_at(6), None);
+        assert_eq!(game.index, index_before);
+
+        assert_eq!(game.find_position(&Board::default()), vec![0, 4]);
+        let after_nf3 = game.board_at(1).unwrap();
+        assert_eq!(game.find_position(&after_nf3), vec![1]);
+
+        // Divergence drops the discarded plies.
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::e7(), Position::e5()))
+            .unwrap();
+        assert_eq!(game.find_position(&after_nf3), vec![1]);
+        assert_eq!(game.find_position(&Board::default()), vec![0]);
+        assert_eq!(game.board_at(5), None);
+        assert_eq!(
+            game.board_at(4).unwrap().piece_at(&Position::e5()),
+            Some(&Piece::new(PieceType::Pawn, Side::Black))
+        );
+    }
 
-            let request = MoveRequest::new(Position::d1(), Position::h5());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Qh5+".to_string());
+    #[test]
+    fn test_clone_and_snapshot() {
+        let mut game = Game::from_san_moves(&["e4", "e5", "Nf3"]).unwrap();
+        let mut clone = game.clone();
+
+        clone
+            .attempt_move(MoveRequest::new(Position::b8(), Position::c6()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::g8(), Position::f6()))
+            .unwrap();
+        assert!(clone.board().piece_at(&Position::c6()).is_some());
+        assert!(game.board().piece_at(&Position::c6()).is_none());
+        assert!(clone.previous_move());
+        assert!(clone.board().piece_at(&Position::f6()).is_none());
+
+        assert_eq!(
+            game.snapshot(),
+            GameSnapshot {
+                fen: String::from(
+                    "rnbqkb1r/pppp1ppp/5n2/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+                ),
+                ply: 4,
+                result: GameResult::Ongoing,
+                side_to_move: Side::White,
+            }
+        );
+
+        game.previous_move();
+        assert_eq!(game.snapshot().ply, 3);
+        assert_eq!(game.snapshot().side_to_move, Side::Black);
+
+        assert_eq!(
+            format!("{game:?}"),
+            "Game { fen: \"rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2\", ply: 3, state: CanMove }"
+        );
+    }
+
+    #[test]
+    fn test_irreversible_moves_reset_repetitions() {
+        let mut game = Game::from_san_moves(&["e4", "d5", "Nf3", "Nf6", "Ng1", "Ng8"]).unwrap();
+
+        // Only the positions since ...d5 are compared, which makes the knight shuffle a repeat.
+        assert_eq!(game.last_irreversible_ply(), 2);
+        assert_eq!(game.repetitions.len(), 4);
+        assert_eq!(game.get_repetition_count(), 2);
+
+        // The capture starts a new window, so nothing from before it is tracked any more.
+        game.attempt_move(MoveRequest::new(Position::e4(), Position::d5()))
+            .unwrap();
+        assert_eq!(game.last_irreversible_ply(), 7);
+        assert_eq!(game.repetitions.len(), 1);
+        assert_eq!(game.get_repetition_count(), 1);
+
+        for (start, end) in [
+            (Position::g8(), Position::f6()),
+            (Position::g1(), Position::f3()),
+            (Position::f6(), Position::g8()),
+            (Position::f3(), Position::g1()),
+        ] {
+            game.attempt_move(MoveRequest::new(start, end)).unwrap();
         }
+        assert_eq!(game.last_irreversible_ply(), 7);
+        assert_eq!(game.get_repetition_count(), 2);
+
+        // Losing castling rights also starts a new window.
+        game.attempt_move(MoveRequest::new(Position::e8(), Position::d7()))
+            .unwrap();
+        assert_eq!(game.last_irreversible_ply(), 12);
+        assert_eq!(game.repetitions.len(), 1);
+
+        // Going back and playing something else restores the earlier window.
+        game.previous_move();
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::g8(), Position::f6()))
+            .unwrap();
+        assert_eq!(game.last_irreversible_ply(), 7);
+        assert_eq!(game.get_repetition_count(), 2);
+        assert_eq!(game.repetitions.len(), 4);
+        assert_eq!(game.fork().repetitions.len(), 4);
+    }
 
-        // Checkmate
-        {
-            let board =
-                fen::parse("rnbqkbnr/ppppp2p/5p2/6p1/4P3/P7/1PPP1PPP/RNBQKBNR w KQkq g6 0 3")?;
-            let mut game = Game::new(board);
+    #[test]
+    fn test_annotate_position() {
+        let mut game = Game::from_san_moves(&["e4", "e5", "Nf3"]).unwrap();
+
+        let mut annotations = Annotations::new();
+        annotations.arrow(Position::b8(), Position::c6(), Color::Green);
+        assert!(game.annotate_position(2, annotations.clone()));
+        assert!(!game.annotate_position(4, annotations.clone()));
+
+        assert_eq!(game.annotations_at(2), Some(&annotations));
+        assert!(game.annotations_at(3).unwrap().is_empty());
+        assert_eq!(game.annotations_at(4), None);
+
+        // Changing history drops the annotations on the abandoned plies.
+        game.previous_move();
+        game.previous_move();
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::c7(), Position::c5()))
+            .unwrap();
+        assert!(game.annotations_at(2).unwrap().is_empty());
+    }
 
-            let request = MoveRequest::new(Position::d1(), Position::h5());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Qh5#".to_string());
+    #[test]
+    fn test_last_move_navigation() {
+        let mut game = Game::from_san_moves(&["e4", "e5", "Nf3"]).unwrap();
+        assert_eq!(
+            game.board().last_move(),
+            Some((Position::g1(), Position::f3()))
+        );
+
+        game.previous_move();
+        assert_eq!(
+            game.board().last_move(),
+            Some((Position::e7(), Position::e5()))
+        );
+        assert_eq!(
+            game.board_at(1).unwrap().last_move(),
+            Some((Position::e2(), Position::e4()))
+        );
+        assert_eq!(game.board_at(0).unwrap().last_move(), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut game = Game::from_san_moves(&["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]).unwrap();
+
+        // Go back and change history, then step back again to save mid-navigation.
+        game.previous_move();
+        game.previous_move();
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::f1(), Position::c4()))
+            .unwrap();
+        game.previous_move();
+
+        let mut saved = Vec::new();
+        game.save(&mut saved).unwrap();
+        assert_eq!(
+            String::from_utf8(saved.clone()).unwrap(),
+            "chess-game 2\n\
+             fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\n\
+             moves e2e4 e7e5 g1f3 b8c6 f1c4\n\
+             index 4\n\
+             rules fifty-move repetition insufficient-material\n"
+        );
+
+        let mut loaded = Game::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.snapshot(), game.snapshot());
+        assert_eq!(loaded.history.len(), game.history.len());
+        for ply in 0..game.history.len() {
+            assert_eq!(loaded.fen_at(ply), game.fen_at(ply));
         }
 
-        Ok(())
+        let mut resaved = Vec::new();
+        loaded.save(&mut resaved).unwrap();
+        assert_eq!(resaved, saved);
+
+        assert!(loaded.next_move());
+        assert!(!loaded.next_move());
+
+        // So do the book moves and the default promotion.
+        let mut game = Game::with_opening(&["e4", "e5"]).unwrap();
+        game.attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+            .unwrap();
+        game.set_default_promotion(Some(PromotionType::Knight));
+        let mut annotations = Annotations::new();
+        annotations.circle(Position::e4(), Color::Red);
+        annotations.arrow(Position::g1(), Position::f3(), Color::Green);
+        game.annotate_position(2, annotations.clone());
+
+        let mut saved = Vec::new();
+        game.save(&mut saved).unwrap();
+        let saved_text = String::from_utf8(saved.clone()).unwrap();
+        assert!(saved_text.contains("book 1 2\n"));
+        assert!(saved_text.contains("promotion n\n"));
+        assert!(saved_text.contains("annotations 2 [%csl Re4] [%cal Gg1f3]\n"));
+
+        let loaded = Game::load(saved.as_slice()).unwrap();
+        for ply in 0..4 {
+            assert_eq!(loaded.is_book_ply(ply), ply == 1 || ply == 2, "ply {ply}");
+        }
+        assert_eq!(loaded.default_promotion(), Some(PromotionType::Knight));
+        assert_eq!(loaded.annotations_at(2), Some(&annotations));
+        assert!(loaded.annotations_at(1).unwrap().is_empty());
+
+        // Results and promotions survive the round trip.
+        let board = fen::parse("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut game = Game::new(board);
+        game.attempt_move(MoveRequest::promotion(
+            Position::b7(),
+            Position::b8(),
+            PromotionType::Knight,
+        ))
+        .unwrap();
+        game.set_result(GameResult::BlackWins, TerminationReason::Timeout);
+
+        let mut saved = Vec::new();
+        game.save(&mut saved).unwrap();
+        let loaded = Game::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.fen_at(1), Some("1N2k3/8/8/8/8/8/8/4K3 b - - 0 1"));
+        assert_eq!(loaded.get_result(), GameResult::BlackWins);
+        assert_eq!(
+            loaded.get_termination_reason(),
+            Some(TerminationReason::Timeout)
+        );
     }
 
     #[test]
-    fn test_disambiguation() -> Result<(), ParseError> {
-        // File disambiguation
-        {
-            let board = fen::parse("3r3r/8/8/R7/4Q2Q/8/8/R6Q b - - 0 1")?;
-            let mut game = Game::new(board);
+    fn test_load_errors() {
+        let start = "fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
-            let request = MoveRequest::new(Position::d8(), Position::f8());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Rdf8".to_string());
+        let saved = format!("chess-game 1\n{start}\nmoves e2e4 e7e5 e4e5 g8f6\nindex 0\n");
+        match Game::load(saved.as_bytes()) {
+            Err(LoadError::IllegalMove { ply, .. }) => assert_eq!(ply, 3),
+            other => panic!("Expected an illegal move, got {other:?}"),
         }
 
-        // Rank disambiguation
-        {
-            let board = fen::parse("3r3r/8/8/R7/4Q2Q/8/8/R6Q w - - 0 1")?;
-            let mut game = Game::new(board);
+        let saved = format!("chess-game 3\n{start}\nmoves\nindex 0\n");
+        assert!(matches!(
+            Game::load(saved.as_bytes()),
+            Err(LoadError::Format(_))
+        ));
+
+        let saved = format!("chess-game 1\n{start}\nmoves e2e4\nindex 2\n");
+        assert!(matches!(
+            Game::load(saved.as_bytes()),
+            Err(LoadError::Format(_))
+        ));
+
+        let saved = "chess-game 1\nmoves e2e4\nindex 0\n";
+        assert!(matches!(
+            Game::load(saved.as_bytes()),
+            Err(LoadError::Format(_))
+        ));
+
+        let saved = format!("chess-game 2\n{start}\nmoves\nindex 0\nrules castling\n");
+        assert!(matches!(
+            Game::load(saved.as_bytes()),
+            Err(LoadError::Format(_))
+        ));
+
+        let saved = format!("chess-game 2\n{start}\nmoves e2e4\nindex 0\nbook 2\n");
+        assert!(matches!(
+            Game::load(saved.as_bytes()),
+            Err(LoadError::Format(_))
+        ));
+
+        let saved = format!("chess-game 2\n{start}\nmoves\nindex 0\nannotations 1 [%csl Re4]\n");
+        assert!(matches!(
+            Game::load(saved.as_bytes()),
+            Err(LoadError::Format(_))
+        ));
+    }
 
-            let request = MoveRequest::new(Position::a1(), Position::a3());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "R1a3".to_string());
+    #[test]
+    fn test_with_board_requires_kings() {
+        assert!(Game::with_board(Board::default()).is_ok());
+        assert!(Game::with_board(Board::empty()).is_err());
+
+        let board = fen::parse("4k3/8/8/8/8/8/8/R3R2R w - - 0 1").unwrap();
+        match Game::with_board(board.clone()) {
+            Err(error) => assert_eq!(error.to_string(), "White has no king."),
+            Ok(_) => panic!("Expected a missing king error"),
         }
 
-        // Rank and file disambiguation
-        {
-            let board = fen::parse("3r3r/8/8/R7/4Q2Q/8/8/R6Q w - - 0 1")?;
-            let mut game = Game::new(board);
+        // Game::new still accepts the position, but won't report a result for it.
+        let game = Game::new(board);
+        assert_eq!(game.get_move_state(), MoveState::Invalid);
+        assert_eq!(game.get_result(), GameResult::Ongoing);
+        assert_eq!(game.get_termination_reason(), None);
+    }
 
-            let request = MoveRequest::new(Position::h4(), Position::e1());
-            let result = game.attempt_move(request).unwrap();
-            let notation = result.to_notation();
-            assert_eq!(notation, "Qh4e1".to_string());
+    #[test]
+    fn test_format_move_list() {
+        let mut game = crate::fixtures::play("scholars-mate");
+        assert_eq!(
+            game.format_move_list(4),
+            concat!(
+                "1.  e4    e5\n",
+                "2.  Qh5   Nc6\n",
+                "3.  Bc4   Nf6\n",
+                "4. >Qxf7#"
+            )
+        );
+
+        for _ in 0..3 {
+            game.previous_move();
         }
+        assert_eq!(
+            game.format_move_list(4),
+            concat!(
+                "1.  e4    e5\n",
+                "2.  Qh5  >Nc6\n",
+                "3.  Bc4   Nf6\n",
+                "4.  Qxf7#"
+            )
+        );
+
+        while game.previous_move() {}
+        assert!(!game.format_move_list(4).contains('>'));
+
+        assert_eq!(Game::new(Board::default()).format_move_list(4), "");
+    }
 
-        Ok(())
+    #[test]
+    fn test_format_move_list_black_to_move() {
+        let board = fen::parse("4k3/8/8/8/8/8/4P3/4K3 b - - 0 9").unwrap();
+        let mut game = Game::new(board);
+        game.attempt_move(MoveRequest::new(Position::e8(), Position::d8()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::d8(), Position::c7()))
+            .unwrap();
+
+        assert_eq!(
+            game.format_move_list(3),
+            concat!(" 9.  ...  Kd8\n", "10.  e4  >Kc7")
+        );
+    }
+
+    #[test]
+    fn test_move_confirmation() {
+        let mut game = Game::new(Board::default());
+        game.set_move_confirmation(MoveConfirmation::Confirm);
+
+        // Staging checks the move without playing it.
+        let move_info = game
+            .attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+            .unwrap();
+        assert_eq!(move_info.to_notation(), "e4");
+        assert_eq!(
+            game.staged_move(),
+            Some(&MoveRequest::new(Position::e2(), Position::e4()))
+        );
+        assert_boards_eq!(game.board(), &Board::default());
+
+        // A new attempt replaces the staged move, and illegal ones leave it alone.
+        game.attempt_move(MoveRequest::new(Position::d2(), Position::d4()))
+            .unwrap();
+        assert_eq!(
+            game.attempt_move(MoveRequest::new(Position::d2(), Position::d5()))
+                .unwrap_err(),
+            MoveError::InvalidMove
+        );
+        assert_eq!(
+            game.staged_move(),
+            Some(&MoveRequest::new(Position::d2(), Position::d4()))
+        );
+
+        let move_info = game.confirm_move().unwrap();
+        assert_eq!(move_info.to_notation(), "d4");
+        assert_eq!(game.staged_move(), None);
+        assert_eq!(game.fen_at(1), Some(fen::generate(game.board()).as_str()));
+        assert_eq!(game.confirm_move().unwrap_err(), MoveError::InvalidMove);
+
+        // Aborting drops the staged move.
+        game.attempt_move(MoveRequest::new(Position::d7(), Position::d5()))
+            .unwrap();
+        assert!(game.cancel_staged_move());
+        assert!(!game.cancel_staged_move());
+        assert!(game.confirm_move().is_err());
+        assert_eq!(game.fen_at(2), None);
+    }
+
+    #[test]
+    fn test_takebacks() {
+        let fixture = &crate::fixtures::CASTLING;
+        let mut game = crate::fixtures::play(fixture.name);
+        let last_ply = fixture.moves.len();
+
+        // Black just castled, so taking back black's move undoes one ply, but once white has
+        // replied it undoes two.
+        assert!(game.take_back(Side::Black));
+        assert_eq!(game.fen_at(last_ply - 1), Some(fixture.fens[last_ply - 1]));
+        assert_eq!(game.fen_at(last_ply), None);
+
+        assert!(game.take_back(Side::Black));
+        assert_eq!(game.fen_at(last_ply - 3), Some(fixture.fens[last_ply - 3]));
+        assert_eq!(game.fen_at(last_ply - 2), None);
+        assert_eq!(
+            game.board(),
+            &fen::parse(fixture.fens[last_ply - 3]).unwrap()
+        );
+
+        game.set_takeback_policy(TakebackPolicy::Forbidden);
+        assert!(!game.take_back(Side::White));
+        assert!(!game.request_takeback(Side::White));
+
+        // A declined request leaves the history untouched.
+        game.set_takeback_policy(TakebackPolicy::RequiresAgreement);
+        assert!(!game.take_back(Side::White));
+        assert!(game.request_takeback(Side::White));
+        assert_eq!(game.pending_takeback_request(), Some(Side::White));
+        assert!(!game.request_takeback(Side::Black));
+        assert!(game.decline_takeback());
+        assert!(!game.accept_takeback());
+        assert_eq!(game.fen_at(last_ply - 3), Some(fixture.fens[last_ply - 3]));
+        assert_eq!(game.fen_at(last_ply - 2), None);
+
+        // A request lapses once another move is played.
+        assert!(game.request_takeback(Side::White));
+        let request = MoveRequest::from_san(game.board(), "Qd7").unwrap();
+        game.attempt_move(request).unwrap();
+        assert_eq!(game.pending_takeback_request(), None);
+        assert!(!game.accept_takeback());
+
+        assert!(game.request_takeback(Side::Black));
+        assert!(game.accept_takeback());
+        assert_eq!(game.fen_at(last_ply - 2), None);
+        assert_eq!(
+            game.board(),
+            &fen::parse(fixture.fens[last_ply - 3]).unwrap()
+        );
+
+        // There is nothing to take back at the start or once the game is over.
+        let mut game = Game::new(Board::default());
+        assert!(!game.take_back(Side::White));
+        game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+            .unwrap();
+        game.set_result(GameResult::BlackWins, TerminationReason::Resignation);
+        assert!(!game.take_back(Side::White));
+        assert!(!crate::fixtures::play("scholars-mate").take_back(Side::White));
+    }
+
+    #[test]
+    fn test_force_move() {
+        let mut game = Game::new(Board::default());
+
+        game.attempt_move(MoveRequest::new(Position::e2(), Position::e4()))
+            .unwrap();
+        let captured = game.force_move(MoveRequest::new(Position::g8(), Position::e4()));
+        assert_eq!(captured, Ok(Some(Piece::new(PieceType::Pawn, Side::White))));
+        game.attempt_move(MoveRequest::new(Position::d2(), Position::d4()))
+            .unwrap();
+        game.force_move(MoveRequest::new(Position::e8(), Position::e6()))
+            .unwrap();
+        game.attempt_move(MoveRequest::new(Position::g1(), Position::f3()))
+            .unwrap();
+        assert_eq!(
+            game.force_move(MoveRequest::new(Position::e3(), Position::e4()))
+                .unwrap_err(),
+            MoveError::EmptySquare {
+                position: Position::e3()
+            }
+        );
+
+        for ply in 0..6 {
+            let board = game.board_at(ply).unwrap();
+            assert!(board.check_consistency().is_ok(), "ply {ply}");
+            assert_eq!(game.is_unchecked_ply(ply), ply == 2 || ply == 4);
+        }
+        assert_eq!(
+            game.board().get_castle_rights(),
+            &board::CastleRights::new(true, true, false, false)
+        );
+        assert_eq!(
+            game.format_move_list(4),
+            "1.  e4    g8e4\n2.  d4    e8e6\n3. >Nf3"
+        );
+
+        // Unchecked moves survive a save and load.
+        let mut saved = Vec::new();
+        game.save(&mut saved).unwrap();
+        assert!(String::from_utf8(saved.clone())
+            .unwrap()
+            .contains("moves e2e4 g8e4! d2d4 e8e6! g1f3\n"));
+        let loaded = Game::load(saved.as_slice()).unwrap();
+        for ply in 0..6 {
+            assert_eq!(loaded.fen_at(ply), game.fen_at(ply));
+            assert_eq!(loaded.is_unchecked_ply(ply), game.is_unchecked_ply(ply));
+        }
+
+        // Playing on from an earlier ply drops the unchecked moves after it.
+        game.previous_move();
+        game.previous_move();
+        game.previous_move();
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::b1(), Position::c3()))
+            .unwrap();
+        assert!(game.is_unchecked_ply(2));
+        assert_eq!(game.fen_at(4), None);
+        assert!(game.board().check_consistency().is_ok());
+    }
+
+    #[test]
+    fn test_move_errors() {
+        let mut game = Game::from_san_moves(&["e4", "e5"]).unwrap();
+
+        assert_eq!(
+            game.attempt_move(MoveRequest::new(Position::e3(), Position::e4()))
+                .unwrap_err(),
+            MoveError::EmptySquare {
+                position: Position::e3()
+            }
+        );
+
+        let error = game
+            .attempt_move(MoveRequest::new(Position::d7(), Position::d5()))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            MoveError::NotYourPiece {
+                position: Position::d7(),
+                piece: Piece::new(PieceType::Pawn, Side::Black)
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "The pawn on d7 belongs to Black, who is not to move."
+        );
+
+        // Black's pawn is the right piece for the viewed position, but that isn't the latest one.
+        game.previous_move();
+        assert_eq!(
+            game.attempt_move(MoveRequest::new(Position::d7(), Position::d5()))
+                .unwrap_err(),
+            MoveError::NotLatestPosition
+        );
+        assert_eq!(game.fen_at(2), Some(crate::fixtures::SCHOLARS_MATE.fens[2]));
+
+        game.set_allow_divergence(true);
+        game.attempt_move(MoveRequest::new(Position::d7(), Position::d5()))
+            .unwrap();
+        assert_eq!(game.format_move_list(0), "1.  e4 >d5");
+    }
+
+    #[test]
+    fn test_checks_given() {
+        let mut game = Game::from_san_moves(&[
+            "e4", "e5", "Qh5", "Nc6", "Qxf7+", "Kxf7", "Bc4+", "d5", "Bxd5+", "Qxd5", "Nf3",
+            "Qxe4+",
+        ])
+        .unwrap();
+        assert_eq!(game.checks_given(Side::White), 3);
+        assert_eq!(game.checks_given(Side::Black), 1);
+
+        // Stepping back before a check takes it off the count again.
+        game.previous_move();
+        assert_eq!(game.checks_given(Side::White), 3);
+        assert_eq!(game.checks_given(Side::Black), 0);
+        for _ in 0..5 {
+            game.previous_move();
+        }
+        assert_eq!(game.checks_given(Side::White), 1);
+        assert!(game.next_move());
+        assert_eq!(game.checks_given(Side::White), 2);
+
+        // Replacing the rest of the game drops its checks too.
+        game.set_allow_divergence(true);
+        let request = MoveRequest::from_san(game.board(), "Ke7").unwrap();
+        game.attempt_move(request).unwrap();
+        assert_eq!(game.checks_given(Side::White), 2);
+        assert_eq!(game.checks_given(Side::Black), 0);
+        assert!(!game.next_move());
+
+        // A check in the starting position wasn't given by anyone in this game.
+        let board = fen::parse("4k3/8/8/8/8/8/8/K3R3 b - - 0 1").unwrap();
+        let game = Game::new(board);
+        assert_eq!(game.checks_given(Side::White), 0);
+    }
+
+    #[test]
+    fn test_navigating_a_repeated_game() {
+        let fixture = &crate::fixtures::REPETITION;
+        let mut game = crate::fixtures::play(fixture.name);
+        let last_ply = fixture.moves.len();
+        assert_eq!(game.get_move_state(), MoveState::ThreefoldRepetition);
+
+        // Earlier plies report what they were when played, whatever happened later.
+        let expected_counts = [1, 1, 1, 1, 2, 2, 2, 2, 3];
+        for ply in (0..last_ply).rev() {
+            assert!(game.previous_move());
+            assert_eq!(
+                game.get_repetition_count(),
+                expected_counts[ply],
+                "ply {ply}"
+            );
+            assert_eq!(game.get_move_state(), MoveState::CanMove, "ply {ply}");
+        }
+        assert_eq!(game.get_result(), GameResult::Draw);
+
+        // Diverging drops the claim along with the moves after it.
+        let request = MoveRequest::new(Position::e2(), Position::e4());
+        assert_eq!(
+            game.attempt_move(request.clone()).unwrap_err(),
+            MoveError::NotLatestPosition
+        );
+        game.set_allow_divergence(true);
+        game.attempt_move(request).unwrap();
+        assert_eq!(game.get_result(), GameResult::Ongoing);
+        assert_eq!(game.get_move_state(), MoveState::CanMove);
+        assert_eq!(game.fen_at(2), None);
+
+        // A repetition that could have been claimed when it was reached can't be claimed once
+        // the game has moved on from it.
+        let mut moves = fixture.moves.to_vec();
+        moves.push("e4");
+        let mut game = Game::from_san_moves(&moves).unwrap();
+        assert!(game.previous_move());
+        assert_eq!(game.get_repetition_count(), 3);
+        assert!(!game.can_claim_draw());
+        assert!(!game.claim_draw());
+        assert!(game.next_move());
+        assert_eq!(game.get_result(), GameResult::Ongoing);
     }
 }
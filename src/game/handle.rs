@@ -0,0 +1,247 @@
+// Runs a Game on its own thread so several threads can drive it without sharing a lock. Every
+// call is a command sent over a channel, so commands are applied one at a time in the order
+// they arrive and each caller gets the response to its own command.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::board::{MoveError, MoveInfo, MoveRequest};
+
+use super::{Game, GameSnapshot};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HandleError {
+    Closed,   // the game was shut down
+    Poisoned, // the game thread panicked, so the game is gone
+}
+
+impl std::fmt::Display for HandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandleError::Closed => write!(f, "The game has been shut down."),
+            HandleError::Poisoned => write!(f, "The game thread panicked."),
+        }
+    }
+}
+
+enum Command {
+    MakeMove(MoveRequest, Sender<Result<MoveInfo, MoveError>>),
+    LegalMoves(Sender<Vec<MoveRequest>>),
+    Status(Sender<GameSnapshot>),
+    Undo(Sender<bool>),
+    Shutdown,
+    #[cfg(test)]
+    Panic,
+}
+
+#[derive(Clone)]
+pub struct GameHandle {
+    commands: Sender<Command>,
+    poisoned: Arc<AtomicBool>,
+}
+
+// Starts the game thread. Joining the thread gives the game back once the handle asks it to shut
+// down or every handle has been dropped.
+pub fn spawn(game: Game) -> (GameHandle, JoinHandle<Game>) {
+    let (commands, receiver) = mpsc::channel();
+    let poisoned = Arc::new(AtomicBool::new(false));
+
+    let thread_poisoned = Arc::clone(&poisoned);
+    let join_handle = thread::spawn(move || {
+        // The guard is dropped before the receiver, so no caller sees the channel close without
+        // also seeing the poisoned flag.
+        let receiver = receiver;
+        let _guard = PoisonGuard(thread_poisoned);
+        run(game, &receiver)
+    });
+
+    (GameHandle { commands, poisoned }, join_handle)
+}
+
+fn run(mut game: Game, commands: &Receiver<Command>) -> Game {
+    // A caller that stopped waiting for its response is no reason to stop the game, so failed
+    // replies are ignored.
+    for command in commands.iter() {
+        match command {
+            Command::MakeMove(request, reply) => {
+                let _ = reply.send(game.attempt_move(request));
+            }
+            Command::LegalMoves(reply) => {
+                let requests = game.legal_move_requests();
+                let _ = reply.send(requests.into_iter().map(|(request, _)| request).collect());
+            }
+            Command::Status(reply) => {
+                let _ = reply.send(game.snapshot());
+            }
+            Command::Undo(reply) => {
+                let last_mover = game.board().side_to_move().opponent();
+                let _ = reply.send(game.take_back(last_mover));
+            }
+            Command::Shutdown => break,
+            #[cfg(test)]
+            Command::Panic => panic!("Panicking on request."),
+        }
+    }
+
+    game
+}
+
+// Marks the handles as poisoned if the game thread unwinds.
+struct PoisonGuard(Arc<AtomicBool>);
+
+impl Drop for PoisonGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl GameHandle {
+    pub fn make_move(
+        &self,
+        request: MoveRequest,
+    ) -> Result<Result<MoveInfo, MoveError>, HandleError> {
+        self.call(|reply| Command::MakeMove(request, reply))
+    }
+
+    pub fn legal_moves(&self) -> Result<Vec<MoveRequest>, HandleError> {
+        self.call(Command::LegalMoves)
+    }
+
+    pub fn status(&self) -> Result<GameSnapshot, HandleError> {
+        self.call(Command::Status)
+    }
+
+    // Takes back the last move, following the game's takeback policy.
+    pub fn undo(&self) -> Result<bool, HandleError> {
+        self.call(Command::Undo)
+    }
+
+    // Commands already sent by other handles are still answered before the thread stops.
+    pub fn shutdown(&self) -> Result<(), HandleError> {
+        self.commands
+            .send(Command::Shutdown)
+            .map_err(|_| self.closed_error())
+    }
+
+    fn call<T>(&self, command: impl FnOnce(Sender<T>) -> Command) -> Result<T, HandleError> {
+        let (reply, response) = mpsc::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| self.closed_error())?;
+
+        response.recv().map_err(|_| self.closed_error())
+    }
+
+    fn closed_error(&self) -> HandleError {
+        if self.poisoned.load(Ordering::SeqCst) {
+            HandleError::Poisoned
+        } else {
+            HandleError::Closed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{position::Position, Board},
+        fen,
+        game::GameResult,
+    };
+
+    #[test]
+    fn make_move_and_undo_test() {
+        let (handle, join_handle) = spawn(Game::new(Board::default()));
+
+        let request = MoveRequest::new(Position::e2(), Position::e4());
+        assert!(handle.make_move(request).unwrap().is_ok());
+        let request = MoveRequest::new(Position::e2(), Position::e4());
+        assert!(handle.make_move(request).unwrap().is_err());
+        assert_eq!(handle.status().unwrap().ply, 1);
+        assert_eq!(handle.legal_moves().unwrap().len(), 20);
+
+        assert_eq!(handle.undo(), Ok(true));
+        assert_eq!(handle.undo(), Ok(false));
+        assert_eq!(handle.status().unwrap().ply, 0);
+
+        handle.shutdown().unwrap();
+        let game = join_handle.join().unwrap();
+        assert_eq!(game.board(), &Board::default());
+        assert_eq!(handle.status(), Err(HandleError::Closed));
+    }
+
+    #[test]
+    fn concurrent_commands_test() {
+        let (handle, join_handle) = spawn(Game::new(Board::default()));
+
+        // One thread plays a game while another keeps reading it. Each status the reader sees
+        // has to be a position the game actually passed through, in order, and the legal moves
+        // it gets back must belong to the position it saw just before.
+        let reader = {
+            let handle = handle.clone();
+            thread::spawn(move || {
+                let mut last_ply = 0;
+                loop {
+                    let status = handle.status().unwrap();
+                    assert!(status.ply >= last_ply);
+                    last_ply = status.ply;
+
+                    let legal_moves = handle.legal_moves().unwrap();
+                    if handle.status().unwrap() == status {
+                        let board = fen::parse(&status.fen).unwrap();
+                        let game = Game::new(board);
+                        assert_eq!(legal_moves.len(), game.legal_move_requests().len());
+                    }
+
+                    if status.result != GameResult::Ongoing {
+                        return last_ply;
+                    }
+                }
+            })
+        };
+
+        let player = {
+            let handle = handle.clone();
+            thread::spawn(move || {
+                for (ply, _) in (0..).zip(crate::fixtures::SCHOLARS_MATE.moves) {
+                    let status = handle.status().unwrap();
+                    assert_eq!(status.ply, ply);
+                    assert_eq!(status.fen, crate::fixtures::SCHOLARS_MATE.fens[ply]);
+
+                    let request = handle.legal_moves().unwrap();
+                    let board = fen::parse(&status.fen).unwrap();
+                    let san = crate::fixtures::SCHOLARS_MATE.moves[ply];
+                    let expected = MoveRequest::from_san(&board, san).unwrap();
+                    assert!(request.contains(&expected));
+                    handle.make_move(expected).unwrap().unwrap();
+                }
+            })
+        };
+
+        player.join().unwrap();
+        assert_eq!(reader.join().unwrap(), 7);
+
+        handle.shutdown().unwrap();
+        let game = join_handle.join().unwrap();
+        assert_eq!(game.get_result(), GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn poisoned_test() {
+        let (handle, join_handle) = spawn(Game::new(Board::default()));
+        handle.commands.send(Command::Panic).unwrap();
+        assert!(join_handle.join().is_err());
+
+        assert_eq!(handle.status(), Err(HandleError::Poisoned));
+        assert_eq!(handle.shutdown(), Err(HandleError::Poisoned));
+    }
+}
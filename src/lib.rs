@@ -1,11 +1,24 @@
+pub mod analysis;
+pub mod annotation;
 pub mod board;
+pub mod endgame;
+pub mod eval;
 pub mod fen;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixtures;
 pub mod game;
+pub mod notation;
 pub mod piece;
+pub mod prelude;
+pub mod stats;
+pub mod training;
 
-use board::{Board, MoveRequest, MoveState};
-use game::Game;
-use piece::Side;
+pub use board::{Board, MoveRequest};
+pub use game::Game;
+pub use piece::Side;
+
+use board::MoveState;
+use game::{GameResult, TerminationReason};
 
 #[derive(Debug)]
 pub struct ParseError(String);
@@ -22,13 +35,16 @@ impl std::fmt::Display for ParseError {
     }
 }
 
+const MOVE_LIST_WIDTH: usize = 7;
+
 pub mod game_options {
     pub const MOVE_OPTION: &str = "1";
     pub const PREVIOUS_OPTION: &str = "2";
     pub const NEXT_OPTION: &str = "3";
     pub const DRAW_OPTION: &str = "4";
-    pub const RESIGN_OPTION: &str = "5";
-    pub const QUIT_OPTION: &str = "6";
+    pub const CLAIM_DRAW_OPTION: &str = "5";
+    pub const RESIGN_OPTION: &str = "6";
+    pub const QUIT_OPTION: &str = "7";
 }
 
 pub mod post_game_options {
@@ -44,37 +60,46 @@ pub fn run() {
 
     let mut keep_going = true;
     while keep_going {
-        let black_score = game.get_black_score();
-        let white_score = game.get_white_score();
+        println!("{}", game.board());
 
-        if black_score > white_score {
-            let relative_score = black_score - white_score;
-            println!("+{relative_score}");
+        let imbalance = analysis::imbalance(game.board());
+        if !imbalance.raw.is_empty() {
+            println!("{imbalance}");
         }
 
-        println!("{}", game.get_board());
+        println!();
 
-        if white_score > black_score {
-            let relative_score = white_score - black_score;
-            println!("+{relative_score}");
+        let move_list = game.format_move_list(MOVE_LIST_WIDTH);
+        if !move_list.is_empty() {
+            println!("{move_list}\n");
         }
 
-        println!();
-
         let move_state = game.get_move_state();
 
         let mut game_over = false;
         match move_state {
-            MoveState::CanMove | MoveState::Check => {
-                println!(concat!(
-                    "Select one of the following options:\n",
-                    "1) Move\n",
-                    "2) Previous\n",
-                    "3) Next\n",
-                    "4) Offer Draw\n",
-                    "5) Resign\n",
-                    "6) Quit\n"
-                ));
+            MoveState::CanMove | MoveState::Check if game.get_result() == GameResult::Ongoing => {
+                let current_turn = game.board().side_to_move();
+                let draw_offered = game.pending_draw_offer() == Some(current_turn.opponent());
+                let draw_option = if draw_offered {
+                    "Accept Draw"
+                } else {
+                    "Offer Draw"
+                };
+
+                println!(
+                    concat!(
+                        "Select one of the following options:\n",
+                        "1) Move\n",
+                        "2) Previous\n",
+                        "3) Next\n",
+                        "4) {}\n",
+                        "5) Claim Draw\n",
+                        "6) Resign\n",
+                        "7) Quit\n"
+                    ),
+                    draw_option
+                );
 
                 println!("Enter choice: ");
 
@@ -99,7 +124,11 @@ pub fn run() {
                         if let Ok(request) = MoveRequest::from_coordinate(coordinates) {
                             match game.attempt_move(request) {
                                 Ok(move_info) => {
-                                    println!("\nMove: {}", move_info.to_notation());
+                                    println!(
+                                        "\nMove: {} ({})",
+                                        move_info.to_notation(),
+                                        move_info.describe()
+                                    );
                                 }
                                 Err(error) => println!("Move Error: {}", error),
                             }
@@ -114,32 +143,34 @@ pub fn run() {
                         game.next_move();
                     }
                     game_options::DRAW_OPTION => {
-                        println!("Your opponent has offered a draw, do you accept (Y/n):");
-
-                        let mut response = String::new();
-                        std::io::stdin()
-                            .read_line(&mut response)
-                            .expect("Failed to read stdin.");
-                        let response = response.to_lowercase();
-                        let response = response.trim();
-
-                        match response {
-                            "y" => {
-                                println!("Your opponent has accepted the draw, game over.\n");
+                        if draw_offered {
+                            if game.accept_draw() {
+                                println!("The draw has been accepted, game over.\n");
                                 game_over = true;
                             }
-
-                            "n" => {
-                                println!("Your opponent has rejected the draw.\n");
-                            }
-
-                            _ => (),
+                        } else if game.offer_draw(current_turn) {
+                            println!("Draw offered, it stands until your opponent moves.\n");
+                        } else {
+                            println!("A draw can't be offered right now.\n");
+                        }
+                    }
+                    game_options::CLAIM_DRAW_OPTION => {
+                        if game.claim_draw() {
+                            println!("Draw claimed.\n");
+                        } else {
+                            println!("There is no draw to claim.\n");
                         }
                     }
                     game_options::RESIGN_OPTION => {
-                        let winning_side = match game.get_board().get_current_turn() {
-                            Side::White => "black",
-                            Side::Black => "white",
+                        let winning_side = game.board().side_to_move().opponent();
+                        game.set_result(
+                            GameResult::win_for(&winning_side),
+                            TerminationReason::Resignation,
+                        );
+
+                        let winning_side = match winning_side {
+                            Side::White => "white",
+                            Side::Black => "black",
                         };
                         println!("Player resigned, {winning_side} won!\n");
 
@@ -149,17 +180,47 @@ pub fn run() {
                     _ => (),
                 }
             }
+            MoveState::CanMove | MoveState::Check => {
+                println!("The game has ended: {}\n", game.get_result().to_pgn_str());
+
+                game_over = true;
+            }
             MoveState::Stalemate => {
-                println!("The game has ended in a stalemate.\n");
+                println!(
+                    "The game has ended in a stalemate, {} has no legal moves and is not in check.\n",
+                    match game.board().side_to_move() {
+                        Side::White => "white",
+                        Side::Black => "black",
+                    }
+                );
 
                 game_over = true;
             }
             MoveState::Checkmate => {
-                let winning_side = match game.get_board().get_current_turn() {
+                let winning_side = match game.board().side_to_move() {
                     Side::White => "black",
                     Side::Black => "white",
                 };
-                println!("Checkmate, {winning_side} won!\n");
+                println!("Checkmate, {winning_side} won!");
+                if let Some(details) = board::checkmate_details(game.board()) {
+                    println!("The king was {details}.");
+                }
+                println!();
+
+                game_over = true;
+            }
+            MoveState::FiftyMoveRule | MoveState::SeventyFiveMoveRule => {
+                println!("The game has ended in a draw by the move rule.\n");
+
+                game_over = true;
+            }
+            MoveState::ThreefoldRepetition | MoveState::FivefoldRepetition => {
+                println!("The game has ended in a draw by repetition.\n");
+
+                game_over = true;
+            }
+            MoveState::Invalid => {
+                println!("The position is missing a king, so the game can't be played.\n");
 
                 game_over = true;
             }
@@ -200,13 +261,13 @@ pub fn run() {
 }
 
 pub fn perform_moves(game: &mut Game, move_requests: Vec<MoveRequest>) {
-    println!("{}\n", game.get_board());
+    println!("{}\n", game.board());
 
     for request in move_requests {
         match game.attempt_move(request) {
             Ok(_) => {
-                println!("{}\n", game.get_board());
-                println!("{:?}\n", board::get_move_state(game.get_board()));
+                println!("{}\n", game.board());
+                println!("{}\n", board::get_move_state(game.board()));
             }
             Err(error) => {
                 println!("{error:?}");
@@ -0,0 +1,282 @@
+use crate::{
+    board::{self, position::Position, Board, MoveInfo, MoveRequest},
+    piece::PromotionType,
+    ParseError,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Notation {
+    // Coordinates only, e.g. e2e4 or e7e8q.
+    Uci,
+    // Standard algebraic, e.g. Nf3 or exd5.
+    San,
+    // Long algebraic, e.g. Ng1-f3 or e4xd5.
+    Lan,
+    // Numeric correspondence notation, e.g. 5254 or 57581.
+    Iccf,
+    // Standard algebraic with piece figurines, e.g. ♘f3.
+    Figurine,
+}
+
+const FIGURINES: [(char, char); 5] = [('K', '♔'), ('Q', '♕'), ('R', '♖'), ('B', '♗'), ('N', '♘')];
+
+const BLACK_FIGURINES: [(char, char); 5] =
+    [('K', '♚'), ('Q', '♛'), ('R', '♜'), ('B', '♝'), ('N', '♞')];
+
+pub fn convert(
+    board: &Board,
+    input: &str,
+    from: Notation,
+    to: Notation,
+) -> Result<String, ParseError> {
+    let request = parse_move(board, input, from)?;
+    render_move(board, &request, to)
+}
+
+// Converts a space separated line of moves, playing each one so later moves are read and
+// written in the right context. Move numbers such as "1." are skipped.
+pub fn convert_line(
+    board: &Board,
+    moves: &str,
+    from: Notation,
+    to: Notation,
+) -> Result<String, ParseError> {
+    let mut board = board.clone();
+    let mut converted = Vec::new();
+
+    let moves = moves
+        .split_whitespace()
+        .filter(|token| !is_move_number(token));
+    for (index, input) in moves.enumerate() {
+        let invalid_move = |error: &dyn std::fmt::Display| {
+            ParseError::new(&format!("Move {index} ({input}): {error}"))
+        };
+
+        let request = parse_move(&board, input, from).map_err(|e| invalid_move(&e))?;
+        converted.push(render_move(&board, &request, to).map_err(|e| invalid_move(&e))?);
+        board::move_piece(&mut board, request).map_err(|e| invalid_move(&e))?;
+    }
+
+    Ok(converted.join(" "))
+}
+
+pub fn parse_move(board: &Board, input: &str, from: Notation) -> Result<MoveRequest, ParseError> {
+    match from {
+        Notation::Uci => MoveRequest::from_coordinate(input.trim()),
+        Notation::San => MoveRequest::from_san(board, input),
+        Notation::Lan => MoveRequest::from_lan(board, input),
+        Notation::Iccf => parse_iccf(input),
+        Notation::Figurine => {
+            let san: String = input
+                .chars()
+                .map(|notation| {
+                    FIGURINES
+                        .iter()
+                        .chain(BLACK_FIGURINES.iter())
+                        .find(|(_, figurine)| *figurine == notation)
+                        .map_or(notation, |(letter, _)| *letter)
+                })
+                .collect();
+            MoveRequest::from_san(board, &san)
+        }
+    }
+}
+
+pub fn render_move(
+    board: &Board,
+    request: &MoveRequest,
+    to: Notation,
+) -> Result<String, ParseError> {
+    let move_info = played_move_info(board, request)?;
+
+    let notation = match to {
+        Notation::Uci => request.to_coordinate(),
+        Notation::San => move_info.to_notation(),
+        Notation::Lan => move_info.to_lan(),
+        Notation::Iccf => {
+            let promotion = request.promotion.as_ref().map(|promotion| match promotion {
+                PromotionType::Queen => '1',
+                PromotionType::Rook => '2',
+                PromotionType::Bishop => '3',
+                PromotionType::Knight => '4',
+            });
+            format!(
+                "{}{}{}{}{}",
+                request.start.file() + 1,
+                request.start.rank() + 1,
+                request.end.file() + 1,
+                request.end.rank() + 1,
+                promotion.map(String::from).unwrap_or_default()
+            )
+        }
+        Notation::Figurine => move_info
+            .to_notation()
+            .chars()
+            .map(|notation| {
+                FIGURINES
+                    .iter()
+                    .find(|(letter, _)| *letter == notation)
+                    .map_or(notation, |(_, figurine)| *figurine)
+            })
+            .collect(),
+    };
+
+    Ok(notation)
+}
+
+// Plays the move on a copy of the board to fill in everything the notations might need.
+fn played_move_info(board: &Board, request: &MoveRequest) -> Result<MoveInfo, ParseError> {
+    let all_legal_moves = board::get_all_legal_moves(board, &board.side_to_move());
+    let is_legal = all_legal_moves
+        .get(&request.start)
+        .is_some_and(|moves| moves.contains_key(&request.end));
+    if !is_legal {
+        return Err(ParseError::new("Illegal move."));
+    }
+
+    let (file_disambiguation, rank_disambiguation) =
+        board::get_disambiguation(board, &all_legal_moves, request);
+
+    let mut new_board = board.clone_for_simulation();
+    let mut move_info = board::move_piece(&mut new_board, request.clone())
+        .map_err(|error| ParseError::new(&error.to_string()))?;
+    move_info.file_disambiguation = file_disambiguation;
+    move_info.rank_disambiguation = rank_disambiguation;
+    move_info.move_state = Some(board::get_move_state(&new_board));
+
+    Ok(move_info)
+}
+
+fn parse_iccf(input: &str) -> Result<MoveRequest, ParseError> {
+    let digits: Vec<usize> = input
+        .trim()
+        .chars()
+        .map(|digit| digit.to_digit(10).map(|digit| digit as usize))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or(ParseError::new("ICCF notation only contains digits."))?;
+
+    if digits.len() != 4 && digits.len() != 5 {
+        return Err(ParseError::new("Expected four or five digits."));
+    }
+
+    // ICCF counts files and ranks from 1.
+    let square = |file: usize, rank: usize| match (file.checked_sub(1), rank.checked_sub(1)) {
+        (Some(file), Some(rank)) => {
+            Position::try_new(file, rank).ok_or(ParseError::new("Invalid square."))
+        }
+        _ => Err(ParseError::new("Invalid square.")),
+    };
+    let start = square(digits[0], digits[1])?;
+    let end = square(digits[2], digits[3])?;
+
+    let promotion = match digits.get(4) {
+        None => None,
+        Some(1) => Some(PromotionType::Queen),
+        Some(2) => Some(PromotionType::Rook),
+        Some(3) => Some(PromotionType::Bishop),
+        Some(4) => Some(PromotionType::Knight),
+        Some(_) => return Err(ParseError::new("Invalid promotion notation.")),
+    };
+
+    Ok(MoveRequest {
+        start,
+        end,
+        promotion,
+    })
+}
+
+fn is_move_number(token: &str) -> bool {
+    token.ends_with('.')
+        && token
+            .trim_end_matches('.')
+            .chars()
+            .all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+
+    const UCI_GAME: &str =
+        "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5 d7d5 e4d5 f6d5 g5f7 e8f7 d1f3 f7e6 b1c3 c6b4 e1g1";
+    const SAN_GAME: &str = "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Nxd5 Nxf7 Kxf7 Qf3+ Ke6 Nc3 Nb4 O-O";
+
+    #[test]
+    fn convert_line_test() -> Result<(), ParseError> {
+        let board = Board::default();
+
+        assert_eq!(
+            convert_line(&board, UCI_GAME, Notation::Uci, Notation::San)?,
+            SAN_GAME
+        );
+        assert_eq!(
+            convert_line(&board, SAN_GAME, Notation::San, Notation::Uci)?,
+            UCI_GAME
+        );
+        assert_eq!(
+            convert_line(&board, "1. e4 e5 2. Nf3", Notation::San, Notation::Uci)?,
+            "e2e4 e7e5 g1f3"
+        );
+
+        let lan = convert_line(&board, SAN_GAME, Notation::San, Notation::Lan)?;
+        assert_eq!(
+            lan,
+            "e2-e4 e7-e5 Ng1-f3 Nb8-c6 Bf1-c4 Ng8-f6 Nf3-g5 d7-d5 e4xd5 Nf6xd5 Ng5xf7 Ke8xf7 Qd1-f3+ Kf7-e6 Nb1-c3 Nc6-b4 O-O"
+        );
+        assert_eq!(
+            convert_line(&board, &lan, Notation::Lan, Notation::San)?,
+            SAN_GAME
+        );
+
+        let iccf = convert_line(&board, UCI_GAME, Notation::Uci, Notation::Iccf)?;
+        assert!(iccf.starts_with("5254 5755 7163"));
+        assert!(iccf.ends_with("5171"));
+        assert_eq!(
+            convert_line(&board, &iccf, Notation::Iccf, Notation::Uci)?,
+            UCI_GAME
+        );
+
+        let error = convert_line(&board, "e4 e5 Ke3", Notation::San, Notation::Uci).unwrap_err();
+        assert!(error.to_string().starts_with("Move 2 (Ke3)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn convert_test() -> Result<(), ParseError> {
+        let board = fen::parse("4k3/1P6/8/8/8/8/8/R3K1N1 w Q - 0 1")?;
+
+        assert_eq!(
+            convert(&board, "g1f3", Notation::Uci, Notation::Figurine)?,
+            "♘f3"
+        );
+        assert_eq!(
+            convert(&board, "♘f3", Notation::Figurine, Notation::Uci)?,
+            "g1f3"
+        );
+        assert_eq!(
+            convert(&board, "b7b8q", Notation::Uci, Notation::San)?,
+            "b8=Q+"
+        );
+        assert_eq!(
+            convert(&board, "b8=N", Notation::San, Notation::Iccf)?,
+            "27284"
+        );
+        assert_eq!(
+            convert(&board, "b7-b8=R+", Notation::Lan, Notation::Figurine)?,
+            "b8=♖+"
+        );
+        assert_eq!(
+            convert(&board, "O-O-O", Notation::Lan, Notation::Uci)?,
+            "e1c1"
+        );
+
+        assert!(convert(&board, "e1e3", Notation::Uci, Notation::San).is_err());
+        assert!(convert(&board, "Ra1-a3", Notation::Lan, Notation::San).is_ok());
+        assert!(convert(&board, "Qa1-a3", Notation::Lan, Notation::San).is_err());
+        assert!(convert(&board, "9254", Notation::Iccf, Notation::San).is_err());
+
+        Ok(())
+    }
+}
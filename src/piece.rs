@@ -1,3 +1,5 @@
+use crate::board::{position::Offset, rank};
+
 #[macro_export]
 macro_rules! piece {
     ( $piece_type:ident, $side:ident ) => {
@@ -5,7 +7,7 @@ macro_rules! piece {
     };
 }
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub enum PieceType {
     Pawn,
     Knight,
@@ -26,9 +28,20 @@ impl PieceType {
             PieceType::King => 0,
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PieceType::Pawn => "pawn",
+            PieceType::Knight => "knight",
+            PieceType::Bishop => "bishop",
+            PieceType::Rook => "rook",
+            PieceType::Queen => "queen",
+            PieceType::King => "king",
+        }
+    }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum PromotionType {
     Knight,
     Bishop,
@@ -66,7 +79,7 @@ impl PromotionType {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
 pub enum Side {
     White = 0,
     Black = 1,
@@ -87,6 +100,27 @@ impl Side {
             Side::Black => Side::White,
         }
     }
+
+    // The rank this side's pawns promote on.
+    pub fn promotion_rank(&self) -> usize {
+        match self {
+            Side::White => rank::EIGHT,
+            Side::Black => rank::ONE,
+        }
+    }
+
+    // The rank this side's pawns start on and can double move from.
+    pub fn pawn_start_rank(&self) -> usize {
+        match self {
+            Side::White => rank::TWO,
+            Side::Black => rank::SEVEN,
+        }
+    }
+
+    // The direction this side's pawns move in.
+    pub fn pawn_direction(&self) -> Offset {
+        Offset::NORTH.for_side(self)
+    }
 }
 
 impl std::fmt::Display for Side {
@@ -100,7 +134,7 @@ impl std::fmt::Display for Side {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub struct Piece {
     pub piece_type: PieceType,
     pub side: Side,
@@ -0,0 +1,12 @@
+// The types needed to set up and play a game, so `use chess::prelude::*` is enough for most
+// callers instead of knowing which module each one lives in.
+
+pub use crate::{
+    board::{
+        position::Position, Board, BoardError, CastleRights, MoveError, MoveInfo, MoveKind,
+        MoveRequest, MoveState,
+    },
+    game::{Game, GameResult, LoadError},
+    piece::{Piece, PieceType, PromotionType, Side},
+    ParseError,
+};
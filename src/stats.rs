@@ -0,0 +1,143 @@
+// Counters for performance debugging, only collected when the "stats" feature is enabled.
+// Without it the counting functions are empty and inline away, and snapshot() is always zero.
+
+#[cfg(feature = "stats")]
+use std::cell::Cell;
+
+pub const ENABLED: bool = cfg!(feature = "stats");
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Snapshot {
+    pub piece_move_generations: u64,
+    pub board_clones: u64,
+    pub legality_simulations: u64,
+    pub legal_move_generations: u64, // full legal move lists for a side
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Piece move generations: {}", self.piece_move_generations)?;
+        writeln!(f, "Board clones:           {}", self.board_clones)?;
+        writeln!(f, "Legality simulations:   {}", self.legality_simulations)?;
+        write!(f, "Legal move generations: {}", self.legal_move_generations)
+    }
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    static COUNTERS: Cell<Snapshot> = Cell::new(Snapshot::default());
+}
+
+#[cfg(feature = "stats")]
+fn update(count: impl FnOnce(&mut Snapshot)) {
+    COUNTERS.with(|counters| {
+        let mut snapshot = counters.get();
+        count(&mut snapshot);
+        counters.set(snapshot);
+    });
+}
+
+// The counts so far on the current thread.
+pub fn snapshot() -> Snapshot {
+    #[cfg(feature = "stats")]
+    return COUNTERS.with(Cell::get);
+
+    #[cfg(not(feature = "stats"))]
+    Snapshot::default()
+}
+
+pub fn reset() {
+    #[cfg(feature = "stats")]
+    COUNTERS.with(|counters| counters.set(Snapshot::default()));
+}
+
+#[inline]
+pub(crate) fn count_piece_move_generation() {
+    #[cfg(feature = "stats")]
+    update(|snapshot| snapshot.piece_move_generations += 1);
+}
+
+#[inline]
+pub(crate) fn count_board_clone() {
+    #[cfg(feature = "stats")]
+    update(|snapshot| snapshot.board_clones += 1);
+}
+
+#[inline]
+pub(crate) fn count_legality_simulation() {
+    #[cfg(feature = "stats")]
+    update(|snapshot| snapshot.legality_simulations += 1);
+}
+
+#[inline]
+pub(crate) fn count_legal_move_generation() {
+    #[cfg(feature = "stats")]
+    update(|snapshot| snapshot.legal_move_generations += 1);
+}
+
+// Runs `f` without its work showing up in the counters, for checks that only debug builds make.
+pub(crate) fn uncounted<T>(f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "stats")]
+    {
+        let before = snapshot();
+        let result = f();
+        COUNTERS.with(|counters| counters.set(before));
+        result
+    }
+
+    #[cfg(not(feature = "stats"))]
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{self, Board},
+        fen,
+    };
+
+    #[test]
+    fn perft_counters_test() {
+        // Castling is checked by playing it out, so simulations are counted in every build.
+        let board = fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        reset();
+        board::perft(&board, 2);
+        let after_perft = snapshot();
+
+        if ENABLED {
+            assert!(after_perft.piece_move_generations > 0);
+            assert!(after_perft.board_clones > 0);
+            assert!(after_perft.legality_simulations > 0);
+            assert!(after_perft.legal_move_generations > 0);
+
+            // Counting only ever goes up until a reset.
+            board::perft(&Board::startpos(), 1);
+            let after_second_perft = snapshot();
+            assert!(after_second_perft.piece_move_generations > after_perft.piece_move_generations);
+            assert!(after_second_perft.board_clones > after_perft.board_clones);
+        } else {
+            assert_eq!(after_perft, Snapshot::default());
+        }
+
+        reset();
+        assert_eq!(snapshot(), Snapshot::default());
+
+        // Nothing at the start needs simulating, and the debug build cross-check isn't counted.
+        board::perft(&Board::startpos(), 1);
+        assert_eq!(snapshot().legality_simulations, 0);
+    }
+
+    #[test]
+    fn disabled_counters_are_free_test() {
+        // Nothing is stored per thread unless the feature asks for it.
+        assert_eq!(ENABLED, cfg!(feature = "stats"));
+        if !ENABLED {
+            count_piece_move_generation();
+            count_board_clone();
+            count_legality_simulation();
+            count_legal_move_generation();
+            assert_eq!(snapshot(), Snapshot::default());
+        }
+    }
+}
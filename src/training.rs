@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use crate::{
+    board::{
+        self,
+        geometry::NUM_SQUARES,
+        position::{Offset, Position},
+        Board, CastleRights,
+    },
+    piece::{PieceType, Side},
+};
+
+pub fn random_square(rng_seed: u64) -> Position {
+    // splitmix64, so nearby seeds still give unrelated squares.
+    let mut value = rng_seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^= value >> 31;
+
+    Position::from_index((value % NUM_SQUARES as u64) as usize).unwrap()
+}
+
+// The shortest route a knight can take, excluding the starting square.
+pub fn knight_path(from: &Position, to: &Position) -> Vec<Position> {
+    let board = empty_board();
+
+    let mut previous: [Option<usize>; NUM_SQUARES] = [None; NUM_SQUARES];
+    let mut visited = [false; NUM_SQUARES];
+    visited[from.value()] = true;
+
+    let mut queue = VecDeque::from([*from]);
+    while let Some(position) = queue.pop_front() {
+        if position == *to {
+            break;
+        }
+
+        for next in sorted(
+            board::moves_for_piece_type(&board, &PieceType::Knight, &Side::White, &position)
+                .into_keys(),
+        ) {
+            if !visited[next.value()] {
+                visited[next.value()] = true;
+                previous[next.value()] = Some(position.value());
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut current = to.value();
+    while current != from.value() {
+        path.push(Position::from_index(current).unwrap());
+        current = previous[current].expect("Every square is reachable by a knight.");
+    }
+    path.reverse();
+
+    path
+}
+
+pub fn squares_attacked_from(
+    piece_type: &PieceType,
+    side: &Side,
+    from: &Position,
+) -> Vec<Position> {
+    match piece_type {
+        // Pawns attack diagonally, which never shows up as a move on an empty board.
+        PieceType::Pawn => sorted(
+            [Offset::NORTH_WEST, Offset::NORTH_EAST]
+                .into_iter()
+                .filter_map(|offset| from + offset.for_side(side)),
+        ),
+        _ => {
+            sorted(board::moves_for_piece_type(&empty_board(), piece_type, side, from).into_keys())
+        }
+    }
+}
+
+fn empty_board() -> Board {
+    Board::new(
+        Vec::new(),
+        Side::White,
+        CastleRights::new(false, false, false, false),
+        None,
+        0,
+        1,
+    )
+}
+
+fn sorted(positions: impl Iterator<Item = Position>) -> Vec<Position> {
+    let mut positions: Vec<Position> = positions.collect();
+    positions.sort_by_key(Position::value);
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_square_test() {
+        for seed in 0..100 {
+            assert_eq!(random_square(seed), random_square(seed));
+        }
+
+        let squares: Vec<Position> = (0..8).map(random_square).collect();
+        assert!(squares.iter().any(|square| *square != squares[0]));
+    }
+
+    #[test]
+    fn knight_path_test() {
+        let path = knight_path(&Position::a1(), &Position::h8());
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.last(), Some(&Position::h8()));
+
+        // Each step is a legal knight jump from the previous square.
+        let mut current = Position::a1();
+        for next in &path {
+            let file_distance = current.file().abs_diff(next.file());
+            let rank_distance = current.rank().abs_diff(next.rank());
+            assert!(matches!((file_distance, rank_distance), (1, 2) | (2, 1)));
+            current = *next;
+        }
+
+        assert_eq!(
+            knight_path(&Position::a1(), &Position::b3()),
+            vec![Position::b3()]
+        );
+        assert_eq!(knight_path(&Position::a1(), &Position::b2()).len(), 4);
+        assert_eq!(knight_path(&Position::e4(), &Position::e5()).len(), 3);
+        assert!(knight_path(&Position::e4(), &Position::e4()).is_empty());
+
+        assert_eq!(
+            knight_path(&Position::a1(), &Position::h8()),
+            knight_path(&Position::a1(), &Position::h8())
+        );
+    }
+
+    #[test]
+    fn squares_attacked_from_test() {
+        assert_eq!(
+            squares_attacked_from(&PieceType::Knight, &Side::White, &Position::a1()),
+            vec![Position::c2(), Position::b3()]
+        );
+        assert_eq!(
+            squares_attacked_from(&PieceType::Rook, &Side::White, &Position::d4()).len(),
+            14
+        );
+        assert_eq!(
+            squares_attacked_from(&PieceType::Bishop, &Side::White, &Position::d4()).len(),
+            13
+        );
+        assert_eq!(
+            squares_attacked_from(&PieceType::Queen, &Side::White, &Position::d4()).len(),
+            27
+        );
+        assert_eq!(
+            squares_attacked_from(&PieceType::King, &Side::White, &Position::e1()),
+            vec![
+                Position::d1(),
+                Position::f1(),
+                Position::d2(),
+                Position::e2(),
+                Position::f2()
+            ]
+        );
+        assert_eq!(
+            squares_attacked_from(&PieceType::Pawn, &Side::White, &Position::a2()),
+            vec![Position::b3()]
+        );
+        assert_eq!(
+            squares_attacked_from(&PieceType::Pawn, &Side::Black, &Position::e5()),
+            vec![Position::d4(), Position::f4()]
+        );
+    }
+}
@@ -0,0 +1,23 @@
+// Plays a game through nothing but the prelude, the way a caller outside the crate would.
+
+use chess::prelude::*;
+
+#[test]
+fn game_loop_test() -> Result<(), ParseError> {
+    let mut game = Game::new(Board::default());
+
+    for coordinate in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+        let request = MoveRequest::from_coordinate(coordinate)?;
+        let move_info: Result<MoveInfo, MoveError> = game.attempt_move(request);
+        assert!(move_info.is_ok());
+    }
+
+    assert_eq!(game.get_move_state(), MoveState::Checkmate);
+    assert_eq!(game.get_result(), GameResult::BlackWins);
+    assert_eq!(
+        game.board().piece_at(&Position::H4),
+        Some(&Piece::new(PieceType::Queen, Side::Black))
+    );
+
+    Ok(())
+}